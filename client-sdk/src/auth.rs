@@ -0,0 +1,369 @@
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode},
+    http::api::auth::{
+        AuthChallengeRequest, AuthResponse, AuthSession, KdfParams, SessionInfo, UserAuth,
+    },
+    suite::{DigestAlgorithm, SymmetricCipherAlgorithm},
+};
+use uuid::Uuid;
+
+use crate::{
+    cipher::{
+        kdf, symmetric::DefaultSymmetricCipher, AsymmetricCipherSpi, CipherSuiteError, Result,
+        SymmetricCipherSpi, SymmetricCiphertext,
+    },
+    client::Client,
+    macros::async_trait,
+    storage::Authentication,
+};
+
+/// The cipher that seals the private keys of new users.
+pub const PRIV_KEY_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
+/// The cipher used for [`UserAuth`] without `priv_key_cipher`.
+pub const LEGACY_PRIV_KEY_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
+/// Derives the `key_len` byte key that wraps a user's private key from their master password.
+///
+/// Without `kdf_params`, PBKDF2 is used with [`kdf::PBKDF2_ITERATIONS`] and the user address as the salt.
+pub fn derive_wrapping_key(
+    password: &str,
+    user_address: &str,
+    kdf_base_digest_alg: DigestAlgorithm,
+    kdf_params: Option<&KdfParams>,
+    key_len: usize,
+) -> Result<Bytes> {
+    let mut key = vec![0u8; key_len];
+    match kdf_params {
+        Some(params) => {
+            kdf::derive_key(password.as_bytes(), kdf_base_digest_alg, params, &mut key)?
+        }
+        None => kdf::pbkdf2(
+            password.as_bytes(),
+            user_address.as_bytes(),
+            kdf::PBKDF2_ITERATIONS,
+            kdf_base_digest_alg,
+            &mut key,
+        )?,
+    }
+    Ok(Bytes::new(key))
+}
+
+/// Encrypts `private_key` under `wrapping_key` with `alg`.
+///
+/// Returns the generated IV and the sealed key (with the authentication tag appended).
+pub async fn seal_private_key(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    alg: SymmetricCipherAlgorithm,
+    wrapping_key: &[u8],
+    private_key: &[u8],
+) -> Result<(Bytes, Bytes)> {
+    cipher.init(alg).await?;
+    let iv = cipher.generate_iv().await?;
+    let SymmetricCiphertext {
+        ciphertext,
+        auth_tag,
+    } = cipher.encrypt(wrapping_key, &iv, private_key).await?;
+    let mut sealed = ciphertext.into_inner();
+    sealed.extend_from_slice(&auth_tag);
+    Ok((iv, Bytes::new(sealed)))
+}
+
+/// Decrypts a private key sealed by [`seal_private_key`].
+pub async fn unseal_private_key(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    alg: SymmetricCipherAlgorithm,
+    wrapping_key: &[u8],
+    iv: &[u8],
+    sealed: &[u8],
+) -> Result<Bytes> {
+    cipher.init(alg).await?;
+    let ciphertext_len = sealed
+        .len()
+        .checked_sub(cipher.tag_len()?)
+        .ok_or(CipherSuiteError::DecryptionFailed)?;
+    let (ciphertext, auth_tag) = sealed.split_at(ciphertext_len);
+    cipher.decrypt(wrapping_key, iv, ciphertext, auth_tag).await
+}
+
+/// Derives the wrapping key from `password` and unseals the private key in `auth`.
+pub async fn unseal_user_private_key(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    password: &str,
+    user_address: &str,
+    auth: &UserAuth,
+) -> Result<Bytes> {
+    let alg = auth.priv_key_cipher.unwrap_or(LEGACY_PRIV_KEY_CIPHER);
+    cipher.init(alg).await?;
+    let wrapping_key = derive_wrapping_key(
+        password,
+        user_address,
+        auth.kdf_base_digest_alg,
+        auth.kdf_params.as_ref(),
+        cipher.key_len()?,
+    )?;
+    unseal_private_key(
+        cipher,
+        alg,
+        &wrapping_key,
+        &auth.priv_key_iv,
+        &auth.secured_private_key,
+    )
+    .await
+}
+
+/// Authenticates a user against a passman server with the challenge-response flow.
+///
+/// The user's private key is fetched from the server in its sealed form and unsealed locally with the master password.
+pub struct ClientAuthentication<C> {
+    client: Client,
+    user_id: Uuid,
+    user_address: String,
+    password: String,
+    cipher: C,
+    session: Option<AuthSession>,
+    public_key: Option<Bytes>,
+    private_key: Option<Bytes>,
+}
+
+impl<C> ClientAuthentication<C> {
+    pub fn new(
+        client: Client,
+        user_id: Uuid,
+        user_address: impl Into<String>,
+        password: impl Into<String>,
+        cipher: C,
+    ) -> Self {
+        Self {
+            client,
+            user_id,
+            user_address: user_address.into(),
+            password: password.into(),
+            cipher,
+            session: None,
+            public_key: None,
+            private_key: None,
+        }
+    }
+
+    pub fn user_id(&self) -> Uuid {
+        self.user_id
+    }
+
+    /// The current session, if [`Authentication::authenticate`] succeeded.
+    pub fn session(&self) -> Option<&AuthSession> {
+        self.session.as_ref()
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The token of the current session.
+    pub fn session_token(&self) -> common::error::Result<&Bytes> {
+        self.session
+            .as_ref()
+            .map(|session| &session.session_token)
+            .ok_or_else(|| Error::new(ErrorCode::NotAuthenticated, "not authenticated"))
+    }
+
+    /// Extends the current session, replacing its token.
+    pub async fn refresh(&mut self) -> common::error::Result<()> {
+        let session = self.client.refresh_session(self.session_token()?).await?;
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Ends the current session on the server, and forgets the unsealed private key.
+    pub async fn logout(&mut self) -> common::error::Result<()> {
+        self.client.end_session(self.session_token()?).await?;
+        self.session = None;
+        self.public_key = None;
+        self.private_key = None;
+        Ok(())
+    }
+
+    /// Lists the active sessions of this user, so that sessions on other devices can be revoked.
+    pub async fn sessions(&self) -> common::error::Result<Vec<SessionInfo>> {
+        self.client
+            .sessions(self.user_id, self.session_token()?)
+            .await
+    }
+
+    /// Ends another session of this user.
+    pub async fn revoke_session(&self, session_id: Uuid) -> common::error::Result<()> {
+        self.client
+            .revoke_session(self.user_id, session_id, self.session_token()?)
+            .await
+    }
+}
+
+impl<C: AsymmetricCipherSpi + Send> ClientAuthentication<C> {
+    /// Seals data to this user's public key, such as the key of a new item.
+    pub async fn seal(&mut self, data: &[u8]) -> common::error::Result<Bytes> {
+        let public_key = self
+            .public_key
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorCode::NotAuthenticated, "not authenticated"))?;
+        Ok(self.cipher.seal(public_key, data).await?)
+    }
+
+    /// Unseals data sealed to this user's public key, such as an item key.
+    ///
+    /// The private key is only available after a successful [`Authentication::authenticate`].
+    pub async fn unseal(&mut self, sealed: &[u8]) -> common::error::Result<Bytes> {
+        let private_key = self
+            .private_key
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorCode::NotAuthenticated, "not authenticated"))?;
+        Ok(self.cipher.unseal(private_key, sealed).await?)
+    }
+}
+
+#[async_trait]
+impl<C: AsymmetricCipherSpi + Send + Sync> Authentication for ClientAuthentication<C> {
+    fn is_unlocked(&self) -> bool {
+        self.session.is_some()
+    }
+
+    async fn authenticate(&mut self) -> common::error::Result<()> {
+        let auth = self.client.user_auth(self.user_id).await?;
+
+        let private_key = unseal_user_private_key(
+            &mut DefaultSymmetricCipher::new(),
+            &self.password,
+            &self.user_address,
+            &auth,
+        )
+        .await?;
+
+        let challenge_session_id = Uuid::new_v4();
+        let challenge = self
+            .client
+            .auth_challenge(&AuthChallengeRequest {
+                user_id: self.user_id,
+                challenge_session_id,
+            })
+            .await?;
+
+        self.cipher.init(auth.auth_key_alg).await?;
+        let challenge_signature = self
+            .cipher
+            .sign(
+                &private_key,
+                challenge.challenge_digest,
+                &challenge.challenge_bytes,
+            )
+            .await
+            .map_err(Error::from)?;
+
+        let session = self
+            .client
+            .auth_response(
+                challenge_session_id,
+                &AuthResponse {
+                    challenge_signature,
+                },
+            )
+            .await?;
+
+        self.session = Some(session);
+        self.public_key = Some(auth.pub_key);
+        self.private_key = Some(private_key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::task::block_on;
+    use common::{http::api::auth::KdfFunction, suite::AsymmetricCipherAlgorithm};
+
+    use super::*;
+
+    fn user_auth(priv_key_cipher: Option<SymmetricCipherAlgorithm>) -> UserAuth {
+        let mut kdf_params = kdf::pbkdf2_params();
+        if let KdfFunction::Pbkdf2 { iterations, .. } = &mut kdf_params.function {
+            *iterations = 1000;
+        }
+
+        let mut cipher = DefaultSymmetricCipher::new();
+        let alg = priv_key_cipher.unwrap_or(LEGACY_PRIV_KEY_CIPHER);
+        block_on(cipher.init(alg)).unwrap();
+        let wrapping_key = derive_wrapping_key(
+            "hunter2",
+            "user@example.com",
+            DigestAlgorithm::Sha256,
+            Some(&kdf_params),
+            cipher.key_len().unwrap(),
+        )
+        .unwrap();
+        let (priv_key_iv, secured_private_key) = block_on(seal_private_key(
+            &mut cipher,
+            alg,
+            &wrapping_key,
+            b"private key",
+        ))
+        .unwrap();
+
+        UserAuth {
+            kdf_base_digest_alg: DigestAlgorithm::Sha256,
+            kdf_params: Some(kdf_params),
+            priv_key_cipher,
+            auth_key_alg: AsymmetricCipherAlgorithm::Ec25519,
+            pub_key: Bytes::new(Vec::new()),
+            priv_key_iv,
+            secured_private_key,
+        }
+    }
+
+    fn unseal(password: &str, auth: &UserAuth) -> Result<Bytes> {
+        block_on(unseal_user_private_key(
+            &mut DefaultSymmetricCipher::new(),
+            password,
+            "user@example.com",
+            auth,
+        ))
+    }
+
+    #[test]
+    fn private_key_round_trips() {
+        for alg in [
+            None,
+            Some(SymmetricCipherAlgorithm::Aes128Gcm),
+            Some(SymmetricCipherAlgorithm::Aes256Cbc),
+            Some(SymmetricCipherAlgorithm::Chacha20),
+        ] {
+            let auth = user_auth(alg);
+            assert_eq!(
+                *unseal("hunter2", &auth).unwrap(),
+                *b"private key",
+                "{alg:?}"
+            );
+            assert_eq!(
+                unseal("hunter3", &auth),
+                Err(CipherSuiteError::DecryptionFailed),
+                "{alg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cipher_is_recorded() {
+        // Sealing with one cipher and unsealing with another must fail rather than produce garbage
+        let mut auth = user_auth(Some(SymmetricCipherAlgorithm::Chacha20));
+        auth.priv_key_cipher = Some(SymmetricCipherAlgorithm::Aes256Gcm);
+        assert_eq!(
+            unseal("hunter2", &auth),
+            Err(CipherSuiteError::DecryptionFailed)
+        );
+
+        let mut auth = user_auth(None);
+        auth.secured_private_key = Bytes::new(auth.secured_private_key[..8].to_vec());
+        assert_eq!(
+            unseal("hunter2", &auth),
+            Err(CipherSuiteError::DecryptionFailed)
+        );
+    }
+}
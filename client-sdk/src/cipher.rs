@@ -1,14 +1,232 @@
 use crate::macros::async_trait;
-use common::suite::*;
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode},
+    http::api::item::{ItemKeyInfo, ItemKeys, KEY_WRAP_CIPHER},
+    suite::*,
+};
+
+pub mod asymmetric;
+pub mod digest;
+pub mod kdf;
+pub mod symmetric;
 
 #[non_exhaustive]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum CipherSuiteError {
     Unsupported,
+    /// The SPI was used before a successful call to `init`.
+    NotInitialized,
+    /// Key material is malformed or does not belong to the selected algorithm.
+    InvalidKey,
+    /// Encrypted data failed to decrypt or authenticate.
+    DecryptionFailed,
+}
+
+impl From<CipherSuiteError> for Error {
+    fn from(value: CipherSuiteError) -> Self {
+        match value {
+            CipherSuiteError::Unsupported => {
+                Error::new(ErrorCode::UnsupportedAlgorithm, "unsupported algorithm")
+            }
+            CipherSuiteError::NotInitialized => {
+                Error::new(ErrorCode::Crypto, "cipher used before initialization")
+            }
+            CipherSuiteError::InvalidKey => Error::new(ErrorCode::Crypto, "invalid key material"),
+            CipherSuiteError::DecryptionFailed => {
+                Error::new(ErrorCode::Crypto, "decryption failed")
+            }
+        }
+    }
 }
 
 pub type Result<T> = core::result::Result<T, CipherSuiteError>;
 
+/// An encoded asymmetric key pair.
+///
+/// The encoding of each key depends on the algorithm that generated it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyPair {
+    pub public_key: Bytes,
+    pub private_key: Bytes,
+}
+
 #[async_trait]
 pub trait AsymmetricCipherSpi {
     async fn init(&mut self, alg: AsymmetricCipherAlgorithm) -> Result<()>;
+    async fn generate_key_pair(&mut self) -> Result<KeyPair>;
+
+    /// Signs `message` with `private_key`.
+    ///
+    /// `digest` is the hash used by algorithms that sign a digest of the message. Algorithms that define their own
+    ///  hash (such as Ed25519) ignore it.
+    async fn sign(
+        &mut self,
+        private_key: &[u8],
+        digest: DigestAlgorithm,
+        message: &[u8],
+    ) -> Result<Bytes>;
+
+    /// Checks that `signature` is a valid signature of `message` by the owner of `public_key`.
+    async fn verify(
+        &mut self,
+        public_key: &[u8],
+        digest: DigestAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool>;
+
+    /// Encrypts `data` (typically an item key) so that only the owner of `public_key` can recover it.
+    async fn seal(&mut self, public_key: &[u8], data: &[u8]) -> Result<Bytes>;
+
+    /// Recovers data sealed by [`AsymmetricCipherSpi::seal`] for the public key matching `private_key`.
+    async fn unseal(&mut self, private_key: &[u8], sealed: &[u8]) -> Result<Bytes>;
+}
+
+/// Incremental message digests.
+///
+/// Data is fed with [`DigestSpi::update`] as it becomes available, so that large inputs never need to be buffered.
+#[async_trait]
+pub trait DigestSpi {
+    async fn init(&mut self, alg: DigestAlgorithm) -> Result<()>;
+
+    /// The length of the digest for the selected algorithm, in bytes.
+    fn output_len(&self) -> Result<usize>;
+
+    fn update(&mut self, data: &[u8]) -> Result<()>;
+
+    /// Returns the digest of all data passed to [`DigestSpi::update`] since `init` or the last `finalize`,
+    ///  and resets the state for the next message.
+    fn finalize(&mut self) -> Result<Bytes>;
+}
+
+/// The output of [`SymmetricCipherSpi::encrypt`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymmetricCiphertext {
+    pub ciphertext: Bytes,
+    pub auth_tag: Bytes,
+}
+
+/// Authenticated symmetric encryption.
+///
+/// Every algorithm produces an authentication tag, and decryption fails rather than return data that does not
+///  match its tag.
+#[async_trait]
+pub trait SymmetricCipherSpi {
+    async fn init(&mut self, alg: SymmetricCipherAlgorithm) -> Result<()>;
+
+    /// The length of keys for the selected algorithm, in bytes.
+    fn key_len(&self) -> Result<usize>;
+
+    /// The length of IVs for the selected algorithm, in bytes.
+    fn iv_len(&self) -> Result<usize>;
+
+    /// The length of authentication tags for the selected algorithm, in bytes.
+    fn tag_len(&self) -> Result<usize>;
+
+    async fn generate_key(&mut self) -> Result<Bytes>;
+    async fn generate_iv(&mut self) -> Result<Bytes>;
+
+    async fn encrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8],
+        plaintext: &[u8],
+    ) -> Result<SymmetricCiphertext>;
+
+    async fn decrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8],
+        ciphertext: &[u8],
+        auth_tag: &[u8],
+    ) -> Result<Bytes>;
+}
+
+/// Encrypts an item body with `item_key`, using the cipher from `keys.base_cipher`.
+///
+/// A fresh IV is generated, and `keys.item_iv` and `keys.item_auth_tag` are updated to match the returned ciphertext.
+pub async fn encrypt_item(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    keys: &mut ItemKeys,
+    item_key: &[u8],
+    body: &[u8],
+) -> Result<Bytes> {
+    cipher.init(keys.base_cipher).await?;
+    let iv = cipher.generate_iv().await?;
+    let SymmetricCiphertext {
+        ciphertext,
+        auth_tag,
+    } = cipher.encrypt(item_key, &iv, body).await?;
+    keys.item_iv = iv;
+    keys.item_auth_tag = Some(auth_tag);
+    Ok(ciphertext)
+}
+
+/// Decrypts an item body encrypted by [`encrypt_item`].
+///
+/// Items without an `item_auth_tag` are rejected.
+pub async fn decrypt_item(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    keys: &ItemKeys,
+    item_key: &[u8],
+    body: &[u8],
+) -> Result<Bytes> {
+    let auth_tag = keys
+        .item_auth_tag
+        .as_ref()
+        .ok_or(CipherSuiteError::DecryptionFailed)?;
+    cipher.init(keys.base_cipher).await?;
+    cipher
+        .decrypt(item_key, &keys.item_iv, body, auth_tag)
+        .await
+}
+
+/// Wraps `item_key` with the symmetric `wrapping_key` using [`KEY_WRAP_CIPHER`].
+pub async fn wrap_item_key(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    wrapping_key: &[u8],
+    item_key: &[u8],
+) -> Result<ItemKeyInfo> {
+    cipher.init(KEY_WRAP_CIPHER).await?;
+    let iv = cipher.generate_iv().await?;
+    let SymmetricCiphertext {
+        ciphertext,
+        auth_tag,
+    } = cipher.encrypt(wrapping_key, &iv, item_key).await?;
+    Ok(ItemKeyInfo {
+        secured_item_key: ciphertext,
+        item_key_iv: iv,
+        item_auth_tag: Some(auth_tag),
+    })
+}
+
+/// Recovers an item key wrapped by [`wrap_item_key`].
+pub async fn unwrap_item_key(
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+    wrapping_key: &[u8],
+    info: &ItemKeyInfo,
+) -> Result<Bytes> {
+    let auth_tag = info
+        .item_auth_tag
+        .as_ref()
+        .ok_or(CipherSuiteError::DecryptionFailed)?;
+    cipher.init(KEY_WRAP_CIPHER).await?;
+    cipher
+        .decrypt(
+            wrapping_key,
+            &info.item_key_iv,
+            &info.secured_item_key,
+            auth_tag,
+        )
+        .await
+}
+
+/// Decodes a hex string, for known-answer tests.
+#[cfg(test)]
+pub(crate) fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
 }
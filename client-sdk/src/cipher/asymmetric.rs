@@ -0,0 +1,394 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use common::{
+    data::Bytes,
+    suite::{AsymmetricCipherAlgorithm, DigestAlgorithm},
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use rsa::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey},
+    traits::PublicKeyParts,
+    Oaep, Pss, RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{digest::DynDigest, Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use super::{digest::with_digest, AsymmetricCipherSpi, CipherSuiteError, KeyPair, Result};
+use crate::macros::async_trait;
+
+/// The software implementation of [`AsymmetricCipherSpi`].
+///
+/// Key encodings and schemes by algorithm:
+/// * `Rsa2048`/`Rsa4096`: PKCS#8 DER private keys and SPKI DER public keys.
+///   Signatures use RSASSA-PSS with the requested digest, and sealing uses RSAES-OAEP with SHA-256.
+/// * `Ec25519`: the raw 32 byte Ed25519 seed and public key. Signatures are Ed25519.
+///   Sealing converts the keys to X25519 and uses an ephemeral key agreement, HKDF-SHA256 and AES-256-GCM,
+///   producing `ephemeral public key || nonce || ciphertext`.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultAsymmetricCipher {
+    alg: Option<AsymmetricCipherAlgorithm>,
+}
+
+impl DefaultAsymmetricCipher {
+    pub const fn new() -> Self {
+        Self { alg: None }
+    }
+
+    fn alg(&self) -> Result<AsymmetricCipherAlgorithm> {
+        self.alg.ok_or(CipherSuiteError::NotInitialized)
+    }
+}
+
+const ECIES_INFO: &[u8] = b"passman ec25519 seal";
+
+const ECIES_NONCE_LEN: usize = 12;
+
+fn ed25519_signing_key(private_key: &[u8]) -> Result<SigningKey> {
+    private_key
+        .try_into()
+        .map(SigningKey::from_bytes)
+        .map_err(|_| CipherSuiteError::InvalidKey)
+}
+
+fn ed25519_verifying_key(public_key: &[u8]) -> Result<VerifyingKey> {
+    let bytes = public_key
+        .try_into()
+        .map_err(|_| CipherSuiteError::InvalidKey)?;
+    VerifyingKey::from_bytes(bytes).map_err(|_| CipherSuiteError::InvalidKey)
+}
+
+fn ecies_key(shared: &[u8; 32], ephemeral: &PublicKey, recipient: &PublicKey) -> Result<Aes256Gcm> {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(ECIES_INFO, &mut key)
+        .map_err(|_| CipherSuiteError::InvalidKey)?;
+    Ok(Aes256Gcm::new(&key.into()))
+}
+
+fn ec25519_seal(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let recipient = PublicKey::from(
+        ed25519_verifying_key(public_key)?
+            .to_montgomery()
+            .to_bytes(),
+    );
+    let secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral = PublicKey::from(&secret);
+    let shared = secret.diffie_hellman(&recipient);
+    if !shared.was_contributory() {
+        return Err(CipherSuiteError::InvalidKey);
+    }
+
+    let cipher = ecies_key(shared.as_bytes(), &ephemeral, &recipient)?;
+    let mut nonce = [0u8; ECIES_NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|_| CipherSuiteError::InvalidKey)?;
+
+    let mut sealed = Vec::with_capacity(32 + ECIES_NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(ephemeral.as_bytes());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn ec25519_unseal(private_key: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < 32 + ECIES_NONCE_LEN {
+        return Err(CipherSuiteError::DecryptionFailed);
+    }
+    let (ephemeral, rest) = sealed.split_at(32);
+    let (nonce, ciphertext) = rest.split_at(ECIES_NONCE_LEN);
+    let ephemeral = PublicKey::from(<[u8; 32]>::try_from(ephemeral).unwrap());
+
+    let secret = StaticSecret::from(ed25519_signing_key(private_key)?.to_scalar_bytes());
+    let recipient = PublicKey::from(&secret);
+    let shared = secret.diffie_hellman(&ephemeral);
+    if !shared.was_contributory() {
+        return Err(CipherSuiteError::DecryptionFailed);
+    }
+
+    ecies_key(shared.as_bytes(), &ephemeral, &recipient)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CipherSuiteError::DecryptionFailed)
+}
+
+fn rsa_bits(alg: AsymmetricCipherAlgorithm) -> Result<usize> {
+    match alg {
+        AsymmetricCipherAlgorithm::Rsa2048 => Ok(2048),
+        AsymmetricCipherAlgorithm::Rsa4096 => Ok(4096),
+        _ => Err(CipherSuiteError::Unsupported),
+    }
+}
+
+fn rsa_private_key(alg: AsymmetricCipherAlgorithm, private_key: &[u8]) -> Result<RsaPrivateKey> {
+    let key =
+        RsaPrivateKey::from_pkcs8_der(private_key).map_err(|_| CipherSuiteError::InvalidKey)?;
+    if key.size() * 8 != rsa_bits(alg)? {
+        return Err(CipherSuiteError::InvalidKey);
+    }
+    Ok(key)
+}
+
+fn rsa_public_key(alg: AsymmetricCipherAlgorithm, public_key: &[u8]) -> Result<RsaPublicKey> {
+    let key =
+        RsaPublicKey::from_public_key_der(public_key).map_err(|_| CipherSuiteError::InvalidKey)?;
+    if key.size() * 8 != rsa_bits(alg)? {
+        return Err(CipherSuiteError::InvalidKey);
+    }
+    Ok(key)
+}
+
+fn rsa_sign<D: Digest + DynDigest + Send + Sync + 'static>(
+    key: &RsaPrivateKey,
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    key.sign_with_rng(&mut OsRng, Pss::new::<D>(), &D::digest(message))
+        .map_err(|_| CipherSuiteError::InvalidKey)
+}
+
+fn rsa_verify<D: Digest + DynDigest + Send + Sync + 'static>(
+    key: &RsaPublicKey,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool> {
+    Ok(key
+        .verify(Pss::new::<D>(), &D::digest(message), signature)
+        .is_ok())
+}
+
+#[async_trait]
+impl AsymmetricCipherSpi for DefaultAsymmetricCipher {
+    async fn init(&mut self, alg: AsymmetricCipherAlgorithm) -> Result<()> {
+        match alg {
+            AsymmetricCipherAlgorithm::Rsa2048
+            | AsymmetricCipherAlgorithm::Rsa4096
+            | AsymmetricCipherAlgorithm::Ec25519 => {
+                self.alg = Some(alg);
+                Ok(())
+            }
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    async fn generate_key_pair(&mut self) -> Result<KeyPair> {
+        match self.alg()? {
+            AsymmetricCipherAlgorithm::Ec25519 => {
+                let key = SigningKey::generate(&mut OsRng);
+                Ok(KeyPair {
+                    public_key: key.verifying_key().to_bytes().to_vec().into(),
+                    private_key: key.to_bytes().to_vec().into(),
+                })
+            }
+            alg => {
+                let key = RsaPrivateKey::new(&mut OsRng, rsa_bits(alg)?)
+                    .map_err(|_| CipherSuiteError::InvalidKey)?;
+                let private_key = key
+                    .to_pkcs8_der()
+                    .map_err(|_| CipherSuiteError::InvalidKey)?;
+                let public_key = key
+                    .to_public_key()
+                    .to_public_key_der()
+                    .map_err(|_| CipherSuiteError::InvalidKey)?;
+                Ok(KeyPair {
+                    public_key: public_key.as_bytes().into(),
+                    private_key: private_key.as_bytes().into(),
+                })
+            }
+        }
+    }
+
+    async fn sign(
+        &mut self,
+        private_key: &[u8],
+        digest: DigestAlgorithm,
+        message: &[u8],
+    ) -> Result<Bytes> {
+        match self.alg()? {
+            AsymmetricCipherAlgorithm::Ec25519 => {
+                let key = ed25519_signing_key(private_key)?;
+                Ok(key.sign(message).to_vec().into())
+            }
+            alg => {
+                let key = rsa_private_key(alg, private_key)?;
+                with_digest!(digest, |D| rsa_sign::<D>(&key, message)).map(Bytes::new)
+            }
+        }
+    }
+
+    async fn verify(
+        &mut self,
+        public_key: &[u8],
+        digest: DigestAlgorithm,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        match self.alg()? {
+            AsymmetricCipherAlgorithm::Ec25519 => {
+                let key = ed25519_verifying_key(public_key)?;
+                let Ok(signature) = Signature::from_slice(signature) else {
+                    return Ok(false);
+                };
+                Ok(key.verify(message, &signature).is_ok())
+            }
+            alg => {
+                let key = rsa_public_key(alg, public_key)?;
+                with_digest!(digest, |D| rsa_verify::<D>(&key, message, signature))
+            }
+        }
+    }
+
+    async fn seal(&mut self, public_key: &[u8], data: &[u8]) -> Result<Bytes> {
+        match self.alg()? {
+            AsymmetricCipherAlgorithm::Ec25519 => ec25519_seal(public_key, data).map(Bytes::new),
+            alg => rsa_public_key(alg, public_key)?
+                .encrypt(&mut OsRng, Oaep::new::<Sha256>(), data)
+                .map(Bytes::new)
+                .map_err(|_| CipherSuiteError::InvalidKey),
+        }
+    }
+
+    async fn unseal(&mut self, private_key: &[u8], sealed: &[u8]) -> Result<Bytes> {
+        match self.alg()? {
+            AsymmetricCipherAlgorithm::Ec25519 => {
+                ec25519_unseal(private_key, sealed).map(Bytes::new)
+            }
+            alg => rsa_private_key(alg, private_key)?
+                .decrypt(Oaep::new::<Sha256>(), sealed)
+                .map(Bytes::new)
+                .map_err(|_| CipherSuiteError::DecryptionFailed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::task::block_on;
+
+    use super::*;
+    use crate::cipher::hex;
+
+    // RFC 8032 section 7.1, TEST 1
+    const ED25519_SECRET: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+    const ED25519_PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+    const ED25519_SIGNATURE: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+    fn cipher(alg: AsymmetricCipherAlgorithm) -> DefaultAsymmetricCipher {
+        let mut cipher = DefaultAsymmetricCipher::new();
+        block_on(cipher.init(alg)).unwrap();
+        cipher
+    }
+
+    #[test]
+    fn ed25519_vector() {
+        let mut cipher = cipher(AsymmetricCipherAlgorithm::Ec25519);
+        let (secret, public) = (hex(ED25519_SECRET), hex(ED25519_PUBLIC));
+        let signature = block_on(cipher.sign(&secret, DigestAlgorithm::Sha256, b"")).unwrap();
+        assert_eq!(*signature, hex(ED25519_SIGNATURE));
+        assert!(
+            block_on(cipher.verify(&public, DigestAlgorithm::Sha256, b"", &signature)).unwrap()
+        );
+    }
+
+    fn check_key_pair(alg: AsymmetricCipherAlgorithm, digests: &[DigestAlgorithm]) {
+        let mut cipher = cipher(alg);
+        let KeyPair {
+            public_key,
+            private_key,
+        } = block_on(cipher.generate_key_pair()).unwrap();
+
+        for &digest in digests {
+            let signature = block_on(cipher.sign(&private_key, digest, b"message")).unwrap();
+            let verify =
+                |cipher: &mut DefaultAsymmetricCipher, message: &[u8], signature: &[u8]| {
+                    block_on(cipher.verify(&public_key, digest, message, signature)).unwrap()
+                };
+            assert!(
+                verify(&mut cipher, b"message", &signature),
+                "{alg} {digest}"
+            );
+            assert!(
+                !verify(&mut cipher, b"messagf", &signature),
+                "{alg} {digest}"
+            );
+            let mut tampered = signature.clone();
+            tampered[0] ^= 1;
+            assert!(
+                !verify(&mut cipher, b"message", &tampered),
+                "{alg} {digest}"
+            );
+            assert!(
+                !verify(&mut cipher, b"message", &signature[1..]),
+                "{alg} {digest}"
+            );
+        }
+
+        let sealed = block_on(cipher.seal(&public_key, b"item key")).unwrap();
+        assert_eq!(
+            *block_on(cipher.unseal(&private_key, &sealed)).unwrap(),
+            *b"item key"
+        );
+        for idx in [0, sealed.len() / 2, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[idx] ^= 1;
+            assert_eq!(
+                block_on(cipher.unseal(&private_key, &tampered)),
+                Err(CipherSuiteError::DecryptionFailed),
+                "{alg} byte {idx}"
+            );
+        }
+        assert_eq!(
+            block_on(cipher.unseal(&private_key, &sealed[..sealed.len() - 1])),
+            Err(CipherSuiteError::DecryptionFailed),
+        );
+
+        let other = block_on(cipher.generate_key_pair()).unwrap();
+        assert_eq!(
+            block_on(cipher.unseal(&other.private_key, &sealed)),
+            Err(CipherSuiteError::DecryptionFailed),
+        );
+    }
+
+    #[test]
+    fn ec25519_round_trips() {
+        check_key_pair(
+            AsymmetricCipherAlgorithm::Ec25519,
+            &[DigestAlgorithm::Sha256],
+        );
+    }
+
+    #[test]
+    fn rsa_round_trips() {
+        check_key_pair(
+            AsymmetricCipherAlgorithm::Rsa2048,
+            &[DigestAlgorithm::Sha256, DigestAlgorithm::Sha3_512],
+        );
+    }
+
+    #[test]
+    fn keys_must_match_the_algorithm() {
+        let ec25519 =
+            block_on(cipher(AsymmetricCipherAlgorithm::Ec25519).generate_key_pair()).unwrap();
+        let mut rsa = cipher(AsymmetricCipherAlgorithm::Rsa4096);
+        assert_eq!(
+            block_on(rsa.seal(&ec25519.public_key, b"item key")),
+            Err(CipherSuiteError::InvalidKey)
+        );
+        let mut ec25519_cipher = cipher(AsymmetricCipherAlgorithm::Ec25519);
+        assert_eq!(
+            block_on(ec25519_cipher.seal(&ec25519.public_key[1..], b"item key")),
+            Err(CipherSuiteError::InvalidKey)
+        );
+        assert_eq!(
+            block_on(DefaultAsymmetricCipher::new().generate_key_pair()),
+            Err(CipherSuiteError::NotInitialized)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use async_std::io::Read;
+use common::{data::Bytes, suite::DigestAlgorithm};
+use sha2::digest::DynDigest;
+
+use super::{CipherSuiteError, DigestSpi, Result};
+use crate::macros::async_trait;
+
+/// Evaluates `$body` with `$D` naming the hash type that implements `$alg`, or fails with
+///  [`CipherSuiteError::Unsupported`].
+///
+/// This is the only mapping from [`DigestAlgorithm`] to hash implementations; code that needs the hash as a type
+///  parameter (such as HMAC or RSA padding) goes through it as well.
+macro_rules! with_digest {
+    ($alg:expr, |$D:ident| $body:expr) => {
+        match $alg {
+            ::common::suite::DigestAlgorithm::Sha256 => {
+                type $D = ::sha2::Sha256;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha224 => {
+                type $D = ::sha2::Sha224;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha384 => {
+                type $D = ::sha2::Sha384;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha512 => {
+                type $D = ::sha2::Sha512;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha512_256 => {
+                type $D = ::sha2::Sha512_256;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha512_224 => {
+                type $D = ::sha2::Sha512_224;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha3_256 => {
+                type $D = ::sha3::Sha3_256;
+                $body
+            }
+            ::common::suite::DigestAlgorithm::Sha3_512 => {
+                type $D = ::sha3::Sha3_512;
+                $body
+            }
+            _ => Err($crate::cipher::CipherSuiteError::Unsupported),
+        }
+    };
+}
+pub(crate) use with_digest;
+
+/// The software implementation of [`DigestSpi`], supporting every [`DigestAlgorithm`].
+#[derive(Default)]
+pub struct DefaultDigest {
+    state: Option<(DigestAlgorithm, Box<dyn DynDigest + Send + Sync>)>,
+}
+
+impl DefaultDigest {
+    pub const fn new() -> Self {
+        Self { state: None }
+    }
+
+    /// Creates a digest already initialized for `alg`.
+    pub fn for_algorithm(alg: DigestAlgorithm) -> Result<Self> {
+        fn boxed<D: DynDigest + Default + Send + Sync + 'static>(
+        ) -> Result<Box<dyn DynDigest + Send + Sync>> {
+            Ok(Box::<D>::default())
+        }
+
+        Ok(Self {
+            state: Some((alg, with_digest!(alg, |D| boxed::<D>())?)),
+        })
+    }
+
+    /// Computes the digest of `data` in one step.
+    pub fn digest(alg: DigestAlgorithm, data: &[u8]) -> Result<Bytes> {
+        let mut digest = Self::for_algorithm(alg)?;
+        digest.update(data)?;
+        digest.finalize()
+    }
+
+    fn state(&mut self) -> Result<&mut (dyn DynDigest + Send + Sync + 'static)> {
+        self.state
+            .as_mut()
+            .map(|(_, state)| &mut **state)
+            .ok_or(CipherSuiteError::NotInitialized)
+    }
+}
+
+impl core::fmt::Debug for DefaultDigest {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DefaultDigest")
+            .field("alg", &self.state.as_ref().map(|(alg, _)| alg))
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl DigestSpi for DefaultDigest {
+    async fn init(&mut self, alg: DigestAlgorithm) -> Result<()> {
+        *self = Self::for_algorithm(alg)?;
+        Ok(())
+    }
+
+    fn output_len(&self) -> Result<usize> {
+        self.state
+            .as_ref()
+            .map(|(alg, _)| alg.output_len())
+            .ok_or(CipherSuiteError::NotInitialized)
+    }
+
+    fn update(&mut self, data: &[u8]) -> Result<()> {
+        self.state()?.update(data);
+        Ok(())
+    }
+
+    fn finalize(&mut self) -> Result<Bytes> {
+        Ok(self.state()?.finalize_reset().into_vec().into())
+    }
+}
+
+/// A reader that passes everything read from the underlying reader into a digest.
+///
+/// Once the reader is exhausted, [`DigestSpi::finalize`] on the digest gives the digest of the whole stream.
+pub struct DigestReader<'a, R> {
+    inner: R,
+    digest: &'a mut (dyn DigestSpi + Send),
+}
+
+impl<'a, R> DigestReader<'a, R> {
+    pub fn new(inner: R, digest: &'a mut (dyn DigestSpi + Send)) -> Self {
+        Self { inner, digest }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Unpin> Read for DigestReader<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                if this.digest.update(&buf[..n]).is_err() {
+                    return Poll::Ready(Err(std::io::Error::other("digest not initialized")));
+                }
+                Poll::Ready(Ok(n))
+            }
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::{io::ReadExt, task::block_on};
+
+    use super::*;
+    use crate::cipher::hex;
+
+    // FIPS 180-4 and FIPS 202 example values for "abc"
+    const ABC_VECTORS: &[(DigestAlgorithm, &str)] = &[
+        (DigestAlgorithm::Sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (DigestAlgorithm::Sha224, "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"),
+        (DigestAlgorithm::Sha384, "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"),
+        (DigestAlgorithm::Sha512, "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+        (DigestAlgorithm::Sha512_256, "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23"),
+        (DigestAlgorithm::Sha512_224, "4634270f707b6a54daae7530460842e20e37ed265ceee9a43e8924aa"),
+        (DigestAlgorithm::Sha3_256, "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"),
+        (DigestAlgorithm::Sha3_512, "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"),
+    ];
+
+    #[test]
+    fn abc_vectors() {
+        for &(alg, expected) in ABC_VECTORS {
+            let digest = DefaultDigest::digest(alg, b"abc").unwrap();
+            assert_eq!(*digest, hex(expected), "{alg}");
+            assert_eq!(digest.len(), alg.output_len(), "{alg}");
+        }
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut digest = DefaultDigest::new();
+        block_on(digest.init(DigestAlgorithm::Sha256)).unwrap();
+        assert_eq!(digest.output_len(), Ok(32));
+        digest.update(b"a").unwrap();
+        digest.update(b"").unwrap();
+        digest.update(b"bc").unwrap();
+        assert_eq!(*digest.finalize().unwrap(), hex(ABC_VECTORS[0].1));
+
+        // finalize resets the state
+        assert_eq!(
+            *digest.finalize().unwrap(),
+            hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+    }
+
+    #[test]
+    fn digest_reader() {
+        let data = vec![0x5a; 100_000];
+        let mut digest = DefaultDigest::for_algorithm(DigestAlgorithm::Sha3_256).unwrap();
+        let mut reader = DigestReader::new(&data[..], &mut digest);
+        let mut read = Vec::new();
+        block_on(reader.read_to_end(&mut read)).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(
+            digest.finalize().unwrap(),
+            DefaultDigest::digest(DigestAlgorithm::Sha3_256, &data).unwrap()
+        );
+    }
+
+    #[test]
+    fn uninitialized() {
+        let mut digest = DefaultDigest::new();
+        assert_eq!(digest.update(b"abc"), Err(CipherSuiteError::NotInitialized));
+        assert_eq!(digest.finalize(), Err(CipherSuiteError::NotInitialized));
+        assert_eq!(digest.output_len(), Err(CipherSuiteError::NotInitialized));
+    }
+}
@@ -0,0 +1,197 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use common::{
+    data::Bytes,
+    http::api::auth::{KdfFunction, KdfParams, KDF_PARAMS_VERSION},
+    suite::DigestAlgorithm,
+};
+use rand::{rngs::OsRng, RngCore};
+
+use super::{digest::with_digest, CipherSuiteError, Result};
+
+/// The length of generated salts, in bytes.
+pub const SALT_LEN: usize = 16;
+
+/// The number of PBKDF2 iterations used for new parameters.
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// The Argon2id memory cost (in KiB) used for new parameters.
+pub const ARGON2ID_MEMORY: u32 = 64 * 1024;
+
+/// The Argon2id time cost used for new parameters.
+pub const ARGON2ID_ITERATIONS: u32 = 3;
+
+/// The Argon2id parallelism used for new parameters.
+pub const ARGON2ID_PARALLELISM: u32 = 4;
+
+fn generate_salt() -> Bytes {
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    Bytes::new(salt)
+}
+
+/// Returns PBKDF2 parameters with a fresh random salt.
+pub fn pbkdf2_params() -> KdfParams {
+    KdfParams {
+        version: KDF_PARAMS_VERSION,
+        function: KdfFunction::Pbkdf2 {
+            iterations: PBKDF2_ITERATIONS,
+            salt: generate_salt(),
+        },
+    }
+}
+
+/// Returns Argon2id parameters with a fresh random salt.
+pub fn argon2id_params() -> KdfParams {
+    KdfParams {
+        version: KDF_PARAMS_VERSION,
+        function: KdfFunction::Argon2id {
+            memory: ARGON2ID_MEMORY,
+            iterations: ARGON2ID_ITERATIONS,
+            parallelism: ARGON2ID_PARALLELISM,
+            salt: generate_salt(),
+        },
+    }
+}
+
+/// Computes PBKDF2-HMAC with `digest` as the underlying hash.
+pub fn pbkdf2(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    digest: DigestAlgorithm,
+    out: &mut [u8],
+) -> Result<()> {
+    if iterations == 0 {
+        return Err(CipherSuiteError::InvalidKey);
+    }
+    with_digest!(digest, |D| {
+        pbkdf2::pbkdf2_hmac::<D>(password, salt, iterations, out);
+        Ok(())
+    })
+}
+
+/// Derives `out.len()` bytes of key material from `password` according to `params`.
+///
+/// `digest` is only used by KDFs built on a hash function, such as PBKDF2.
+pub fn derive_key(
+    password: &[u8],
+    digest: DigestAlgorithm,
+    params: &KdfParams,
+    out: &mut [u8],
+) -> Result<()> {
+    if params.version != KDF_PARAMS_VERSION {
+        return Err(CipherSuiteError::Unsupported);
+    }
+
+    match &params.function {
+        KdfFunction::Pbkdf2 { iterations, salt } => {
+            pbkdf2(password, salt, *iterations, digest, out)
+        }
+        KdfFunction::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+            salt,
+        } => {
+            let params = Params::new(*memory, *iterations, *parallelism, Some(out.len()))
+                .map_err(|_| CipherSuiteError::InvalidKey)?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(password, salt, out)
+                .map_err(|_| CipherSuiteError::InvalidKey)
+        }
+        _ => Err(CipherSuiteError::Unsupported),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher::hex;
+
+    #[test]
+    fn pbkdf2_sha256_vectors() {
+        // RFC 7914 section 11, and the PBKDF2-HMAC-SHA256 counterparts of the RFC 6070 vectors
+        let vectors: &[(&[u8], &[u8], u32, &str)] = &[
+            (b"passwd", b"salt", 1, "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"),
+            (b"password", b"salt", 1, "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b"),
+            (b"password", b"salt", 2, "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"),
+            (b"password", b"salt", 4096, "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a"),
+        ];
+        for &(password, salt, iterations, expected) in vectors {
+            let expected = hex(expected);
+            let mut out = vec![0u8; expected.len()];
+            pbkdf2(
+                password,
+                salt,
+                iterations,
+                DigestAlgorithm::Sha256,
+                &mut out,
+            )
+            .unwrap();
+            assert_eq!(out, expected, "{iterations} iterations");
+        }
+    }
+
+    #[test]
+    fn pbkdf2_rejects_zero_iterations() {
+        let mut out = [0u8; 32];
+        assert_eq!(
+            pbkdf2(b"password", b"salt", 0, DigestAlgorithm::Sha256, &mut out),
+            Err(CipherSuiteError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn argon2id_vector() {
+        // From the test suite of the Argon2 reference implementation
+        let params = KdfParams {
+            version: KDF_PARAMS_VERSION,
+            function: KdfFunction::Argon2id {
+                memory: 1 << 16,
+                iterations: 2,
+                parallelism: 1,
+                salt: Bytes::from(&b"somesalt"[..]),
+            },
+        };
+        let mut out = [0u8; 32];
+        derive_key(b"password", DigestAlgorithm::Sha256, &params, &mut out).unwrap();
+        assert_eq!(
+            out[..],
+            hex("09316115d5cf24ed5a15a31a3ba326e5cf32edc24702987c02b6566f61913cf7")
+        );
+    }
+
+    #[test]
+    fn derive_key_uses_params() {
+        let mut params = pbkdf2_params();
+        let KdfFunction::Pbkdf2 { iterations, salt } = &mut params.function else {
+            unreachable!()
+        };
+        *iterations = 4096;
+        *salt = Bytes::from(&b"salt"[..]);
+        let mut out = [0u8; 32];
+        derive_key(b"password", DigestAlgorithm::Sha256, &params, &mut out).unwrap();
+        assert_eq!(
+            out[..],
+            hex("c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a")
+        );
+
+        params.version = KDF_PARAMS_VERSION + 1;
+        assert_eq!(
+            derive_key(b"password", DigestAlgorithm::Sha256, &params, &mut out),
+            Err(CipherSuiteError::Unsupported)
+        );
+    }
+
+    #[test]
+    fn fresh_salts() {
+        let (KdfFunction::Pbkdf2 { salt: a, .. }, KdfFunction::Argon2id { salt: b, .. }) =
+            (pbkdf2_params().function, argon2id_params().function)
+        else {
+            unreachable!()
+        };
+        assert_eq!(a.len(), SALT_LEN);
+        assert_eq!(b.len(), SALT_LEN);
+        assert_ne!(a, b);
+    }
+}
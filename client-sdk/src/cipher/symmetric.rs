@@ -0,0 +1,398 @@
+use aes_gcm::{
+    aead::{AeadInPlace, KeyInit},
+    Aes128Gcm, Aes256Gcm,
+};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use chacha20poly1305::ChaCha20Poly1305;
+use common::{data::Bytes, suite::SymmetricCipherAlgorithm};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+
+use super::{CipherSuiteError, Result, SymmetricCipherSpi, SymmetricCiphertext};
+use crate::macros::async_trait;
+
+/// The software implementation of [`SymmetricCipherSpi`].
+///
+/// * `Aes128Gcm`/`Aes256Gcm` are AES-GCM with a 12 byte IV and a 16 byte tag.
+/// * `Aes128Cbc`/`Aes256Cbc` are AES-CBC with PKCS#7 padding and a 16 byte IV, authenticated with encrypt-then-MAC.
+///   Separate encryption and MAC keys are derived from the key with HKDF-SHA256, and the tag is the
+///   HMAC-SHA256 of `iv || ciphertext`.
+/// * `Chacha20` is ChaCha20-Poly1305 with a 12 byte IV and a 16 byte tag.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultSymmetricCipher {
+    alg: Option<SymmetricCipherAlgorithm>,
+}
+
+impl DefaultSymmetricCipher {
+    pub const fn new() -> Self {
+        Self { alg: None }
+    }
+
+    fn alg(&self) -> Result<SymmetricCipherAlgorithm> {
+        self.alg.ok_or(CipherSuiteError::NotInitialized)
+    }
+}
+
+const CBC_ENC_INFO: &[u8] = b"passman cbc encryption key";
+const CBC_MAC_INFO: &[u8] = b"passman cbc mac key";
+
+fn aead_encrypt<C: KeyInit + AeadInPlace>(
+    key: &[u8],
+    iv: &[u8],
+    plaintext: &[u8],
+) -> Result<SymmetricCiphertext> {
+    let cipher = C::new_from_slice(key).map_err(|_| CipherSuiteError::InvalidKey)?;
+    let mut buf = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(iv.into(), b"", &mut buf)
+        .map_err(|_| CipherSuiteError::InvalidKey)?;
+    Ok(SymmetricCiphertext {
+        ciphertext: Bytes::new(buf),
+        auth_tag: tag.to_vec().into(),
+    })
+}
+
+fn aead_decrypt<C: KeyInit + AeadInPlace>(
+    key: &[u8],
+    iv: &[u8],
+    ciphertext: &[u8],
+    auth_tag: &[u8],
+) -> Result<Bytes> {
+    let cipher = C::new_from_slice(key).map_err(|_| CipherSuiteError::InvalidKey)?;
+    if auth_tag.len() != 16 {
+        return Err(CipherSuiteError::DecryptionFailed);
+    }
+    let mut buf = ciphertext.to_vec();
+    cipher
+        .decrypt_in_place_detached(iv.into(), b"", &mut buf, auth_tag.into())
+        .map_err(|_| CipherSuiteError::DecryptionFailed)?;
+    Ok(Bytes::new(buf))
+}
+
+/// Splits `key` into an encryption key of the same length, and a MAC key.
+fn cbc_keys(key: &[u8]) -> Result<(Vec<u8>, [u8; 32])> {
+    let hkdf = Hkdf::<Sha256>::new(None, key);
+    let mut enc_key = vec![0u8; key.len()];
+    let mut mac_key = [0u8; 32];
+    hkdf.expand(CBC_ENC_INFO, &mut enc_key)
+        .and_then(|_| hkdf.expand(CBC_MAC_INFO, &mut mac_key))
+        .map_err(|_| CipherSuiteError::InvalidKey)?;
+    Ok((enc_key, mac_key))
+}
+
+fn cbc_mac(mac_key: &[u8; 32], iv: &[u8], ciphertext: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(mac_key).unwrap();
+    mac.update(iv);
+    mac.update(ciphertext);
+    mac
+}
+
+fn cbc_encrypt<C: BlockEncryptMut + KeyIvInit>(
+    key: &[u8],
+    iv: &[u8],
+    plaintext: &[u8],
+) -> Result<SymmetricCiphertext> {
+    let (enc_key, mac_key) = cbc_keys(key)?;
+    let ciphertext = C::new_from_slices(&enc_key, iv)
+        .map_err(|_| CipherSuiteError::InvalidKey)?
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+    let tag = cbc_mac(&mac_key, iv, &ciphertext).finalize().into_bytes();
+    Ok(SymmetricCiphertext {
+        ciphertext: Bytes::new(ciphertext),
+        auth_tag: tag.to_vec().into(),
+    })
+}
+
+fn cbc_decrypt<C: BlockDecryptMut + KeyIvInit>(
+    key: &[u8],
+    iv: &[u8],
+    ciphertext: &[u8],
+    auth_tag: &[u8],
+) -> Result<Bytes> {
+    let (enc_key, mac_key) = cbc_keys(key)?;
+    cbc_mac(&mac_key, iv, ciphertext)
+        .verify_slice(auth_tag)
+        .map_err(|_| CipherSuiteError::DecryptionFailed)?;
+    C::new_from_slices(&enc_key, iv)
+        .map_err(|_| CipherSuiteError::InvalidKey)?
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map(Bytes::new)
+        .map_err(|_| CipherSuiteError::DecryptionFailed)
+}
+
+#[async_trait]
+impl SymmetricCipherSpi for DefaultSymmetricCipher {
+    async fn init(&mut self, alg: SymmetricCipherAlgorithm) -> Result<()> {
+        match alg {
+            SymmetricCipherAlgorithm::Aes128Gcm
+            | SymmetricCipherAlgorithm::Aes128Cbc
+            | SymmetricCipherAlgorithm::Aes256Gcm
+            | SymmetricCipherAlgorithm::Aes256Cbc
+            | SymmetricCipherAlgorithm::Chacha20 => {
+                self.alg = Some(alg);
+                Ok(())
+            }
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    fn key_len(&self) -> Result<usize> {
+        match self.alg()? {
+            SymmetricCipherAlgorithm::Aes128Gcm | SymmetricCipherAlgorithm::Aes128Cbc => Ok(16),
+            SymmetricCipherAlgorithm::Aes256Gcm
+            | SymmetricCipherAlgorithm::Aes256Cbc
+            | SymmetricCipherAlgorithm::Chacha20 => Ok(32),
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    fn iv_len(&self) -> Result<usize> {
+        match self.alg()? {
+            SymmetricCipherAlgorithm::Aes128Gcm
+            | SymmetricCipherAlgorithm::Aes256Gcm
+            | SymmetricCipherAlgorithm::Chacha20 => Ok(12),
+            SymmetricCipherAlgorithm::Aes128Cbc | SymmetricCipherAlgorithm::Aes256Cbc => Ok(16),
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    fn tag_len(&self) -> Result<usize> {
+        match self.alg()? {
+            SymmetricCipherAlgorithm::Aes128Gcm
+            | SymmetricCipherAlgorithm::Aes256Gcm
+            | SymmetricCipherAlgorithm::Chacha20 => Ok(16),
+            SymmetricCipherAlgorithm::Aes128Cbc | SymmetricCipherAlgorithm::Aes256Cbc => Ok(32),
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    async fn generate_key(&mut self) -> Result<Bytes> {
+        let mut key = vec![0u8; self.key_len()?];
+        OsRng.fill_bytes(&mut key);
+        Ok(Bytes::new(key))
+    }
+
+    async fn generate_iv(&mut self) -> Result<Bytes> {
+        let mut iv = vec![0u8; self.iv_len()?];
+        OsRng.fill_bytes(&mut iv);
+        Ok(Bytes::new(iv))
+    }
+
+    async fn encrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8],
+        plaintext: &[u8],
+    ) -> Result<SymmetricCiphertext> {
+        if key.len() != self.key_len()? || iv.len() != self.iv_len()? {
+            return Err(CipherSuiteError::InvalidKey);
+        }
+        match self.alg()? {
+            SymmetricCipherAlgorithm::Aes128Gcm => aead_encrypt::<Aes128Gcm>(key, iv, plaintext),
+            SymmetricCipherAlgorithm::Aes256Gcm => aead_encrypt::<Aes256Gcm>(key, iv, plaintext),
+            SymmetricCipherAlgorithm::Chacha20 => {
+                aead_encrypt::<ChaCha20Poly1305>(key, iv, plaintext)
+            }
+            SymmetricCipherAlgorithm::Aes128Cbc => {
+                cbc_encrypt::<cbc::Encryptor<aes::Aes128>>(key, iv, plaintext)
+            }
+            SymmetricCipherAlgorithm::Aes256Cbc => {
+                cbc_encrypt::<cbc::Encryptor<aes::Aes256>>(key, iv, plaintext)
+            }
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+
+    async fn decrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8],
+        ciphertext: &[u8],
+        auth_tag: &[u8],
+    ) -> Result<Bytes> {
+        if key.len() != self.key_len()? {
+            return Err(CipherSuiteError::InvalidKey);
+        }
+        if iv.len() != self.iv_len()? {
+            return Err(CipherSuiteError::DecryptionFailed);
+        }
+        match self.alg()? {
+            SymmetricCipherAlgorithm::Aes128Gcm => {
+                aead_decrypt::<Aes128Gcm>(key, iv, ciphertext, auth_tag)
+            }
+            SymmetricCipherAlgorithm::Aes256Gcm => {
+                aead_decrypt::<Aes256Gcm>(key, iv, ciphertext, auth_tag)
+            }
+            SymmetricCipherAlgorithm::Chacha20 => {
+                aead_decrypt::<ChaCha20Poly1305>(key, iv, ciphertext, auth_tag)
+            }
+            SymmetricCipherAlgorithm::Aes128Cbc => {
+                cbc_decrypt::<cbc::Decryptor<aes::Aes128>>(key, iv, ciphertext, auth_tag)
+            }
+            SymmetricCipherAlgorithm::Aes256Cbc => {
+                cbc_decrypt::<cbc::Decryptor<aes::Aes256>>(key, iv, ciphertext, auth_tag)
+            }
+            _ => Err(CipherSuiteError::Unsupported),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::task::block_on;
+
+    use super::*;
+    use crate::cipher::hex;
+
+    const ALGORITHMS: &[SymmetricCipherAlgorithm] = &[
+        SymmetricCipherAlgorithm::Aes128Gcm,
+        SymmetricCipherAlgorithm::Aes128Cbc,
+        SymmetricCipherAlgorithm::Aes256Gcm,
+        SymmetricCipherAlgorithm::Aes256Cbc,
+        SymmetricCipherAlgorithm::Chacha20,
+    ];
+
+    fn cipher(alg: SymmetricCipherAlgorithm) -> DefaultSymmetricCipher {
+        let mut cipher = DefaultSymmetricCipher::new();
+        block_on(cipher.init(alg)).unwrap();
+        cipher
+    }
+
+    #[test]
+    fn aes_gcm_vectors() {
+        // Test cases 2 and 14 of "The Galois/Counter Mode of Operation (GCM)", McGrew and Viega
+        let vectors = [
+            (
+                SymmetricCipherAlgorithm::Aes128Gcm,
+                "0388dace60b6a392f328c2b971b2fe78",
+                "ab6e47d42cec13bdf53a67b21257bddf",
+            ),
+            (
+                SymmetricCipherAlgorithm::Aes256Gcm,
+                "cea7403d4d606b6e074ec5d3baf39d18",
+                "d0d1c8a799996bf0265b98b5d48ab919",
+            ),
+        ];
+        for (alg, ciphertext, auth_tag) in vectors {
+            let mut cipher = cipher(alg);
+            let key = vec![0u8; cipher.key_len().unwrap()];
+            let iv = [0u8; 12];
+            let encrypted = block_on(cipher.encrypt(&key, &iv, &[0u8; 16])).unwrap();
+            assert_eq!(*encrypted.ciphertext, hex(ciphertext), "{alg}");
+            assert_eq!(*encrypted.auth_tag, hex(auth_tag), "{alg}");
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for &alg in ALGORITHMS {
+            let mut cipher = cipher(alg);
+            let key = block_on(cipher.generate_key()).unwrap();
+            let iv = block_on(cipher.generate_iv()).unwrap();
+            for plaintext in [&b""[..], b"secret", &[7u8; 100]] {
+                let SymmetricCiphertext {
+                    ciphertext,
+                    auth_tag,
+                } = block_on(cipher.encrypt(&key, &iv, plaintext)).unwrap();
+                assert_eq!(auth_tag.len(), cipher.tag_len().unwrap(), "{alg}");
+                let decrypted = block_on(cipher.decrypt(&key, &iv, &ciphertext, &auth_tag));
+                assert_eq!(decrypted.unwrap().as_ref(), plaintext, "{alg}");
+            }
+        }
+    }
+
+    #[test]
+    fn tampering_is_rejected() {
+        for &alg in ALGORITHMS {
+            let mut cipher = cipher(alg);
+            let key = block_on(cipher.generate_key()).unwrap();
+            let iv = block_on(cipher.generate_iv()).unwrap();
+            let SymmetricCiphertext {
+                ciphertext,
+                auth_tag,
+            } = block_on(cipher.encrypt(&key, &iv, b"a secret of some length")).unwrap();
+
+            let flip = |bytes: &[u8], idx: usize| {
+                let mut bytes = bytes.to_vec();
+                bytes[idx] ^= 1;
+                bytes
+            };
+            let other_key = block_on(cipher.generate_key()).unwrap();
+            let cases = [
+                (
+                    other_key.to_vec(),
+                    iv.to_vec(),
+                    ciphertext.to_vec(),
+                    auth_tag.to_vec(),
+                ),
+                (
+                    key.to_vec(),
+                    flip(&iv, 0),
+                    ciphertext.to_vec(),
+                    auth_tag.to_vec(),
+                ),
+                (
+                    key.to_vec(),
+                    iv.to_vec(),
+                    flip(&ciphertext, 0),
+                    auth_tag.to_vec(),
+                ),
+                (
+                    key.to_vec(),
+                    iv.to_vec(),
+                    flip(&ciphertext, ciphertext.len() - 1),
+                    auth_tag.to_vec(),
+                ),
+                (
+                    key.to_vec(),
+                    iv.to_vec(),
+                    ciphertext.to_vec(),
+                    flip(&auth_tag, 0),
+                ),
+                (
+                    key.to_vec(),
+                    iv.to_vec(),
+                    ciphertext.to_vec(),
+                    auth_tag[..auth_tag.len() - 1].to_vec(),
+                ),
+                (key.to_vec(), iv.to_vec(), ciphertext.to_vec(), Vec::new()),
+                (
+                    key.to_vec(),
+                    iv[1..].to_vec(),
+                    ciphertext.to_vec(),
+                    auth_tag.to_vec(),
+                ),
+            ];
+            for (idx, (key, iv, ciphertext, auth_tag)) in cases.into_iter().enumerate() {
+                assert_eq!(
+                    block_on(cipher.decrypt(&key, &iv, &ciphertext, &auth_tag)),
+                    Err(CipherSuiteError::DecryptionFailed),
+                    "{alg} case {idx}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parameters_are_checked() {
+        let mut cipher = cipher(SymmetricCipherAlgorithm::Aes256Gcm);
+        assert_eq!(
+            block_on(cipher.encrypt(&[0u8; 16], &[0u8; 12], b"")),
+            Err(CipherSuiteError::InvalidKey)
+        );
+        assert_eq!(
+            block_on(cipher.encrypt(&[0u8; 32], &[0u8; 16], b"")),
+            Err(CipherSuiteError::InvalidKey)
+        );
+        assert_eq!(
+            block_on(cipher.decrypt(&[0u8; 16], &[0u8; 12], b"", &[0u8; 16])),
+            Err(CipherSuiteError::InvalidKey)
+        );
+        assert_eq!(
+            DefaultSymmetricCipher::new().key_len(),
+            Err(CipherSuiteError::NotInitialized)
+        );
+    }
+}
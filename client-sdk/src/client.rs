@@ -0,0 +1,648 @@
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode, Result},
+    http::api::{
+        acl::AclRow,
+        auth::{
+            AuthChallengeRequest, AuthChallengeResponse, AuthResponse, AuthSession, KdfParams,
+            SessionInfo, UserAuth,
+        },
+        group::{GroupInfo, GroupKeyInfo, NewGroupRequest, NewGroupResponse},
+        item::{
+            ItemContents, ItemKeyInfo, ItemKeys, ItemMetadata, NewItemRequest, NewItemResponse,
+        },
+        user::{NewUserRequest, NewUserResponse, UserPublicKey, UserRootInfo},
+        Hello,
+    },
+    suite::{AsymmetricCipherAlgorithm, DigestAlgorithm},
+};
+use reqwest::{RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::{
+    auth::{derive_wrapping_key, seal_private_key, PRIV_KEY_CIPHER},
+    cipher::{symmetric::DefaultSymmetricCipher, AsymmetricCipherSpi, SymmetricCipherSpi},
+};
+
+fn transport_error(e: reqwest::Error) -> Error {
+    Error::new(ErrorCode::Transport, e.to_string())
+}
+
+/// Turns an error response into an [`Error`], using the error body if the server sent one, and the status
+///  otherwise.
+async fn check_status(res: reqwest::Response) -> Result<reqwest::Response> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let body = res.bytes().await.unwrap_or_default();
+    Err(serde_json::from_slice(&body).unwrap_or_else(|_| {
+        Error::new(
+            ErrorCode::from_http_status(status.as_u16()),
+            format!("server responded with {status}"),
+        )
+    }))
+}
+
+/// The `ETag` header of an item response.
+fn etag(res: &reqwest::Response) -> Result<String> {
+    res.headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| Error::new(ErrorCode::Transport, "server did not send an ETag"))
+}
+
+/// A connection to a passman server.
+#[derive(Clone, Debug)]
+pub struct Client {
+    base_url: Url,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// Creates a client for the server at `base_url`.
+    ///
+    /// Request paths are resolved relative to `base_url`, so a server mounted below a path such as
+    ///  `https://example.com/passman` is reached whether or not the URL ends in `/`.
+    pub fn new(mut base_url: Url) -> Self {
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Self {
+            base_url,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub(crate) fn url(&self, path: &str) -> Result<Url> {
+        self.base_url
+            .join(path)
+            .map_err(|e| Error::new(ErrorCode::Transport, e.to_string()))
+    }
+
+    pub(crate) async fn send<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T> {
+        check_status(req.send().await.map_err(transport_error)?)
+            .await?
+            .json()
+            .await
+            .map_err(transport_error)
+    }
+
+    /// Sends `req`, discarding any response body.
+    pub(crate) async fn send_empty(&self, req: RequestBuilder) -> Result<()> {
+        check_status(req.send().await.map_err(transport_error)?).await?;
+        Ok(())
+    }
+
+    /// `GET /hello`
+    pub async fn hello(&self) -> Result<Hello> {
+        self.send(self.http.get(self.url("hello")?)).await
+    }
+
+    /// `POST /users/new`
+    pub async fn new_user(&self, req: &NewUserRequest) -> Result<NewUserResponse> {
+        self.send(self.http.post(self.url("users/new")?).json(req))
+            .await
+    }
+
+    /// `GET /users/<uuid>/auth`
+    pub async fn user_auth(&self, user_id: Uuid) -> Result<UserAuth> {
+        self.send(self.http.get(self.url(&format!("users/{user_id}/auth"))?))
+            .await
+    }
+
+    /// `POST /auth/challenge`
+    pub async fn auth_challenge(
+        &self,
+        req: &AuthChallengeRequest,
+    ) -> Result<AuthChallengeResponse> {
+        self.send(self.http.post(self.url("auth/challenge")?).json(req))
+            .await
+    }
+
+    /// `POST /auth/response`
+    pub async fn auth_response(
+        &self,
+        challenge_session_id: Uuid,
+        res: &AuthResponse,
+    ) -> Result<AuthSession> {
+        self.send(
+            self.http
+                .post(self.url("auth/response")?)
+                .bearer_auth(challenge_session_id)
+                .json(res),
+        )
+        .await
+    }
+
+    /// `POST /auth/session/refresh`
+    pub async fn refresh_session(&self, session_token: &Bytes) -> Result<AuthSession> {
+        self.send(
+            self.http
+                .post(self.url("auth/session/refresh")?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `DELETE /auth/session`
+    pub async fn end_session(&self, session_token: &Bytes) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url("auth/session")?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /users/<uuid>/sessions`
+    pub async fn sessions(&self, user_id: Uuid, session_token: &Bytes) -> Result<Vec<SessionInfo>> {
+        self.send(
+            self.http
+                .get(self.url(&format!("users/{user_id}/sessions"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `DELETE /users/<uuid>/sessions/<session-uuid>`
+    pub async fn revoke_session(
+        &self,
+        user_id: Uuid,
+        session_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url(&format!("users/{user_id}/sessions/{session_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /users/<uuid>/root`
+    pub async fn user_root(&self, user_id: Uuid, session_token: &Bytes) -> Result<UserRootInfo> {
+        self.send(
+            self.http
+                .get(self.url(&format!("users/{user_id}/root"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /<object-type>/<uuid>/acl`, or only the rows for `subject` if given.
+    ///
+    /// `object_type` is `item`, `user` or `group`.
+    pub async fn acl(
+        &self,
+        object_type: &str,
+        object: Uuid,
+        subject: Option<Uuid>,
+        session_token: &Bytes,
+    ) -> Result<Vec<AclRow>> {
+        let mut url = self.url(&format!("{object_type}/{object}/acl"))?;
+        if let Some(subject) = subject {
+            url.query_pairs_mut()
+                .append_pair("subject", &subject.to_string());
+        }
+        self.send(self.http.get(url).bearer_auth(session_token.to_base64()))
+            .await
+    }
+
+    /// `POST /<object-type>/<uuid>/acl`
+    pub async fn update_acl(
+        &self,
+        object_type: &str,
+        object: Uuid,
+        rows: &[AclRow],
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .post(self.url(&format!("{object_type}/{object}/acl"))?)
+                .json(rows)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /<object-type>/<uuid>/acl`
+    pub async fn replace_acl(
+        &self,
+        object_type: &str,
+        object: Uuid,
+        rows: &[AclRow],
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url(&format!("{object_type}/{object}/acl"))?)
+                .json(rows)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /server/permissions`
+    pub async fn server_permissions(&self, session_token: &Bytes) -> Result<Vec<AclRow>> {
+        self.send(
+            self.http
+                .get(self.url("server/permissions")?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `POST /server/permissions`
+    pub async fn update_server_permissions(
+        &self,
+        rows: &[AclRow],
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .post(self.url("server/permissions")?)
+                .json(rows)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /server/permissions`
+    pub async fn replace_server_permissions(
+        &self,
+        rows: &[AclRow],
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url("server/permissions")?)
+                .json(rows)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /users/<uuid>/public-key`
+    pub async fn user_public_key(
+        &self,
+        user_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<UserPublicKey> {
+        self.send(
+            self.http
+                .get(self.url(&format!("users/{user_id}/public-key"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `POST /groups/new`
+    pub async fn new_group(
+        &self,
+        request: &NewGroupRequest,
+        session_token: &Bytes,
+    ) -> Result<NewGroupResponse> {
+        self.send(
+            self.http
+                .post(self.url("groups/new")?)
+                .json(request)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /groups/<uuid>`
+    pub async fn group(&self, group_id: Uuid, session_token: &Bytes) -> Result<GroupInfo> {
+        self.send(
+            self.http
+                .get(self.url(&format!("groups/{group_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `DELETE /groups/<uuid>`
+    pub async fn delete_group(&self, group_id: Uuid, session_token: &Bytes) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url(&format!("groups/{group_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /groups/<group-uuid>/members/<member-uuid>`
+    pub async fn put_group_member(
+        &self,
+        group_id: Uuid,
+        member_id: Uuid,
+        key: &GroupKeyInfo,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url(&format!("groups/{group_id}/members/{member_id}"))?)
+                .json(key)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `DELETE /groups/<group-uuid>/members/<member-uuid>`
+    pub async fn delete_group_member(
+        &self,
+        group_id: Uuid,
+        member_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url(&format!("groups/{group_id}/members/{member_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /groups/<group-uuid>/members/<member-uuid>/key`
+    pub async fn group_member_key(
+        &self,
+        group_id: Uuid,
+        member_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<GroupKeyInfo> {
+        self.send(
+            self.http
+                .get(self.url(&format!("groups/{group_id}/members/{member_id}/key"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /items/<uuid>`
+    ///
+    /// Returns the encrypted item contents and their key list, along with their `ETag`.
+    pub async fn item_contents(
+        &self,
+        item_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<(ItemContents, String)> {
+        let res = self
+            .http
+            .get(self.url(&format!("items/{item_id}"))?)
+            .bearer_auth(session_token.to_base64())
+            .send()
+            .await
+            .map_err(transport_error)?;
+        let res = check_status(res).await?;
+        let etag = etag(&res)?;
+        let contents = res.json().await.map_err(transport_error)?;
+        Ok((contents, etag))
+    }
+
+    /// `GET /items/<uuid>/keys`
+    pub async fn item_keys(&self, item_id: Uuid, session_token: &Bytes) -> Result<ItemKeys> {
+        self.send(
+            self.http
+                .get(self.url(&format!("items/{item_id}/keys"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /items/<item-uuid>/keys/<key-uuid>`
+    pub async fn item_key_info(
+        &self,
+        item_id: Uuid,
+        key_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<ItemKeyInfo> {
+        self.send(
+            self.http
+                .get(self.url(&format!("items/{item_id}/keys/{key_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /items/<uuid>/metadata`
+    pub async fn item_metadata(
+        &self,
+        item_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<ItemMetadata> {
+        self.send(
+            self.http
+                .get(self.url(&format!("items/{item_id}/metadata"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `POST /items/new`
+    pub async fn new_item(
+        &self,
+        request: &NewItemRequest,
+        session_token: &Bytes,
+    ) -> Result<NewItemResponse> {
+        self.send(
+            self.http
+                .post(self.url("items/new")?)
+                .json(request)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /items/<uuid>`, with `If-Match: <etag>` if `if_match` is given.
+    ///
+    /// `contents` must already be encrypted. Returns the new `ETag`, and fails with [`ErrorCode::Conflict`] if
+    ///  `if_match` is given and the contents were replaced since it was read.
+    pub async fn put_item_contents(
+        &self,
+        item_id: Uuid,
+        contents: &ItemContents,
+        if_match: Option<&str>,
+        session_token: &Bytes,
+    ) -> Result<String> {
+        let mut req = self
+            .http
+            .put(self.url(&format!("items/{item_id}"))?)
+            .json(contents)
+            .bearer_auth(session_token.to_base64());
+        if let Some(etag) = if_match {
+            req = req.header(reqwest::header::IF_MATCH, etag);
+        }
+        let res = check_status(req.send().await.map_err(transport_error)?).await?;
+        etag(&res)
+    }
+
+    /// `DELETE /items/<uuid>`
+    pub async fn delete_item(&self, item_id: Uuid, session_token: &Bytes) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url(&format!("items/{item_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /items/<uuid>/keys`
+    pub async fn put_item_keys(
+        &self,
+        item_id: Uuid,
+        keys: &ItemKeys,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url(&format!("items/{item_id}/keys"))?)
+                .json(keys)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /items/<item-uuid>/keys/<key-uuid>`
+    pub async fn put_item_key_info(
+        &self,
+        item_id: Uuid,
+        key_id: Uuid,
+        info: &ItemKeyInfo,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url(&format!("items/{item_id}/keys/{key_id}"))?)
+                .json(info)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `GET /items/<item-uuid>/parents`
+    pub async fn item_parents(&self, item_id: Uuid, session_token: &Bytes) -> Result<Vec<Uuid>> {
+        self.send(
+            self.http
+                .get(self.url(&format!("items/{item_id}/parents"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `PUT /items/<item-uuid>/parents/<vault-uuid>`
+    pub async fn put_item_parent(
+        &self,
+        item_id: Uuid,
+        vault_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .put(self.url(&format!("items/{item_id}/parents/{vault_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// `DELETE /items/<item-uuid>/parents/<vault-uuid>`
+    pub async fn delete_item_parent(
+        &self,
+        item_id: Uuid,
+        vault_id: Uuid,
+        session_token: &Bytes,
+    ) -> Result<()> {
+        self.send_empty(
+            self.http
+                .delete(self.url(&format!("items/{item_id}/parents/{vault_id}"))?)
+                .bearer_auth(session_token.to_base64()),
+        )
+        .await
+    }
+
+    /// Registers a new user on the server.
+    ///
+    /// A new key pair is generated with `cipher`, and the private key is sealed with [`PRIV_KEY_CIPHER`] under a key
+    ///  derived from `password` according to `kdf_params` before anything is sent. Neither the password nor the
+    ///  private key leave the client.
+    ///
+    /// Fresh parameters can be obtained from [`argon2id_params`](crate::cipher::kdf::argon2id_params)
+    ///  or [`pbkdf2_params`](crate::cipher::kdf::pbkdf2_params).
+    pub async fn register(
+        &self,
+        user_address: &str,
+        password: &str,
+        key_alg: AsymmetricCipherAlgorithm,
+        kdf_base_digest_alg: DigestAlgorithm,
+        kdf_params: KdfParams,
+        cipher: &mut (dyn AsymmetricCipherSpi + Send),
+    ) -> Result<Uuid> {
+        cipher.init(key_alg).await?;
+        let key_pair = cipher.generate_key_pair().await?;
+
+        let mut sealing_cipher = DefaultSymmetricCipher::new();
+        sealing_cipher.init(PRIV_KEY_CIPHER).await?;
+        let wrapping_key = derive_wrapping_key(
+            password,
+            user_address,
+            kdf_base_digest_alg,
+            Some(&kdf_params),
+            sealing_cipher.key_len()?,
+        )?;
+        let (priv_key_iv, secured_private_key) = seal_private_key(
+            &mut sealing_cipher,
+            PRIV_KEY_CIPHER,
+            &wrapping_key,
+            &key_pair.private_key,
+        )
+        .await?;
+
+        let req = NewUserRequest {
+            user_address: user_address.to_string(),
+            initial_auth: UserAuth {
+                kdf_base_digest_alg,
+                kdf_params: Some(kdf_params),
+                priv_key_cipher: Some(PRIV_KEY_CIPHER),
+                auth_key_alg: key_alg,
+                pub_key: key_pair.public_key,
+                priv_key_iv,
+                secured_private_key,
+            },
+        };
+
+        Ok(self.new_user(&req).await?.user_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base_url_keeps_its_path() {
+        for base in [
+            "https://example.com/passman",
+            "https://example.com/passman/",
+        ] {
+            let client = Client::new(base.parse().unwrap());
+            assert_eq!(client.base_url().as_str(), "https://example.com/passman/");
+            assert_eq!(
+                client.url("users/new").unwrap().as_str(),
+                "https://example.com/passman/users/new"
+            );
+        }
+
+        let client = Client::new("https://example.com".parse().unwrap());
+        assert_eq!(
+            client.url("hello").unwrap().as_str(),
+            "https://example.com/hello"
+        );
+    }
+}
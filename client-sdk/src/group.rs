@@ -0,0 +1,142 @@
+//! Groups, and the group keys that items are shared with.
+//!
+//! The server only stores the group key wrapped for each member. A user that is a member through other groups
+//!  recovers the key by unwrapping the keys of those groups in turn.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode, Result},
+    http::api::{
+        group::{GroupKeyInfo, NewGroupRequest, PrincipalKind},
+        item::KEY_WRAP_CIPHER,
+    },
+};
+use uuid::Uuid;
+
+use crate::{
+    auth::ClientAuthentication,
+    cipher::{unwrap_item_key, wrap_item_key, AsymmetricCipherSpi, SymmetricCipherSpi},
+};
+
+/// Creates a group with a new group key, and returns its ID and key.
+///
+/// The authenticated user becomes the owner of the group and its first member.
+pub async fn create_group<C: AsymmetricCipherSpi + Send>(
+    auth: &mut ClientAuthentication<C>,
+    display_name: Option<String>,
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+) -> Result<(Uuid, Bytes)> {
+    cipher.init(KEY_WRAP_CIPHER).await?;
+    let key = cipher.generate_key().await?;
+    let creator_key = GroupKeyInfo {
+        secured_item_key: auth.seal(&key).await?,
+        item_key_iv: Bytes::new(Vec::new()),
+        item_auth_tag: None,
+    };
+
+    let request = NewGroupRequest {
+        display_name,
+        key_id: Uuid::new_v4(),
+        creator_key,
+    };
+    let group_id = auth
+        .client()
+        .new_group(&request, auth.session_token()?)
+        .await?
+        .group_id;
+    Ok((group_id, key))
+}
+
+/// Recovers the key of `group_id` for the authenticated user, through the groups it is a member of if needed.
+pub async fn group_key<C: AsymmetricCipherSpi + Send>(
+    auth: &mut ClientAuthentication<C>,
+    group_id: Uuid,
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+) -> Result<Bytes> {
+    let user_id = auth.user_id();
+    let client = auth.client().clone();
+    let token = auth.session_token()?.clone();
+
+    // Search the member groups for one that the user is a direct member of, remembering the group that
+    //  contains each
+    let mut containing = HashMap::new();
+    let mut seen = HashSet::from([group_id]);
+    let mut pending = VecDeque::from([group_id]);
+    let mut found = None;
+    while let Some(id) = pending.pop_front() {
+        let info = match client.group(id, &token).await {
+            Ok(info) => info,
+            Err(e) if id != group_id && *e.code() == ErrorCode::PermissionDenied => continue,
+            Err(e) => return Err(e),
+        };
+        if info
+            .members
+            .iter()
+            .any(|member| member.kind == PrincipalKind::User && member.member_id == user_id)
+        {
+            found = Some(id);
+            break;
+        }
+        for member in info.members {
+            if member.kind == PrincipalKind::Group && seen.insert(member.member_id) {
+                containing.insert(member.member_id, id);
+                pending.push_back(member.member_id);
+            }
+        }
+    }
+    let mut current = found.ok_or_else(|| {
+        Error::new(
+            ErrorCode::PermissionDenied,
+            format!("not a member of group {group_id}"),
+        )
+    })?;
+
+    let sealed = client.group_member_key(current, user_id, &token).await?;
+    let mut key = auth.unseal(&sealed.secured_item_key).await?;
+    while let Some(&parent) = containing.get(&current) {
+        let wrapped = client.group_member_key(parent, current, &token).await?;
+        key = unwrap_item_key(cipher, &key, &wrapped).await?;
+        current = parent;
+    }
+    Ok(key)
+}
+
+/// Adds a user to a group, sealing the group key to the user's public key with `asymmetric`.
+pub async fn add_user<C: AsymmetricCipherSpi + Send>(
+    auth: &mut ClientAuthentication<C>,
+    group_id: Uuid,
+    user_id: Uuid,
+    symmetric: &mut (dyn SymmetricCipherSpi + Send),
+    asymmetric: &mut (dyn AsymmetricCipherSpi + Send),
+) -> Result<()> {
+    let key = group_key(auth, group_id, symmetric).await?;
+    let token = auth.session_token()?;
+    let public_key = auth.client().user_public_key(user_id, token).await?;
+
+    asymmetric.init(public_key.pub_key_alg).await?;
+    let sealed = GroupKeyInfo {
+        secured_item_key: asymmetric.seal(&public_key.pub_key, &key).await?,
+        item_key_iv: Bytes::new(Vec::new()),
+        item_auth_tag: None,
+    };
+    auth.client()
+        .put_group_member(group_id, user_id, &sealed, token)
+        .await
+}
+
+/// Adds the group `member_id` to a group, wrapping the group key with the key of `member_id`.
+pub async fn add_group<C: AsymmetricCipherSpi + Send>(
+    auth: &mut ClientAuthentication<C>,
+    group_id: Uuid,
+    member_id: Uuid,
+    cipher: &mut (dyn SymmetricCipherSpi + Send),
+) -> Result<()> {
+    let key = group_key(auth, group_id, cipher).await?;
+    let member_key = group_key(auth, member_id, cipher).await?;
+    let wrapped = wrap_item_key(cipher, &member_key, &key).await?;
+    auth.client()
+        .put_group_member(group_id, member_id, &wrapped, auth.session_token()?)
+        .await
+}
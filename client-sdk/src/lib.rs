@@ -1,3 +1,9 @@
+pub mod auth;
 pub mod cipher;
+pub mod client;
+pub mod group;
 pub mod macros;
+pub mod otp;
+pub mod search;
 pub mod storage;
+pub mod vault;
@@ -0,0 +1,377 @@
+//! One-time password generation for OTP items.
+
+use common::{
+    error::{Error, ErrorCode, Result},
+    item::{self, TotpAlgorithm, TotpGeneratorItem},
+};
+use hmac::{Hmac, Mac};
+use time::{Duration, OffsetDateTime};
+
+use crate::storage;
+
+pub mod otpauth;
+
+/// The largest number of digits a generated code can have.
+pub const MAX_DIGITS: u32 = 10;
+
+fn otp_error(text: impl Into<String>) -> Error {
+    Error::new(ErrorCode::InvalidRequest, text)
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], counter: u64) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).unwrap();
+    mac.update(&counter.to_be_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Computes the HOTP value of RFC 4226 for `counter`, as a zero-padded string of `digits` digits.
+pub fn hotp(alg: TotpAlgorithm, key: &[u8], counter: u64, digits: u32) -> Result<String> {
+    if digits == 0 || digits > MAX_DIGITS {
+        return Err(otp_error(format!("unsupported number of digits {digits}")));
+    }
+
+    let mac = match alg {
+        TotpAlgorithm::Sha1 => hmac::<Hmac<sha1::Sha1>>(key, counter),
+        TotpAlgorithm::Sha256 => hmac::<Hmac<sha2::Sha256>>(key, counter),
+        TotpAlgorithm::Sha512 => hmac::<Hmac<sha2::Sha512>>(key, counter),
+    };
+
+    // Dynamic truncation (RFC 4226 section 5.3)
+    let offset = (mac[mac.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes(mac[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+    let code = u64::from(binary) % 10u64.pow(digits);
+    Ok(format!("{code:0width$}", width = digits as usize))
+}
+
+/// The codes of a TOTP generator at a point in time.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct TotpCode {
+    /// The code for the current time step.
+    pub code: String,
+    /// The time until the current time step ends.
+    pub remaining: Duration,
+    /// The code for the following time step.
+    pub next_code: String,
+}
+
+impl TotpCode {
+    /// [`TotpCode::remaining`] rounded up to whole seconds, for display.
+    pub fn seconds_remaining(&self) -> u64 {
+        let seconds = self.remaining.whole_seconds() as u64;
+        if self.remaining.subsec_nanoseconds() > 0 {
+            seconds + 1
+        } else {
+            seconds
+        }
+    }
+}
+
+/// Computes the RFC 6238 time step that contains `at`, and the time until it ends.
+fn time_step(item: &TotpGeneratorItem, at: OffsetDateTime) -> Result<(u64, Duration)> {
+    let step = item.totp_step.0;
+    if !step.is_positive() {
+        return Err(otp_error(format!("invalid TOTP step {}", item.totp_step)));
+    }
+    let epoch = item.totp_epoch.unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    let elapsed = at - epoch;
+    if elapsed.is_negative() {
+        return Err(otp_error("time is before the TOTP epoch"));
+    }
+
+    let step_nanos = step.whole_nanoseconds();
+    let elapsed_nanos = elapsed.whole_nanoseconds();
+    let counter = (elapsed_nanos / step_nanos) as u64;
+    let into_step = elapsed_nanos % step_nanos;
+    Ok((
+        counter,
+        Duration::nanoseconds((step_nanos - into_step) as i64),
+    ))
+}
+
+/// Computes the code of a TOTP generator at `at`.
+pub fn totp_at(item: &TotpGeneratorItem, at: OffsetDateTime) -> Result<TotpCode> {
+    let (counter, remaining) = time_step(item, at)?;
+    Ok(TotpCode {
+        code: hotp(item.alg, &item.totp_key, counter, item.totp_digits)?,
+        remaining,
+        next_code: hotp(item.alg, &item.totp_key, counter + 1, item.totp_digits)?,
+    })
+}
+
+/// Computes the current code of a TOTP generator.
+pub fn totp(item: &TotpGeneratorItem) -> Result<TotpCode> {
+    totp_at(item, OffsetDateTime::now_utc())
+}
+
+/// How many times [`next_hotp_code`] tries to write back the counter when it was incremented concurrently.
+pub const HOTP_UPDATE_ATTEMPTS: usize = 5;
+
+/// Generates the next code of a stored HOTP generator item, and writes back the incremented counter.
+///
+/// The counter is written with [`storage::Item::update`], so a code is only returned once the counter it was
+///  generated from has been consumed, and two devices never return the same code.
+pub async fn next_hotp_code(item: &mut dyn storage::Item) -> Result<String> {
+    let mut attempts = 1;
+    loop {
+        let mut code = None;
+        let result = item
+            .update(&mut |contents| {
+                let item::Item::HotpGenerator(mut generator) =
+                    item::Item::from_json(contents).map_err(|e| otp_error(e.to_string()))?
+                else {
+                    return Err(otp_error("item is not an HOTP generator"));
+                };
+                code = Some(hotp(
+                    generator.alg,
+                    &generator.hotp_key,
+                    generator.hotp_counter,
+                    generator.hotp_digits,
+                )?);
+                generator.hotp_counter = generator
+                    .hotp_counter
+                    .checked_add(1)
+                    .ok_or_else(|| otp_error("HOTP counter overflowed"))?;
+                item::Item::from(generator)
+                    .to_json()
+                    .map_err(|e| otp_error(e.to_string()))
+            })
+            .await;
+
+        match result {
+            Ok(()) => return Ok(code.unwrap()),
+            Err(e) if *e.code() == ErrorCode::Conflict && attempts < HOTP_UPDATE_ATTEMPTS => {
+                attempts += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::io::{Cursor, Read};
+    use common::{
+        data::{Bytes, IsoDuration},
+        http::api::item::{ItemMetadata, NewItemRequest},
+        item::HotpGeneratorItem,
+    };
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        macros::async_trait,
+        storage::{local::test::TempVault, Storage},
+    };
+
+    fn item(alg: TotpAlgorithm, key: &[u8]) -> TotpGeneratorItem {
+        TotpGeneratorItem {
+            display_name: None,
+            url: "example.com".parse().unwrap(),
+            alg,
+            totp_epoch: None,
+            totp_step: IsoDuration::seconds(30),
+            totp_digits: 8,
+            totp_key: Bytes::from(key),
+        }
+    }
+
+    const SHA1_SEED: &[u8] = b"12345678901234567890";
+    const SHA256_SEED: &[u8] = b"12345678901234567890123456789012";
+    const SHA512_SEED: &[u8] = b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    // RFC 6238 Appendix B
+    const VECTORS: &[(i64, &str, &str, &str)] = &[
+        (59, "94287082", "46119246", "90693936"),
+        (1111111109, "07081804", "68084774", "25091201"),
+        (1111111111, "14050471", "67062674", "99943326"),
+        (1234567890, "89005924", "91819424", "93441116"),
+        (2000000000, "69279037", "90698825", "38618901"),
+        (20000000000, "65353130", "77737706", "47863826"),
+    ];
+
+    #[test]
+    fn rfc6238_vectors() {
+        for &(time, sha1, sha256, sha512) in VECTORS {
+            let at = OffsetDateTime::from_unix_timestamp(time).unwrap();
+            let code = totp_at(&item(TotpAlgorithm::Sha1, SHA1_SEED), at).unwrap();
+            assert_eq!(code.code, sha1, "SHA1 at {time}");
+            let code = totp_at(&item(TotpAlgorithm::Sha256, SHA256_SEED), at).unwrap();
+            assert_eq!(code.code, sha256, "SHA256 at {time}");
+            let code = totp_at(&item(TotpAlgorithm::Sha512, SHA512_SEED), at).unwrap();
+            assert_eq!(code.code, sha512, "SHA512 at {time}");
+        }
+    }
+
+    #[test]
+    fn remaining_and_next_code() {
+        let item = item(TotpAlgorithm::Sha1, SHA1_SEED);
+        let code = totp_at(&item, OffsetDateTime::from_unix_timestamp(59).unwrap()).unwrap();
+        assert_eq!(code.seconds_remaining(), 1);
+        let next = totp_at(&item, OffsetDateTime::from_unix_timestamp(60).unwrap()).unwrap();
+        assert_eq!(code.next_code, next.code);
+        assert_eq!(next.seconds_remaining(), 30);
+    }
+
+    #[test]
+    fn epoch_and_step() {
+        let mut shifted = item(TotpAlgorithm::Sha1, SHA1_SEED);
+        shifted.totp_epoch = Some(OffsetDateTime::from_unix_timestamp(1000).unwrap());
+        shifted.totp_step = "PT1M".parse().unwrap();
+        // 1000 + 2 * 60 + 59 is in time step 2, the same as 59 * 2 is with the default parameters.
+        let code = totp_at(
+            &shifted,
+            OffsetDateTime::from_unix_timestamp(1000 + 2 * 60 + 59).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            code.code,
+            hotp(TotpAlgorithm::Sha1, SHA1_SEED, 2, 8).unwrap()
+        );
+        assert_eq!(code.seconds_remaining(), 1);
+
+        assert!(totp_at(&shifted, OffsetDateTime::from_unix_timestamp(999).unwrap()).is_err());
+    }
+
+    // RFC 4226 Appendix D
+    const HOTP_VECTORS: [&str; 10] = [
+        "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583", "399871",
+        "520489",
+    ];
+
+    #[test]
+    fn rfc4226_vectors() {
+        for (counter, expected) in HOTP_VECTORS.iter().enumerate() {
+            let code = hotp(TotpAlgorithm::Sha1, SHA1_SEED, counter as u64, 6).unwrap();
+            assert_eq!(code, *expected, "counter {counter}");
+        }
+    }
+
+    fn hotp_item(counter: u64) -> Vec<u8> {
+        item::Item::from(HotpGeneratorItem {
+            display_name: None,
+            url: "example.com".parse().unwrap(),
+            alg: TotpAlgorithm::Sha1,
+            hotp_counter: counter,
+            hotp_digits: 6,
+            hotp_key: Bytes::from(SHA1_SEED),
+        })
+        .to_json()
+        .unwrap()
+    }
+
+    fn hotp_counter(contents: &[u8]) -> u64 {
+        match item::Item::from_json(contents).unwrap() {
+            item::Item::HotpGenerator(generator) => generator.hotp_counter,
+            other => panic!("unexpected item {other:?}"),
+        }
+    }
+
+    /// An item whose first `conflicts` updates fail as if another device had used a code in the meantime.
+    struct ContendedItem {
+        contents: Vec<u8>,
+        conflicts: usize,
+        updates: usize,
+    }
+
+    #[async_trait]
+    impl storage::Item for ContendedItem {
+        fn id(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        async fn metadata(&mut self) -> Result<ItemMetadata> {
+            Err(Error::new(
+                ErrorCode::Internal,
+                "the contended item has no metadata",
+            ))
+        }
+
+        async fn read(&mut self) -> Result<Box<dyn Read + Unpin + Send + '_>> {
+            Ok(Box::new(Cursor::new(self.contents.clone())))
+        }
+
+        async fn write(&mut self, contents: &mut (dyn Read + Unpin + Send)) -> Result<()> {
+            self.contents = storage::read_contents(contents).await?;
+            Ok(())
+        }
+
+        async fn update(
+            &mut self,
+            update: &mut (dyn for<'b> FnMut(&'b [u8]) -> Result<Vec<u8>> + Send),
+        ) -> Result<()> {
+            self.updates += 1;
+            let updated = update(&self.contents)?;
+            if self.conflicts > 0 {
+                self.conflicts -= 1;
+                self.contents = hotp_item(hotp_counter(&self.contents) + 1);
+                return Err(Error::new(ErrorCode::Conflict, "changed concurrently"));
+            }
+            self.contents = updated;
+            Ok(())
+        }
+
+        async fn delete(self: Box<Self>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn next_hotp_code_retries_conflicts() {
+        let mut item = ContendedItem {
+            contents: hotp_item(0),
+            conflicts: 2,
+            updates: 0,
+        };
+        let code = async_std::task::block_on(next_hotp_code(&mut item)).unwrap();
+        // The codes for counters 0 and 1 were used by the conflicting writers
+        assert_eq!(code, HOTP_VECTORS[2]);
+        assert_eq!(item.updates, 3);
+        assert_eq!(hotp_counter(&item.contents), 3);
+    }
+
+    #[test]
+    fn next_hotp_code_gives_up() {
+        let mut item = ContendedItem {
+            contents: hotp_item(0),
+            conflicts: usize::MAX,
+            updates: 0,
+        };
+        let err = async_std::task::block_on(next_hotp_code(&mut item))
+            .err()
+            .unwrap();
+        assert_eq!(*err.code(), ErrorCode::Conflict);
+        assert_eq!(item.updates, HOTP_UPDATE_ATTEMPTS);
+    }
+
+    #[test]
+    fn next_hotp_code_across_handles() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut first = vault.create().await;
+            let id = first
+                .create_item(
+                    NewItemRequest {
+                        content_type: "application/json".to_string(),
+                        base_acl: Vec::new(),
+                    },
+                    &mut Cursor::new(hotp_item(0)),
+                )
+                .await
+                .unwrap()
+                .id();
+            let mut second = vault.open().await;
+
+            let mut codes = Vec::new();
+            for round in 0..4 {
+                let storage = if round % 2 == 0 {
+                    &mut first
+                } else {
+                    &mut second
+                };
+                let mut item = storage.item(id).await.unwrap();
+                codes.push(next_hotp_code(&mut *item).await.unwrap());
+            }
+            assert_eq!(codes, HOTP_VECTORS[..4]);
+        });
+    }
+}
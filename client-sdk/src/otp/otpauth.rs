@@ -0,0 +1,346 @@
+//! Import and export of `otpauth://totp/` URIs, as found in 2FA QR codes.
+//!
+//! The format is the Key URI Format used by most authenticator apps:
+//!  `otpauth://totp/<issuer>:<account>?secret=<base32>&issuer=<issuer>&algorithm=SHA1&digits=6&period=30`.
+
+use common::{
+    data::{Bytes, IsoDuration},
+    item::{TotpAlgorithm, TotpGeneratorItem, UrlMatcher},
+};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Url;
+use time::{Duration, OffsetDateTime};
+
+/// Characters that are left unescaped in labels and parameter values.
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub const DEFAULT_DIGITS: u32 = 6;
+
+pub const DEFAULT_PERIOD: u32 = 30;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OtpAuthError {
+    /// The string is not an `otpauth://` URI.
+    InvalidUri(String),
+    /// The URI is for a kind of generator other than TOTP, such as `hotp`.
+    UnsupportedType(String),
+    /// The URI has a parameter that cannot be represented, such as an HOTP `counter`.
+    UnsupportedParameter(String),
+    MissingSecret,
+    /// The secret is not valid base32.
+    InvalidSecret,
+    UnsupportedAlgorithm(String),
+    InvalidDigits(String),
+    InvalidPeriod(String),
+    MissingAccount,
+}
+
+impl core::fmt::Display for OtpAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUri(e) => write!(f, "invalid otpauth URI: {e}"),
+            Self::UnsupportedType(ty) if ty == "hotp" => {
+                f.write_str("HOTP URIs are not supported for TOTP items")
+            }
+            Self::UnsupportedType(ty) => write!(f, "unsupported otpauth type {ty}"),
+            Self::UnsupportedParameter(param) => {
+                write!(f, "unsupported otpauth parameter {param}")
+            }
+            Self::MissingSecret => f.write_str("otpauth URI has no secret"),
+            Self::InvalidSecret => f.write_str("otpauth secret is not valid base32"),
+            Self::UnsupportedAlgorithm(alg) => write!(f, "unsupported otpauth algorithm {alg}"),
+            Self::InvalidDigits(digits) => write!(f, "invalid otpauth digits {digits}"),
+            Self::InvalidPeriod(period) => write!(f, "invalid otpauth period {period}"),
+            Self::MissingAccount => f.write_str("otpauth URI has no account name"),
+        }
+    }
+}
+
+impl std::error::Error for OtpAuthError {}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for c in s.bytes().filter(|c| !matches!(c, b' ' | b'-' | b'=')) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = buffer << 5 | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for &b in data {
+        buffer = buffer << 8 | b as u64;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    out
+}
+
+/// The contents of an `otpauth://totp/` URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtpAuthUri {
+    pub issuer: Option<String>,
+    pub account: String,
+    pub secret: Bytes,
+    pub alg: TotpAlgorithm,
+    pub digits: u32,
+    /// The time step, in seconds.
+    pub period: u32,
+}
+
+impl OtpAuthUri {
+    pub fn parse(uri: &str) -> Result<Self, OtpAuthError> {
+        let url = Url::parse(uri).map_err(|e| OtpAuthError::InvalidUri(e.to_string()))?;
+        if url.scheme() != "otpauth" {
+            return Err(OtpAuthError::InvalidUri(format!(
+                "unexpected scheme {}",
+                url.scheme()
+            )));
+        }
+        match url.host_str() {
+            Some(ty) if ty.eq_ignore_ascii_case("totp") => {}
+            Some(ty) => return Err(OtpAuthError::UnsupportedType(ty.to_ascii_lowercase())),
+            None => return Err(OtpAuthError::InvalidUri("missing type".to_string())),
+        }
+
+        let label = percent_decode_str(url.path().trim_start_matches('/'))
+            .decode_utf8()
+            .map_err(|e| OtpAuthError::InvalidUri(e.to_string()))?;
+        let (mut issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim()),
+            None => (None, label.trim()),
+        };
+        if account.is_empty() {
+            return Err(OtpAuthError::MissingAccount);
+        }
+
+        let mut secret = None;
+        let mut alg = TotpAlgorithm::Sha1;
+        let mut digits = DEFAULT_DIGITS;
+        let mut period = DEFAULT_PERIOD;
+        for (key, value) in url.query_pairs() {
+            match &*key {
+                "secret" => {
+                    secret = Some(base32_decode(&value).ok_or(OtpAuthError::InvalidSecret)?);
+                }
+                "issuer" => issuer = Some(value.into_owned()),
+                "algorithm" => {
+                    alg = match &*value.to_ascii_uppercase() {
+                        "SHA1" => TotpAlgorithm::Sha1,
+                        "SHA256" => TotpAlgorithm::Sha256,
+                        "SHA512" => TotpAlgorithm::Sha512,
+                        _ => return Err(OtpAuthError::UnsupportedAlgorithm(value.into_owned())),
+                    }
+                }
+                "digits" => {
+                    digits = value
+                        .parse()
+                        .ok()
+                        .filter(|digits| (1..=super::MAX_DIGITS).contains(digits))
+                        .ok_or_else(|| OtpAuthError::InvalidDigits(value.to_string()))?
+                }
+                "period" => {
+                    period = value
+                        .parse()
+                        .ok()
+                        .filter(|&period| period > 0)
+                        .ok_or_else(|| OtpAuthError::InvalidPeriod(value.to_string()))?
+                }
+                "counter" => return Err(OtpAuthError::UnsupportedParameter(key.into_owned())),
+                // Vendor extensions, such as `image`, do not affect the generated codes.
+                _ => {}
+            }
+        }
+
+        let secret = secret
+            .filter(|secret| !secret.is_empty())
+            .ok_or(OtpAuthError::MissingSecret)?;
+
+        Ok(Self {
+            issuer: issuer.filter(|issuer| !issuer.is_empty()),
+            account: account.to_string(),
+            secret: Bytes::new(secret),
+            alg,
+            digits,
+            period,
+        })
+    }
+
+    /// The label of the URI, `<issuer>:<account>` or `<account>`.
+    pub fn label(&self) -> String {
+        match &self.issuer {
+            Some(issuer) => format!("{issuer}:{}", self.account),
+            None => self.account.clone(),
+        }
+    }
+
+    /// Converts the URI into a TOTP item for `url`. The label becomes the `display_name` of the item.
+    pub fn into_item(self, url: UrlMatcher) -> TotpGeneratorItem {
+        TotpGeneratorItem {
+            display_name: Some(self.label()),
+            url,
+            alg: self.alg,
+            totp_epoch: None,
+            totp_step: IsoDuration::seconds(self.period.into()),
+            totp_digits: self.digits,
+            totp_key: self.secret,
+        }
+    }
+
+    /// Builds a URI from a TOTP item, taking the issuer and account from its `display_name` as in
+    ///  [`OtpAuthUri::into_item`].
+    ///
+    /// Fails if the item has an epoch other than the Unix Epoch, or a step that is not a whole number of seconds,
+    ///  as neither can be represented in a URI.
+    pub fn from_item(item: &TotpGeneratorItem) -> Result<Self, OtpAuthError> {
+        if item
+            .totp_epoch
+            .is_some_and(|epoch| epoch != OffsetDateTime::UNIX_EPOCH)
+        {
+            return Err(OtpAuthError::UnsupportedParameter("totp_epoch".to_string()));
+        }
+        let step: Duration = item.totp_step.into();
+        let period = u32::try_from(step.whole_seconds())
+            .ok()
+            .filter(|&period| period > 0 && step.subsec_nanoseconds() == 0)
+            .ok_or_else(|| OtpAuthError::InvalidPeriod(item.totp_step.to_string()))?;
+
+        let label = item.display_name.as_deref().unwrap_or_default();
+        let (issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim()),
+            None => (None, label.trim()),
+        };
+        if account.is_empty() {
+            return Err(OtpAuthError::MissingAccount);
+        }
+
+        Ok(Self {
+            issuer: issuer.filter(|issuer| !issuer.is_empty()),
+            account: account.to_string(),
+            secret: item.totp_key.clone(),
+            alg: item.alg,
+            digits: item.totp_digits,
+            period,
+        })
+    }
+}
+
+impl core::fmt::Display for OtpAuthUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("otpauth://totp/")?;
+        if let Some(issuer) = &self.issuer {
+            write!(f, "{}:", utf8_percent_encode(issuer, URI_COMPONENT))?;
+        }
+        write!(
+            f,
+            "{}?secret={}",
+            utf8_percent_encode(&self.account, URI_COMPONENT),
+            base32_encode(&self.secret)
+        )?;
+        if let Some(issuer) = &self.issuer {
+            write!(f, "&issuer={}", utf8_percent_encode(issuer, URI_COMPONENT))?;
+        }
+        let alg = match self.alg {
+            TotpAlgorithm::Sha1 => "SHA1",
+            TotpAlgorithm::Sha256 => "SHA256",
+            TotpAlgorithm::Sha512 => "SHA512",
+        };
+        write!(
+            f,
+            "&algorithm={alg}&digits={}&period={}",
+            self.digits, self.period
+        )
+    }
+}
+
+impl core::str::FromStr for OtpAuthUri {
+    type Err = OtpAuthError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_render() {
+        let uri = OtpAuthUri::parse(
+            "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA512&digits=8&period=60",
+        )
+        .unwrap();
+        assert_eq!(uri.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(uri.account, "john.doe@email.com");
+        assert_eq!(uri.alg, TotpAlgorithm::Sha512);
+        assert_eq!((uri.digits, uri.period), (8, 60));
+        assert_eq!(
+            base32_encode(&uri.secret),
+            "HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ"
+        );
+
+        let item = uri.clone().into_item("acme.example".parse().unwrap());
+        assert_eq!(item.totp_step, IsoDuration::seconds(60));
+        let exported = OtpAuthUri::from_item(&item).unwrap();
+        assert_eq!(exported, uri);
+        assert_eq!(OtpAuthUri::parse(&exported.to_string()).unwrap(), uri);
+    }
+
+    #[test]
+    fn defaults() {
+        let uri = OtpAuthUri::parse("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP").unwrap();
+        assert_eq!(uri.issuer, None);
+        assert_eq!(uri.alg, TotpAlgorithm::Sha1);
+        assert_eq!((uri.digits, uri.period), (DEFAULT_DIGITS, DEFAULT_PERIOD));
+        assert_eq!(&*uri.secret, b"Hello!\xde\xad\xbe\xef");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            OtpAuthUri::parse("otpauth://hotp/alice?secret=JBSWY3DPEHPK3PXP&counter=1"),
+            Err(OtpAuthError::UnsupportedType("hotp".to_string()))
+        );
+        assert_eq!(
+            OtpAuthUri::parse("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP&counter=1"),
+            Err(OtpAuthError::UnsupportedParameter("counter".to_string()))
+        );
+        assert_eq!(
+            OtpAuthUri::parse("otpauth://totp/alice"),
+            Err(OtpAuthError::MissingSecret)
+        );
+        assert_eq!(
+            OtpAuthUri::parse("otpauth://totp/alice?secret=JBSWY3DPEHPK3PX1"),
+            Err(OtpAuthError::InvalidSecret)
+        );
+        assert_eq!(
+            OtpAuthUri::parse("otpauth://totp/alice?secret=JBSWY3DPEHPK3PXP&algorithm=MD5"),
+            Err(OtpAuthError::UnsupportedAlgorithm("MD5".to_string()))
+        );
+    }
+}
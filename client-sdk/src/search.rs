@@ -0,0 +1,582 @@
+//! A local search index over decrypted items.
+//!
+//! Items are end-to-end encrypted, so the server cannot search them. The client indexes the `display_name`,
+//!  `login_id` and URL of each item, and stores the index encrypted with a key of its choosing.
+
+use std::path::Path;
+
+use async_std::io::ReadExt;
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode, Result},
+    item::{is_json_content_type, Item},
+    suite::SymmetricCipherAlgorithm,
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use time::PrimitiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    cipher::{SymmetricCipherSpi, SymmetricCiphertext},
+    storage::Storage,
+};
+
+/// The current version of the serialized index.
+pub const INDEX_FORMAT_VERSION: u32 = 1;
+
+/// The cipher used to encrypt the index at rest.
+pub const INDEX_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
+const EXACT_SCORE: u32 = 4;
+const PREFIX_SCORE: u32 = 3;
+const FUZZY_SCORE: u32 = 1;
+
+fn index_error(e: impl core::fmt::Display) -> Error {
+    Error::new(ErrorCode::InvalidData, e.to_string())
+}
+
+/// Splits text into lowercase words, so that `alice@example.com` can be found by `example`.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The number of typos tolerated in a query word of `len` characters.
+fn max_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, or `None` if it is more than `max`.
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[b.len()]).filter(|&distance| distance <= max)
+}
+
+/// Scores how well a query word matches an indexed word.
+fn score_word(query: &str, word: &str) -> u32 {
+    if word == query {
+        return EXACT_SCORE;
+    }
+    if word.starts_with(query) {
+        return PREFIX_SCORE;
+    }
+
+    // Fuzzy matches are also allowed on prefixes, so that a typo in a partially typed word still matches.
+    let max = max_typos(query.chars().count());
+    if max == 0 {
+        return 0;
+    }
+    let query: Vec<char> = query.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let prefix_len = word.len().min(query.len() + max);
+    let matches = (query.len().saturating_sub(max)..=prefix_len)
+        .any(|len| edit_distance(&query, &word[..len], max).is_some());
+    if matches {
+        FUZZY_SCORE
+    } else {
+        0
+    }
+}
+
+/// The indexed fields of an item.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The modification time of the item when it was indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<PrimitiveDateTime>,
+    words: Vec<String>,
+}
+
+impl IndexEntry {
+    fn new(item: &Item, mtime: Option<PrimitiveDateTime>) -> Self {
+        let display_name = item.display_name().map(str::to_string);
+        let login_id = match item {
+            Item::Password(item) => item.login_id.clone(),
+            _ => None,
+        };
+        let url = item.url().map(ToString::to_string);
+
+        let mut words: Vec<String> = [&display_name, &login_id, &url]
+            .into_iter()
+            .flatten()
+            .flat_map(|field| tokenize(field))
+            .collect();
+        words.sort();
+        words.dedup();
+
+        Self {
+            display_name,
+            login_id,
+            url,
+            mtime,
+            words,
+        }
+    }
+
+    /// Scores the entry against the words of a query. Every query word must match.
+    fn score(&self, query: &[String]) -> u32 {
+        let mut total = 0;
+        for query in query {
+            let best = self
+                .words
+                .iter()
+                .map(|word| score_word(query, word))
+                .max()
+                .unwrap_or(0);
+            if best == 0 {
+                return 0;
+            }
+            total += best;
+        }
+        total
+    }
+}
+
+/// A search result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchHit<'a> {
+    pub id: Uuid,
+    pub score: u32,
+    pub entry: &'a IndexEntry,
+}
+
+/// The index in its encrypted form.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SealedIndex {
+    format: u32,
+    cipher: SymmetricCipherAlgorithm,
+    iv: Bytes,
+    auth_tag: Bytes,
+    contents: Bytes,
+}
+
+/// A search index over the `display_name`, `login_id` and URL of items.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchIndex {
+    entries: IndexMap<Uuid, IndexEntry>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&IndexEntry> {
+        self.entries.get(&id)
+    }
+
+    /// Adds or replaces the entry for an item.
+    pub fn insert(&mut self, id: Uuid, item: &Item, mtime: Option<PrimitiveDateTime>) {
+        self.entries.insert(id, IndexEntry::new(item, mtime));
+    }
+
+    pub fn remove(&mut self, id: Uuid) -> Option<IndexEntry> {
+        self.entries.shift_remove(&id)
+    }
+
+    /// Finds the items matching every word of `query`, either exactly, as a prefix, or with a few typos, best
+    ///  matches first.
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        let query: Vec<String> = tokenize(query).collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<SearchHit> = self
+            .entries
+            .iter()
+            .filter_map(|(&id, entry)| {
+                let score = entry.score(&query);
+                (score > 0).then_some(SearchHit { id, score, entry })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.entry.display_name.cmp(&b.entry.display_name))
+        });
+        hits
+    }
+
+    /// Brings the entries for `ids` up to date with `storage`.
+    ///
+    /// Items whose modification time has not changed since they were indexed are not read again. Items that no
+    ///  longer exist, are not stored as JSON or cannot be parsed as an [`Item`] are removed from the index. Returns
+    ///  the number of items that were read.
+    pub async fn refresh<S: Storage + Send + ?Sized>(
+        &mut self,
+        storage: &mut S,
+        ids: impl IntoIterator<Item = Uuid>,
+    ) -> Result<usize> {
+        let mut read = 0;
+        for id in ids {
+            let mut item = match storage.item(id).await {
+                Ok(item) => item,
+                Err(e) if *e.code() == ErrorCode::NotFound => {
+                    self.remove(id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let metadata = item.metadata().await?;
+            if !is_json_content_type(&metadata.content_type) {
+                self.remove(id);
+                continue;
+            }
+            let mtime = metadata.mtime;
+            if self.entries.get(&id).and_then(|entry| entry.mtime) == Some(mtime) {
+                continue;
+            }
+
+            let mut json = Vec::new();
+            item.read()
+                .await?
+                .read_to_end(&mut json)
+                .await
+                .map_err(|e| Error::new(ErrorCode::Transport, e.to_string()))?;
+            read += 1;
+            match Item::from_json(&json) {
+                Ok(parsed) => self.insert(id, &parsed, Some(mtime)),
+                Err(_) => {
+                    self.remove(id);
+                }
+            }
+        }
+        Ok(read)
+    }
+
+    /// Encrypts the index with `key`.
+    pub async fn seal(
+        &self,
+        cipher: &mut (dyn SymmetricCipherSpi + Send),
+        key: &[u8],
+    ) -> Result<Bytes> {
+        let plaintext = serde_json::to_vec(self).map_err(index_error)?;
+        cipher.init(INDEX_CIPHER).await?;
+        let iv = cipher.generate_iv().await?;
+        let SymmetricCiphertext {
+            ciphertext,
+            auth_tag,
+        } = cipher.encrypt(key, &iv, &plaintext).await?;
+
+        let sealed = SealedIndex {
+            format: INDEX_FORMAT_VERSION,
+            cipher: INDEX_CIPHER,
+            iv,
+            auth_tag,
+            contents: ciphertext,
+        };
+        Ok(Bytes::new(
+            serde_json::to_vec(&sealed).map_err(index_error)?,
+        ))
+    }
+
+    /// Decrypts an index encrypted by [`SearchIndex::seal`]. Fails unless it is encrypted with [`INDEX_CIPHER`].
+    pub async fn unseal(
+        cipher: &mut (dyn SymmetricCipherSpi + Send),
+        key: &[u8],
+        sealed: &[u8],
+    ) -> Result<Self> {
+        let sealed: SealedIndex = serde_json::from_slice(sealed).map_err(index_error)?;
+        if sealed.format != INDEX_FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorCode::VersionMismatch,
+                format!("unsupported search index format {}", sealed.format),
+            ));
+        }
+        // The cipher is not chosen by whoever wrote the file, so that it cannot be downgraded
+        if sealed.cipher != INDEX_CIPHER {
+            return Err(Error::new(
+                ErrorCode::UnsupportedAlgorithm,
+                format!(
+                    "search index is encrypted with {}, not {INDEX_CIPHER}",
+                    sealed.cipher
+                ),
+            ));
+        }
+        cipher.init(INDEX_CIPHER).await?;
+        let plaintext = cipher
+            .decrypt(key, &sealed.iv, &sealed.contents, &sealed.auth_tag)
+            .await?;
+        serde_json::from_slice(&plaintext).map_err(index_error)
+    }
+
+    /// Encrypts the index with `key`, and atomically replaces the file at `path` with it.
+    pub async fn save(
+        &self,
+        path: &Path,
+        cipher: &mut (dyn SymmetricCipherSpi + Send),
+        key: &[u8],
+    ) -> Result<()> {
+        let sealed = self.seal(cipher, key).await?;
+        let io_error = |e: std::io::Error| Error::new(ErrorCode::Transport, e.to_string());
+        let tmp = path.with_extension("tmp");
+        async_std::fs::write(&tmp, &*sealed)
+            .await
+            .map_err(io_error)?;
+        async_std::fs::rename(&tmp, path).await.map_err(io_error)
+    }
+
+    /// Reads and decrypts an index saved by [`SearchIndex::save`].
+    pub async fn load(
+        path: &Path,
+        cipher: &mut (dyn SymmetricCipherSpi + Send),
+        key: &[u8],
+    ) -> Result<Self> {
+        let sealed = async_std::fs::read(path)
+            .await
+            .map_err(|e| Error::new(ErrorCode::Transport, e.to_string()))?;
+        Self::unseal(cipher, key, &sealed).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::io::Cursor;
+    use common::item::PasswordItem;
+
+    use super::*;
+    use crate::{
+        cipher::symmetric::DefaultSymmetricCipher,
+        storage::local::test::{create_json, create_raw, TempVault},
+    };
+
+    const KEY: [u8; 32] = [7; 32];
+
+    fn chars(word: &str) -> Vec<char> {
+        word.chars().collect()
+    }
+
+    fn password(name: &str, login_id: &str, url: &str) -> Item {
+        Item::Password(PasswordItem {
+            display_name: Some(name.to_string()),
+            url: url.parse().unwrap(),
+            login_id: Some(login_id.to_string()),
+            login_password: "hunter2".to_string(),
+        })
+    }
+
+    #[test]
+    fn edit_distance_bound() {
+        assert_eq!(
+            edit_distance(&chars("kitten"), &chars("sitting"), 3),
+            Some(3)
+        );
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting"), 2), None);
+        assert_eq!(edit_distance(&chars("abc"), &chars("abc"), 0), Some(0));
+        assert_eq!(edit_distance(&chars(""), &chars("abc"), 3), Some(3));
+        assert_eq!(edit_distance(&chars("a"), &chars("abcd"), 2), None);
+    }
+
+    #[test]
+    fn max_typos_thresholds() {
+        // Three characters tolerate no typos
+        assert_eq!(max_typos(3), 0);
+        assert_eq!(score_word("gut", "git"), 0);
+        // Four to seven characters tolerate one
+        assert_eq!(max_typos(4), 1);
+        assert_eq!(score_word("gihub", "github"), FUZZY_SCORE);
+        assert_eq!(max_typos(7), 1);
+        assert_eq!(score_word("exbmpxe", "example"), 0);
+        // Eight or more tolerate two
+        assert_eq!(max_typos(8), 2);
+        assert_eq!(score_word("passwrod", "password"), FUZZY_SCORE);
+        assert_eq!(score_word("pasxworx", "password"), FUZZY_SCORE);
+        assert_eq!(score_word("paxxworx", "password"), 0);
+    }
+
+    #[test]
+    fn score_word_kinds() {
+        assert_eq!(score_word("github", "github"), EXACT_SCORE);
+        assert_eq!(score_word("git", "github"), PREFIX_SCORE);
+        // A typo in a partially typed word
+        assert_eq!(score_word("gihu", "github"), FUZZY_SCORE);
+        assert_eq!(score_word("gitlab", "github"), 0);
+    }
+
+    #[test]
+    fn search_ranks_and_requires_every_word() {
+        let mut index = SearchIndex::new();
+        let exact = Uuid::from_u128(1);
+        let prefix = Uuid::from_u128(2);
+        let fuzzy = Uuid::from_u128(3);
+        index.insert(exact, &password("Mail", "alice", "mail.example.com"), None);
+        index.insert(prefix, &password("Mailbox", "alice", "mailbox.org"), None);
+        index.insert(fuzzy, &password("Maul", "bob", "maul.example.net"), None);
+
+        let ids = |query| {
+            index
+                .search(query)
+                .iter()
+                .map(|hit| hit.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids("mail"), [exact, prefix, fuzzy]);
+        assert_eq!(ids("mail alice"), [exact, prefix]);
+        assert_eq!(ids("example.com"), [exact]);
+        assert!(ids("").is_empty());
+    }
+
+    #[test]
+    fn refresh_skips_unchanged_items() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut storage = vault.create().await;
+            let mail = create_json(&mut storage, &password("Mail", "alice", "example.com")).await;
+            let bank = create_json(&mut storage, &password("Bank", "alice", "example.org")).await;
+            let missing = Uuid::new_v4();
+
+            let mut index = SearchIndex::new();
+            index.insert(missing, &password("Gone", "alice", "example.net"), None);
+            assert_eq!(
+                index
+                    .refresh(&mut storage, [mail, bank, missing])
+                    .await
+                    .unwrap(),
+                2
+            );
+            assert!(index.get(missing).is_none());
+            assert_eq!(index.refresh(&mut storage, [mail, bank]).await.unwrap(), 0);
+
+            let renamed = password("Webmail", "alice", "example.com");
+            storage
+                .item(mail)
+                .await
+                .unwrap()
+                .write(&mut Cursor::new(renamed.to_json().unwrap()))
+                .await
+                .unwrap();
+            assert_eq!(index.refresh(&mut storage, [mail, bank]).await.unwrap(), 1);
+            assert_eq!(
+                index.get(mail).unwrap().display_name.as_deref(),
+                Some("Webmail")
+            );
+
+            storage.item(bank).await.unwrap().delete().await.unwrap();
+            assert_eq!(index.refresh(&mut storage, [mail, bank]).await.unwrap(), 0);
+            assert!(index.get(bank).is_none());
+            assert_eq!(index.len(), 1);
+        });
+    }
+
+    #[test]
+    fn refresh_skips_other_items() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut storage = vault.create().await;
+            let text = create_raw(&mut storage, "text/plain", b"example.com").await;
+            let broken = create_raw(
+                &mut storage,
+                "application/x-passman-login-password+json",
+                b"{\"type\":",
+            )
+            .await;
+            let mail = create_json(&mut storage, &password("Mail", "alice", "example.com")).await;
+
+            let mut index = SearchIndex::new();
+            index.insert(text, &password("Stale", "alice", "example.com"), None);
+            assert_eq!(
+                index
+                    .refresh(&mut storage, [text, broken, mail])
+                    .await
+                    .unwrap(),
+                2
+            );
+            assert!(index.get(text).is_none());
+            assert!(index.get(broken).is_none());
+            assert_eq!(index.len(), 1);
+            assert_eq!(index.search("example")[0].id, mail);
+        });
+    }
+
+    /// Seals `index`, and lets `change` modify the sealed form before it is serialized again.
+    fn sealed_with(index: &SearchIndex, change: impl FnOnce(&mut SealedIndex)) -> Vec<u8> {
+        let sealed =
+            async_std::task::block_on(index.seal(&mut DefaultSymmetricCipher::new(), &KEY))
+                .unwrap();
+        let mut sealed: SealedIndex = serde_json::from_slice(&sealed).unwrap();
+        change(&mut sealed);
+        serde_json::to_vec(&sealed).unwrap()
+    }
+
+    fn unseal(sealed: &[u8]) -> Result<SearchIndex> {
+        async_std::task::block_on(SearchIndex::unseal(
+            &mut DefaultSymmetricCipher::new(),
+            &KEY,
+            sealed,
+        ))
+    }
+
+    #[test]
+    fn seal_round_trip() {
+        let mut index = SearchIndex::new();
+        index.insert(
+            Uuid::from_u128(1),
+            &password("Mail", "alice", "example.com"),
+            None,
+        );
+
+        assert_eq!(unseal(&sealed_with(&index, |_| {})).unwrap(), index);
+
+        let tampered = sealed_with(&index, |sealed| {
+            let mut contents = sealed.contents.clone().into_inner();
+            contents[0] ^= 1;
+            sealed.contents = Bytes::new(contents);
+        });
+        assert!(unseal(&tampered).is_err());
+
+        let wrong_key = async_std::task::block_on(SearchIndex::unseal(
+            &mut DefaultSymmetricCipher::new(),
+            &[8; 32],
+            &sealed_with(&index, |_| {}),
+        ));
+        assert!(wrong_key.is_err());
+
+        let newer = sealed_with(&index, |sealed| sealed.format = INDEX_FORMAT_VERSION + 1);
+        assert_eq!(
+            *unseal(&newer).err().unwrap().code(),
+            ErrorCode::VersionMismatch
+        );
+
+        let downgraded = sealed_with(&index, |sealed| {
+            sealed.cipher = SymmetricCipherAlgorithm::Aes256Cbc
+        });
+        assert_eq!(
+            *unseal(&downgraded).err().unwrap().code(),
+            ErrorCode::UnsupportedAlgorithm
+        );
+    }
+}
@@ -1,15 +1,43 @@
 use crate::macros::async_trait;
 
-use async_std::io::Read;
-use common::error::{Error, Result};
+use async_std::io::{Read, ReadExt};
+use common::{
+    error::{Error, ErrorCode, Result},
+    http::api::item::{ItemMetadata, NewItemRequest},
+    suite::SymmetricCipherAlgorithm,
+};
 
 use uuid::Uuid;
 
+pub mod http;
+pub mod local;
+
+/// The cipher used to encrypt the contents of new items.
+pub const ITEM_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
 #[async_trait]
 pub trait Storage {
     fn is_unlocked(&self) -> bool;
     fn authentication(&mut self) -> Box<dyn Authentication + '_>;
     async fn item(&mut self, id: Uuid) -> Result<Box<dyn Item + '_>>;
+
+    /// Creates a new item holding `contents`.
+    ///
+    /// A fresh item key is generated and wrapped with the user's root key, and the contents are encrypted with it
+    ///  before they leave the client.
+    async fn create_item(
+        &mut self,
+        request: NewItemRequest,
+        contents: &mut (dyn Read + Unpin + Send),
+    ) -> Result<Box<dyn Item + '_>>;
+
+    /// Records that the vault `vault` contains the item `id`, so that the item inherits the vault's ACL.
+    ///
+    /// This does not change the `content` of the vault; see [`vault::add_to_vault`](crate::vault::add_to_vault).
+    async fn link_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()>;
+
+    /// Removes a record made by [`Storage::link_parent`].
+    async fn unlink_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()>;
 }
 
 #[async_trait]
@@ -18,7 +46,44 @@ pub trait Authentication {
     async fn authenticate(&mut self) -> Result<()>;
 }
 
+#[async_trait]
+impl<A: Authentication + Send + ?Sized> Authentication for &mut A {
+    fn is_unlocked(&self) -> bool {
+        (**self).is_unlocked()
+    }
+
+    async fn authenticate(&mut self) -> Result<()> {
+        (**self).authenticate().await
+    }
+}
+
 #[async_trait]
 pub trait Item {
-    async fn read(&mut self) -> Result<Box<dyn Read + '_>>;
+    fn id(&self) -> Uuid;
+    async fn metadata(&mut self) -> Result<ItemMetadata>;
+    async fn read(&mut self) -> Result<Box<dyn Read + Unpin + Send + '_>>;
+
+    /// Replaces the contents of the item, re-encrypting them with the existing item key.
+    async fn write(&mut self, contents: &mut (dyn Read + Unpin + Send)) -> Result<()>;
+
+    /// Replaces the contents of the item with `update(contents)`, only if nobody else wrote the item in between.
+    ///
+    /// Fails with [`ErrorCode::Conflict`] if the item was changed after it was read, in which case nothing is
+    ///  written.
+    async fn update(
+        &mut self,
+        update: &mut (dyn for<'b> FnMut(&'b [u8]) -> Result<Vec<u8>> + Send),
+    ) -> Result<()>;
+
+    /// Deletes the item, along with its keys.
+    async fn delete(self: Box<Self>) -> Result<()>;
+}
+
+pub(crate) async fn read_contents(contents: &mut (dyn Read + Unpin + Send)) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    contents
+        .read_to_end(&mut buf)
+        .await
+        .map_err(|e| Error::new(ErrorCode::Transport, e.to_string()))?;
+    Ok(buf)
 }
@@ -0,0 +1,721 @@
+use std::collections::HashMap;
+
+use async_std::io::{Cursor, Read};
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode, Result},
+    http::api::{
+        item::{
+            ItemContents, ItemKeyInfo, ItemKeys, ItemMetadata, NewItemRequest, NewItemResponse,
+        },
+        user::UserRootInfo,
+        Hello, PROTOCOL_ID_PASSMAN,
+    },
+};
+use reqwest::Url;
+use uuid::Uuid;
+
+use super::{read_contents, Authentication, Item, Storage, ITEM_CIPHER};
+use crate::{
+    auth::ClientAuthentication,
+    cipher::{
+        asymmetric::DefaultAsymmetricCipher, decrypt_item, encrypt_item,
+        symmetric::DefaultSymmetricCipher, unwrap_item_key, AsymmetricCipherSpi,
+        SymmetricCipherSpi,
+    },
+    client::Client,
+    macros::async_trait,
+};
+
+/// A [`Storage`] backed by a passman server.
+///
+/// Items are fetched in their encrypted form and decrypted locally, and encrypted locally before they are written.
+pub struct HttpStorage<A = DefaultAsymmetricCipher, S = DefaultSymmetricCipher> {
+    hello: Hello,
+    auth: ClientAuthentication<A>,
+    symmetric: S,
+    root: Option<UserRootInfo>,
+    keyring: HashMap<Uuid, Bytes>,
+}
+
+impl HttpStorage {
+    /// Connects to the server at `base_url` as the given user, using the default cipher implementations.
+    ///
+    /// See [`HttpStorage::with_ciphers`].
+    pub async fn connect(
+        base_url: Url,
+        server_id: Option<Uuid>,
+        user_id: Uuid,
+        user_address: &str,
+        password: &str,
+    ) -> Result<Self> {
+        Self::with_ciphers(
+            Client::new(base_url),
+            server_id,
+            user_id,
+            user_address,
+            password,
+            DefaultAsymmetricCipher::new(),
+            DefaultSymmetricCipher::new(),
+        )
+        .await
+    }
+}
+
+impl<A, S> HttpStorage<A, S> {
+    /// Performs the `/hello` handshake with the server behind `client`.
+    ///
+    /// Fails with [`ErrorCode::VersionMismatch`] if the server does not speak the passman protocol, and with
+    ///  [`ErrorCode::ServerMismatch`] if `server_id` is given and does not match the ID of the server. The storage starts locked; use [`Storage::authentication`] to unlock it.
+    pub async fn with_ciphers(
+        client: Client,
+        server_id: Option<Uuid>,
+        user_id: Uuid,
+        user_address: &str,
+        password: &str,
+        asymmetric: A,
+        symmetric: S,
+    ) -> Result<Self> {
+        let hello = client.hello().await?;
+
+        if hello.protocol_id != PROTOCOL_ID_PASSMAN {
+            return Err(Error::new(
+                ErrorCode::VersionMismatch,
+                format!("server speaks unknown protocol {}", hello.protocol_id),
+            ));
+        }
+
+        if let Some(server_id) = server_id {
+            if hello.server_id != server_id {
+                return Err(Error::new(
+                    ErrorCode::ServerMismatch,
+                    format!(
+                        "expected server {} but connected to {}",
+                        server_id, hello.server_id
+                    ),
+                ));
+            }
+        }
+
+        Ok(Self {
+            hello,
+            auth: ClientAuthentication::new(client, user_id, user_address, password, asymmetric),
+            symmetric,
+            root: None,
+            keyring: HashMap::new(),
+        })
+    }
+
+    /// The response to the `/hello` handshake.
+    pub fn hello(&self) -> &Hello {
+        &self.hello
+    }
+
+    pub fn client(&self) -> &Client {
+        self.auth.client()
+    }
+
+    /// Adds a symmetric key that can be used to unwrap item keys that reference `key_id`.
+    pub fn add_key(&mut self, key_id: Uuid, key: Bytes) {
+        self.keyring.insert(key_id, key);
+    }
+
+    /// Returns the user's root info, fetching it on first use.
+    pub async fn root_info(&mut self) -> Result<&UserRootInfo> {
+        if self.root.is_none() {
+            let token = self.auth.session_token()?;
+            let root = self
+                .auth
+                .client()
+                .user_root(self.auth.user_id(), token)
+                .await?;
+            self.root = Some(root);
+        }
+        Ok(self.root.as_ref().unwrap())
+    }
+}
+
+#[async_trait]
+impl<A, S> Storage for HttpStorage<A, S>
+where
+    A: AsymmetricCipherSpi + Send + Sync,
+    S: SymmetricCipherSpi + Send + Sync,
+{
+    fn is_unlocked(&self) -> bool {
+        self.auth.is_unlocked()
+    }
+
+    fn authentication(&mut self) -> Box<dyn Authentication + '_> {
+        Box::new(&mut self.auth)
+    }
+
+    async fn item(&mut self, id: Uuid) -> Result<Box<dyn Item + '_>> {
+        if !self.is_unlocked() {
+            return Err(Error::new(ErrorCode::NotAuthenticated, "storage is locked"));
+        }
+        Ok(Box::new(HttpItem { storage: self, id }))
+    }
+
+    /// `POST /items/new`, followed by uploading the item key sealed to the user, then the contents together with
+    ///  the key list.
+    async fn create_item(
+        &mut self,
+        request: NewItemRequest,
+        contents: &mut (dyn Read + Unpin + Send),
+    ) -> Result<Box<dyn Item + '_>> {
+        if !self.is_unlocked() {
+            return Err(Error::new(ErrorCode::NotAuthenticated, "storage is locked"));
+        }
+        let body = read_contents(contents).await?;
+        let root_key = self.root_info().await?.root_key;
+
+        self.symmetric.init(ITEM_CIPHER).await?;
+        let item_key = self.symmetric.generate_key().await?;
+        let mut keys = ItemKeys {
+            base_cipher: ITEM_CIPHER,
+            key_refs: vec![root_key],
+            item_iv: Bytes::new(Vec::new()),
+            item_auth_tag: None,
+        };
+        let ciphertext = encrypt_item(&mut self.symmetric, &mut keys, &item_key, &body).await?;
+        let key_info = ItemKeyInfo {
+            secured_item_key: self.auth.seal(&item_key).await?,
+            item_key_iv: Bytes::new(Vec::new()),
+            item_auth_tag: None,
+        };
+
+        let client = self.auth.client();
+        let token = self.auth.session_token()?;
+        let NewItemResponse { item_id } = client.new_item(&request, token).await?;
+        client
+            .put_item_key_info(item_id, root_key, &key_info, token)
+            .await?;
+        client
+            .put_item_contents(
+                item_id,
+                &ItemContents {
+                    keys,
+                    contents: ciphertext,
+                },
+                None,
+                token,
+            )
+            .await?;
+
+        Ok(Box::new(HttpItem {
+            storage: self,
+            id: item_id,
+        }))
+    }
+
+    /// `PUT /items/<uuid>/parents/<vault-uuid>`
+    async fn link_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()> {
+        let token = self.auth.session_token()?;
+        self.auth.client().put_item_parent(id, vault, token).await
+    }
+
+    /// `DELETE /items/<uuid>/parents/<vault-uuid>`
+    async fn unlink_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()> {
+        let token = self.auth.session_token()?;
+        self.auth
+            .client()
+            .delete_item_parent(id, vault, token)
+            .await
+    }
+}
+
+/// An item stored on a passman server.
+pub struct HttpItem<'a, A, S> {
+    storage: &'a mut HttpStorage<A, S>,
+    id: Uuid,
+}
+
+impl<A, S> HttpItem<'_, A, S>
+where
+    A: AsymmetricCipherSpi + Send + Sync,
+    S: SymmetricCipherSpi + Send + Sync,
+{
+    /// `GET /items/<uuid>/keys`
+    pub async fn keys(&self) -> Result<ItemKeys> {
+        let auth = &self.storage.auth;
+        auth.client()
+            .item_keys(self.id, auth.session_token()?)
+            .await
+    }
+
+    /// Recovers the item key from the first key in `keys.key_refs` that this client can unwrap.
+    async fn item_key(&mut self, keys: &ItemKeys) -> Result<Bytes> {
+        let root_key = self.storage.root_info().await?.root_key;
+        let storage = &mut *self.storage;
+
+        for &key_id in &keys.key_refs {
+            if key_id == root_key {
+                let info = storage
+                    .auth
+                    .client()
+                    .item_key_info(self.id, key_id, storage.auth.session_token()?)
+                    .await?;
+                return storage.auth.unseal(&info.secured_item_key).await;
+            } else if let Some(key) = storage.keyring.get(&key_id) {
+                let info = storage
+                    .auth
+                    .client()
+                    .item_key_info(self.id, key_id, storage.auth.session_token()?)
+                    .await?;
+                return Ok(unwrap_item_key(&mut storage.symmetric, key, &info).await?);
+            }
+        }
+
+        Err(Error::new(
+            ErrorCode::Crypto,
+            format!("no usable key for item {}", self.id),
+        ))
+    }
+}
+
+#[async_trait]
+impl<A, S> Item for HttpItem<'_, A, S>
+where
+    A: AsymmetricCipherSpi + Send + Sync,
+    S: SymmetricCipherSpi + Send + Sync,
+{
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// `GET /items/<uuid>/metadata`
+    async fn metadata(&mut self) -> Result<ItemMetadata> {
+        let auth = &self.storage.auth;
+        auth.client()
+            .item_metadata(self.id, auth.session_token()?)
+            .await
+    }
+
+    async fn read(&mut self) -> Result<Box<dyn Read + Unpin + Send + '_>> {
+        let auth = &self.storage.auth;
+        let (ItemContents { keys, contents }, _) = auth
+            .client()
+            .item_contents(self.id, auth.session_token()?)
+            .await?;
+        let item_key = self.item_key(&keys).await?;
+
+        let body = decrypt_item(&mut self.storage.symmetric, &keys, &item_key, &contents).await?;
+        Ok(Box::new(Cursor::new(body.into_inner())))
+    }
+
+    /// Replaces the contents through [`Item::update`], so that the key list they are written with is the one that
+    ///  was read.
+    ///
+    /// Fails with [`ErrorCode::Conflict`] if the item was changed in between, as a key reference added by another
+    ///  client would otherwise be dropped.
+    async fn write(&mut self, contents: &mut (dyn Read + Unpin + Send)) -> Result<()> {
+        let body = read_contents(contents).await?;
+        self.update(&mut |_| Ok(body.clone())).await
+    }
+
+    /// `PUT /items/<uuid>` with `If-Match`, using the `ETag` of the contents that were read.
+    async fn update(
+        &mut self,
+        update: &mut (dyn for<'b> FnMut(&'b [u8]) -> Result<Vec<u8>> + Send),
+    ) -> Result<()> {
+        let auth = &self.storage.auth;
+        let (ItemContents { mut keys, contents }, etag) = auth
+            .client()
+            .item_contents(self.id, auth.session_token()?)
+            .await?;
+        let item_key = self.item_key(&keys).await?;
+        let body = decrypt_item(&mut self.storage.symmetric, &keys, &item_key, &contents).await?;
+
+        let body = update(&body)?;
+        let ciphertext =
+            encrypt_item(&mut self.storage.symmetric, &mut keys, &item_key, &body).await?;
+
+        let auth = &self.storage.auth;
+        auth.client()
+            .put_item_contents(
+                self.id,
+                &ItemContents {
+                    keys,
+                    contents: ciphertext,
+                },
+                Some(&etag),
+                auth.session_token()?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// `DELETE /items/<uuid>`
+    async fn delete(self: Box<Self>) -> Result<()> {
+        let auth = &self.storage.auth;
+        auth.client()
+            .delete_item(self.id, auth.session_token()?)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read as _, Write as _},
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+    };
+
+    use async_std::io::{Cursor, ReadExt};
+    use common::{
+        data::Bytes,
+        error::ErrorCode,
+        http::api::{
+            auth::{AuthChallengeResponse, AuthSession, UserAuth},
+            item::{ItemContents, ItemKeyInfo, ItemKeys, NewItemRequest, NewItemResponse},
+            user::{NewUserRequest, NewUserResponse, UserRootInfo},
+            Hello, PROTOCOL_ID_PASSMAN,
+        },
+        suite::{AsymmetricCipherAlgorithm, DigestAlgorithm},
+    };
+    use serde::{de::DeserializeOwned, Serialize};
+    use time::{OffsetDateTime, PrimitiveDateTime};
+    use uuid::Uuid;
+
+    use super::HttpStorage;
+    use crate::{
+        cipher::asymmetric::DefaultAsymmetricCipher,
+        client::Client,
+        storage::{local::test::kdf_params, Storage},
+    };
+
+    const ADDRESS: &str = "user@example.com";
+    const PASSWORD: &str = "hunter2";
+
+    fn now() -> PrimitiveDateTime {
+        let now = OffsetDateTime::now_utc();
+        PrimitiveDateTime::new(now.date(), now.time())
+    }
+
+    #[derive(Default)]
+    struct StubItem {
+        contents: Option<ItemContents>,
+        key_info: HashMap<Uuid, ItemKeyInfo>,
+        revision: u32,
+    }
+
+    /// A stand-in for a passman server, which keeps one user and its items in memory, and accepts any answer to a
+    ///  challenge.
+    struct Stub {
+        server_id: Uuid,
+        user: Option<UserAuth>,
+        root_key: Uuid,
+        items: HashMap<Uuid, StubItem>,
+        /// The `If-Match` header of every `PUT /items/<uuid>`.
+        if_match: Vec<Option<String>>,
+        /// A key that another client shares the next item that is read with, right after it is read.
+        share_after_read: Option<Uuid>,
+    }
+
+    struct Response {
+        status: u16,
+        etag: Option<String>,
+        body: Vec<u8>,
+    }
+
+    impl Response {
+        fn json(body: &impl Serialize) -> Self {
+            Self {
+                status: 200,
+                etag: None,
+                body: serde_json::to_vec(body).unwrap(),
+            }
+        }
+
+        fn status(status: u16) -> Self {
+            Self {
+                status,
+                etag: None,
+                body: Vec::new(),
+            }
+        }
+    }
+
+    impl Stub {
+        fn handle(
+            &mut self,
+            method: &str,
+            path: &str,
+            if_match: Option<String>,
+            body: &[u8],
+        ) -> Response {
+            fn parse<T: DeserializeOwned>(body: &[u8]) -> T {
+                serde_json::from_slice(body).unwrap()
+            }
+            let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+            let id = |idx: usize| segments[idx].parse::<Uuid>().unwrap();
+
+            match (method, segments.as_slice()) {
+                ("GET", ["hello"]) => Response::json(&Hello {
+                    server_id: self.server_id,
+                    protocol_id: PROTOCOL_ID_PASSMAN,
+                    hello_time: now(),
+                }),
+                ("POST", ["users", "new"]) => {
+                    self.user = Some(parse::<NewUserRequest>(body).initial_auth);
+                    Response::json(&NewUserResponse {
+                        user_id: Uuid::new_v4(),
+                    })
+                }
+                ("GET", ["users", _, "auth"]) => Response::json(self.user.as_ref().unwrap()),
+                ("GET", ["users", _, "root"]) => Response::json(&UserRootInfo {
+                    root_object: Uuid::nil(),
+                    root_key: self.root_key,
+                }),
+                ("POST", ["auth", "challenge"]) => Response::json(&AuthChallengeResponse {
+                    challenge_digest: DigestAlgorithm::Sha256,
+                    challenge_bytes: vec![7; 32].into(),
+                }),
+                ("POST", ["auth", "response"]) => Response::json(&AuthSession {
+                    session_id: Uuid::new_v4(),
+                    session_token: vec![8; 32].into(),
+                    expires: now(),
+                }),
+                ("POST", ["items", "new"]) => {
+                    let item_id = Uuid::new_v4();
+                    self.items.insert(item_id, StubItem::default());
+                    Response::json(&NewItemResponse { item_id })
+                }
+                (method, ["items", _, rest @ ..]) => {
+                    let Some(item) = self.items.get_mut(&id(1)) else {
+                        return Response::status(404);
+                    };
+                    let etag = format!("\"{}\"", item.revision);
+                    match (method, rest) {
+                        ("GET", []) => match &mut item.contents {
+                            Some(contents) => {
+                                let res = Response {
+                                    etag: Some(etag),
+                                    ..Response::json(contents)
+                                };
+                                if let Some(key) = self.share_after_read.take() {
+                                    contents.keys.key_refs.push(key);
+                                    item.revision += 1;
+                                }
+                                res
+                            }
+                            None => Response::status(404),
+                        },
+                        ("PUT", []) => {
+                            self.if_match.push(if_match.clone());
+                            if if_match.is_some_and(|tag| tag != etag) {
+                                return Response::status(409);
+                            }
+                            item.contents = Some(parse(body));
+                            item.revision += 1;
+                            Response {
+                                etag: Some(format!("\"{}\"", item.revision)),
+                                ..Response::status(204)
+                            }
+                        }
+                        ("GET", ["keys"]) => match &item.contents {
+                            Some(contents) => Response::json(&contents.keys),
+                            None => Response::status(404),
+                        },
+                        ("GET", ["keys", _]) => match item.key_info.get(&id(3)) {
+                            Some(info) => Response::json(info),
+                            None => Response::status(404),
+                        },
+                        ("PUT", ["keys", _]) => {
+                            item.key_info.insert(id(3), parse(body));
+                            Response::status(204)
+                        }
+                        _ => Response::status(404),
+                    }
+                }
+                _ => Response::status(404),
+            }
+        }
+
+        /// Serves the stub on a local port, returning its state and a client for it.
+        fn serve(server_id: Uuid) -> (Arc<Mutex<Stub>>, Client) {
+            let stub = Arc::new(Mutex::new(Stub {
+                server_id,
+                user: None,
+                root_key: Uuid::new_v4(),
+                items: HashMap::new(),
+                if_match: Vec::new(),
+                share_after_read: None,
+            }));
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let state = stub.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let state = state.clone();
+                    std::thread::spawn(move || serve_connection(stream.unwrap(), &state));
+                }
+            });
+            (stub, Client::new(url.parse().unwrap()))
+        }
+    }
+
+    /// Answers the HTTP/1.1 requests on `stream` until the client closes it.
+    fn serve_connection(stream: TcpStream, stub: &Mutex<Stub>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        loop {
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                return;
+            }
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+
+            let (mut len, mut if_match) = (0, None);
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').unwrap();
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => len = value.trim().parse().unwrap(),
+                    "if-match" => if_match = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+
+            let res = stub.lock().unwrap().handle(method, path, if_match, &body);
+            let mut head = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+                res.status,
+                res.body.len()
+            );
+            if let Some(etag) = res.etag {
+                head.push_str(&format!("ETag: {etag}\r\n"));
+            }
+            head.push_str("\r\n");
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&res.body).unwrap();
+        }
+    }
+
+    /// Runs `fut` on a runtime that the HTTP client can use.
+    fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(fut)
+    }
+
+    /// Registers a user with the stub, and connects to it as that user.
+    async fn connect(
+        client: Client,
+        server_id: Option<Uuid>,
+    ) -> common::error::Result<HttpStorage> {
+        let user_id = client
+            .register(
+                ADDRESS,
+                PASSWORD,
+                AsymmetricCipherAlgorithm::Ec25519,
+                DigestAlgorithm::Sha256,
+                kdf_params(),
+                &mut DefaultAsymmetricCipher::new(),
+            )
+            .await?;
+        HttpStorage::with_ciphers(
+            client,
+            server_id,
+            user_id,
+            ADDRESS,
+            PASSWORD,
+            DefaultAsymmetricCipher::new(),
+            Default::default(),
+        )
+        .await
+    }
+
+    #[test]
+    fn handshake() {
+        let server_id = Uuid::new_v4();
+        block_on(async {
+            let (_, client) = Stub::serve(server_id);
+            let storage = connect(client.clone(), Some(server_id)).await.unwrap();
+            assert_eq!(storage.hello().server_id, server_id);
+            assert!(!storage.is_unlocked());
+            assert!(connect(client.clone(), None).await.is_ok());
+
+            let err = connect(client, Some(Uuid::new_v4())).await.err().unwrap();
+            assert_eq!(*err.code(), ErrorCode::ServerMismatch);
+        });
+    }
+
+    #[test]
+    fn read_write() {
+        block_on(async {
+            let (stub, client) = Stub::serve(Uuid::new_v4());
+            let mut storage = connect(client, None).await.unwrap();
+            assert_eq!(
+                *storage.item(Uuid::new_v4()).await.err().unwrap().code(),
+                ErrorCode::NotAuthenticated
+            );
+            storage.authentication().authenticate().await.unwrap();
+
+            let id = storage
+                .create_item(
+                    NewItemRequest {
+                        content_type: "text/plain".into(),
+                        base_acl: Vec::new(),
+                    },
+                    &mut Cursor::new(b"first".to_vec()),
+                )
+                .await
+                .unwrap()
+                .id();
+            let stored = |stub: &Mutex<Stub>| -> (ItemKeys, Bytes) {
+                let stub = stub.lock().unwrap();
+                let contents = stub.items[&id].contents.clone().unwrap();
+                (contents.keys, contents.contents)
+            };
+            let (keys, ciphertext) = stored(&stub);
+            assert_eq!(keys.key_refs, [stub.lock().unwrap().root_key]);
+            assert!(!ciphertext.windows(5).any(|w| w == b"first"));
+
+            let mut item = storage.item(id).await.unwrap();
+            item.write(&mut Cursor::new(b"second".to_vec()))
+                .await
+                .unwrap();
+            let mut body = String::new();
+            item.read()
+                .await
+                .unwrap()
+                .read_to_string(&mut body)
+                .await
+                .unwrap();
+            assert_eq!(body, "second");
+            // The contents are written against the ETag of the key list they were encrypted with
+            assert_eq!(
+                stub.lock().unwrap().if_match,
+                [None, Some("\"1\"".to_string())]
+            );
+
+            // A key reference added by another client between reading and writing is not lost
+            let shared = Uuid::new_v4();
+            stub.lock().unwrap().share_after_read = Some(shared);
+            let err = item
+                .write(&mut Cursor::new(b"third".to_vec()))
+                .await
+                .unwrap_err();
+            assert_eq!(*err.code(), ErrorCode::Conflict);
+            let (keys, _) = stored(&stub);
+            assert!(keys.key_refs.contains(&shared));
+            item.write(&mut Cursor::new(b"third".to_vec()))
+                .await
+                .unwrap();
+            assert_eq!(stored(&stub).0.key_refs, keys.key_refs);
+        });
+    }
+}
@@ -0,0 +1,806 @@
+use std::path::{Path, PathBuf};
+
+use async_std::io::{Cursor, Read};
+use common::{
+    data::Bytes,
+    error::{Error, ErrorCode, Result},
+    http::api::{
+        auth::KdfParams,
+        item::{ItemKeyInfo, ItemKeys, ItemMetadata, NewItemRequest},
+        user::UserRootInfo,
+    },
+    item,
+    suite::{DigestAlgorithm, SymmetricCipherAlgorithm},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use uuid::Uuid;
+
+use super::{read_contents, Authentication, Item, Storage, ITEM_CIPHER};
+use crate::{
+    cipher::{
+        decrypt_item, encrypt_item, kdf, symmetric::DefaultSymmetricCipher, unwrap_item_key,
+        wrap_item_key, SymmetricCipherSpi, SymmetricCiphertext,
+    },
+    macros::async_trait,
+};
+
+/// The current version of the vault file format.
+pub const VAULT_FORMAT_VERSION: u32 = 1;
+
+/// The cipher used to encrypt the contents of new vault files.
+pub const VAULT_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
+/// The on-disk form of a vault. Everything except the parameters needed to unlock it is encrypted.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct VaultFile {
+    format: u32,
+    vault_key_id: Uuid,
+    kdf_digest: DigestAlgorithm,
+    kdf_params: KdfParams,
+    cipher: SymmetricCipherAlgorithm,
+    iv: Bytes,
+    auth_tag: Bytes,
+    contents: Bytes,
+}
+
+/// The decrypted contents of a vault.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct VaultContents {
+    root_object: Uuid,
+    items: IndexMap<Uuid, VaultItem>,
+}
+
+/// A single item, stored the same way as on a server: encrypted contents, the item key wrapped by each key in
+///  `keys.key_refs`, and metadata.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct VaultItem {
+    metadata: ItemMetadata,
+    keys: ItemKeys,
+    key_info: IndexMap<Uuid, ItemKeyInfo>,
+    contents: Bytes,
+}
+
+struct UnlockedVault {
+    key: Bytes,
+    contents: VaultContents,
+}
+
+/// An exclusive advisory lock on the `.lock` file next to a vault file. It is released when dropped, or when the
+///  process exits.
+struct VaultLock {
+    _file: std::fs::File,
+}
+
+fn now() -> PrimitiveDateTime {
+    let now = OffsetDateTime::now_utc();
+    PrimitiveDateTime::new(now.date(), now.time())
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(ErrorCode::Storage, e.to_string())
+}
+
+fn invalid_vault(e: serde_json::Error) -> Error {
+    Error::new(
+        ErrorCode::InvalidData,
+        format!("vault file is not valid: {e}"),
+    )
+}
+
+fn serialize_error(e: serde_json::Error) -> Error {
+    Error::new(ErrorCode::Internal, e.to_string())
+}
+
+/// Unlocks a [`LocalStorage`] by deriving the vault key from the master password.
+pub struct LocalAuthentication<S> {
+    path: PathBuf,
+    password: String,
+    cipher: S,
+    file: Option<VaultFile>,
+    vault: Option<UnlockedVault>,
+}
+
+impl<S: SymmetricCipherSpi + Send> LocalAuthentication<S> {
+    async fn derive_key(&self, file: &VaultFile) -> Result<Bytes> {
+        let mut key = vec![0u8; 32];
+        kdf::derive_key(
+            self.password.as_bytes(),
+            file.kdf_digest,
+            &file.kdf_params,
+            &mut key,
+        )?;
+        Ok(Bytes::new(key))
+    }
+
+    async fn read_file(&self) -> Result<VaultFile> {
+        self.read_file_if_exists().await?.ok_or_else(|| {
+            Error::new(
+                ErrorCode::NotFound,
+                format!("{} does not exist", self.path.display()),
+            )
+        })
+    }
+
+    async fn read_file_if_exists(&self) -> Result<Option<VaultFile>> {
+        let serialized = match async_std::fs::read(&self.path).await {
+            Ok(serialized) => serialized,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(e)),
+        };
+        let file: VaultFile = serde_json::from_slice(&serialized).map_err(invalid_vault)?;
+        if file.format != VAULT_FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorCode::VersionMismatch,
+                format!("unsupported vault format {}", file.format),
+            ));
+        }
+        Ok(Some(file))
+    }
+
+    /// Takes the lock that every change to the vault file is made under.
+    async fn lock(&self) -> Result<VaultLock> {
+        let path = self.path.with_extension("lock");
+        async_std::task::spawn_blocking(move || -> std::io::Result<VaultLock> {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path)?;
+            file.lock()?;
+            Ok(VaultLock { _file: file })
+        })
+        .await
+        .map_err(io_error)
+    }
+
+    async fn decrypt(&mut self, file: &VaultFile, key: &[u8]) -> Result<VaultContents> {
+        self.cipher.init(file.cipher).await?;
+        let plaintext = self
+            .cipher
+            .decrypt(key, &file.iv, &file.contents, &file.auth_tag)
+            .await?;
+        serde_json::from_slice(&plaintext).map_err(invalid_vault)
+    }
+
+    /// Re-reads the vault file if it was saved by someone else since it was last read or saved.
+    async fn reload(&mut self) -> Result<()> {
+        let (Some(current), Some(vault)) = (self.file.as_ref(), self.vault.as_ref()) else {
+            return Err(Error::new(ErrorCode::NotAuthenticated, "vault is locked"));
+        };
+        let file = self.read_file().await?;
+        if file.iv == current.iv {
+            return Ok(());
+        }
+
+        let key = if file.kdf_digest == current.kdf_digest && file.kdf_params == current.kdf_params
+        {
+            vault.key.clone()
+        } else {
+            self.derive_key(&file).await?
+        };
+        let contents = self.decrypt(&file, &key).await?;
+
+        self.file = Some(file);
+        self.vault = Some(UnlockedVault { key, contents });
+        Ok(())
+    }
+
+    /// Encrypts the unlocked vault and atomically replaces the vault file.
+    ///
+    /// Fails with [`ErrorCode::Conflict`] without writing anything if the vault file was saved by someone else
+    ///  since it was last read or saved. Callers hold `_lock` from the time they [`reload`](Self::reload) the vault
+    ///  until the save, so that concurrent changes are never overwritten.
+    async fn save(&mut self, _lock: &VaultLock) -> Result<()> {
+        let on_disk = self.read_file_if_exists().await?.map(|file| file.iv);
+        let (Some(file), Some(vault)) = (self.file.as_mut(), self.vault.as_ref()) else {
+            return Err(Error::new(ErrorCode::NotAuthenticated, "vault is locked"));
+        };
+        // A vault that was never saved has no IV yet, and its file must not exist
+        let expected = Some(&file.iv).filter(|iv| !iv.is_empty());
+        if on_disk.as_ref() != expected {
+            return Err(Error::new(
+                ErrorCode::Conflict,
+                format!("{} was changed concurrently", self.path.display()),
+            ));
+        }
+
+        let plaintext = serde_json::to_vec(&vault.contents).map_err(serialize_error)?;
+        self.cipher.init(file.cipher).await?;
+        let iv = self.cipher.generate_iv().await?;
+        let SymmetricCiphertext {
+            ciphertext,
+            auth_tag,
+        } = self.cipher.encrypt(&vault.key, &iv, &plaintext).await?;
+        let mut saved = file.clone();
+        saved.iv = iv;
+        saved.auth_tag = auth_tag;
+        saved.contents = ciphertext;
+
+        let serialized = serde_json::to_vec_pretty(&saved).map_err(serialize_error)?;
+        let tmp = self.path.with_extension("tmp");
+        async_std::fs::write(&tmp, serialized)
+            .await
+            .map_err(io_error)?;
+        async_std::fs::rename(&tmp, &self.path)
+            .await
+            .map_err(io_error)?;
+        *file = saved;
+        Ok(())
+    }
+
+    /// Takes the vault lock and reloads the vault, before a change that is then saved with
+    ///  [`LocalAuthentication::save`].
+    async fn begin_change(&mut self) -> Result<VaultLock> {
+        let lock = self.lock().await?;
+        self.reload().await?;
+        Ok(lock)
+    }
+}
+
+#[async_trait]
+impl<S: SymmetricCipherSpi + Send + Sync> Authentication for LocalAuthentication<S> {
+    fn is_unlocked(&self) -> bool {
+        self.vault.is_some()
+    }
+
+    async fn authenticate(&mut self) -> Result<()> {
+        let file = self.read_file().await?;
+        let key = self.derive_key(&file).await?;
+        let contents = self.decrypt(&file, &key).await?;
+
+        self.file = Some(file);
+        self.vault = Some(UnlockedVault { key, contents });
+        Ok(())
+    }
+}
+
+/// A [`Storage`] kept in a single encrypted file, for use without a server.
+///
+/// Items use the same model as on a server. Each item key is wrapped with the vault key, which is derived from
+///  the master password and identified by the `root_key` of [`LocalStorage::root_info`].
+pub struct LocalStorage<S = DefaultSymmetricCipher> {
+    auth: LocalAuthentication<S>,
+}
+
+impl LocalStorage {
+    /// Opens an existing vault file. The storage starts locked; use [`Storage::authentication`] to unlock it.
+    pub fn open(path: impl Into<PathBuf>, password: &str) -> Self {
+        Self::with_cipher(path, password, DefaultSymmetricCipher::new())
+    }
+
+    /// Creates a new vault file at `path` holding an empty root [`Vault`][item::Vault], and returns it unlocked.
+    pub async fn create(
+        path: impl Into<PathBuf>,
+        password: &str,
+        kdf_digest: DigestAlgorithm,
+        kdf_params: KdfParams,
+    ) -> Result<Self> {
+        Self::create_with_cipher(
+            path,
+            password,
+            kdf_digest,
+            kdf_params,
+            DefaultSymmetricCipher::new(),
+        )
+        .await
+    }
+}
+
+impl<S: SymmetricCipherSpi + Send + Sync> LocalStorage<S> {
+    pub fn with_cipher(path: impl Into<PathBuf>, password: &str, cipher: S) -> Self {
+        Self {
+            auth: LocalAuthentication {
+                path: path.into(),
+                password: password.to_string(),
+                cipher,
+                file: None,
+                vault: None,
+            },
+        }
+    }
+
+    pub async fn create_with_cipher(
+        path: impl Into<PathBuf>,
+        password: &str,
+        kdf_digest: DigestAlgorithm,
+        kdf_params: KdfParams,
+        cipher: S,
+    ) -> Result<Self> {
+        let mut storage = Self::with_cipher(path, password, cipher);
+        let lock = storage.auth.lock().await?;
+        if async_std::path::Path::new(storage.path()).exists().await {
+            return Err(Error::new(
+                ErrorCode::Conflict,
+                format!("{} already exists", storage.path().display()),
+            ));
+        }
+
+        let file = VaultFile {
+            format: VAULT_FORMAT_VERSION,
+            vault_key_id: Uuid::new_v4(),
+            kdf_digest,
+            kdf_params,
+            cipher: VAULT_CIPHER,
+            iv: Bytes::new(Vec::new()),
+            auth_tag: Bytes::new(Vec::new()),
+            contents: Bytes::new(Vec::new()),
+        };
+        let key = storage.auth.derive_key(&file).await?;
+        let root_object = Uuid::new_v4();
+        storage.auth.file = Some(file);
+        storage.auth.vault = Some(UnlockedVault {
+            key,
+            contents: VaultContents {
+                root_object,
+                items: IndexMap::new(),
+            },
+        });
+
+        let root = item::Item::Vault(item::Vault {
+            display_name: None,
+            content: Vec::new(),
+        });
+        let root = root.to_json().map_err(serialize_error)?;
+        storage
+            .insert_item(
+                root_object,
+                format!("{}{}", item::VAULT_TYPE, item::JSON_FORMAT_SUFFIX),
+                &root,
+            )
+            .await?;
+        storage.auth.save(&lock).await?;
+        drop(lock);
+        Ok(storage)
+    }
+
+    /// Encrypts `body` with a fresh item key wrapped with the vault key, and adds it to the vault as `id`.
+    async fn insert_item(&mut self, id: Uuid, content_type: String, body: &[u8]) -> Result<()> {
+        let vault_key_id = self.root_info()?.root_key;
+        let auth = &mut self.auth;
+        let vault_key = auth.vault.as_ref().unwrap().key.clone();
+
+        auth.cipher.init(ITEM_CIPHER).await?;
+        let item_key = auth.cipher.generate_key().await?;
+        let mut keys = ItemKeys {
+            base_cipher: ITEM_CIPHER,
+            key_refs: vec![vault_key_id],
+            item_iv: Bytes::new(Vec::new()),
+            item_auth_tag: None,
+        };
+        let contents = encrypt_item(&mut auth.cipher, &mut keys, &item_key, body).await?;
+        let key_info = wrap_item_key(&mut auth.cipher, &vault_key, &item_key).await?;
+
+        let now = now();
+        let item = VaultItem {
+            metadata: ItemMetadata {
+                content_type,
+                mtime: now,
+                atime: now,
+                ctime: now,
+            },
+            keys,
+            key_info: IndexMap::from([(vault_key_id, key_info)]),
+            contents,
+        };
+        auth.vault.as_mut().unwrap().contents.items.insert(id, item);
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.auth.path
+    }
+
+    /// The root object and the ID of the vault key, matching the server's `GET /users/<uuid>/root`.
+    pub fn root_info(&self) -> Result<UserRootInfo> {
+        match (&self.auth.file, &self.auth.vault) {
+            (Some(file), Some(vault)) => Ok(UserRootInfo {
+                root_object: vault.contents.root_object,
+                root_key: file.vault_key_id,
+            }),
+            _ => Err(Error::new(ErrorCode::NotAuthenticated, "vault is locked")),
+        }
+    }
+
+    /// The IDs of every item in the vault.
+    pub fn item_ids(&self) -> Result<Vec<Uuid>> {
+        self.auth
+            .vault
+            .as_ref()
+            .map(|vault| vault.contents.items.keys().copied().collect())
+            .ok_or_else(|| Error::new(ErrorCode::NotAuthenticated, "vault is locked"))
+    }
+}
+
+#[async_trait]
+impl<S: SymmetricCipherSpi + Send + Sync> Storage for LocalStorage<S> {
+    fn is_unlocked(&self) -> bool {
+        self.auth.is_unlocked()
+    }
+
+    fn authentication(&mut self) -> Box<dyn Authentication + '_> {
+        Box::new(&mut self.auth)
+    }
+
+    async fn item(&mut self, id: Uuid) -> Result<Box<dyn Item + '_>> {
+        let vault = self
+            .auth
+            .vault
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorCode::NotAuthenticated, "vault is locked"))?;
+        if !vault.contents.items.contains_key(&id) {
+            return Err(Error::new(ErrorCode::NotFound, format!("no item {id}")));
+        }
+        Ok(Box::new(LocalItem { storage: self, id }))
+    }
+
+    /// Adds a new item to the vault, wrapping its key with the vault key, and saves the vault.
+    ///
+    /// `request.base_acl` is ignored, as a local vault has a single user.
+    async fn create_item(
+        &mut self,
+        request: NewItemRequest,
+        contents: &mut (dyn Read + Unpin + Send),
+    ) -> Result<Box<dyn Item + '_>> {
+        let body = read_contents(contents).await?;
+        let lock = self.auth.begin_change().await?;
+        let id = Uuid::new_v4();
+        self.insert_item(id, request.content_type, &body).await?;
+        self.auth.save(&lock).await?;
+        drop(lock);
+
+        Ok(Box::new(LocalItem { storage: self, id }))
+    }
+
+    /// Does nothing, as a local vault has no ACLs to inherit.
+    async fn link_parent(&mut self, _id: Uuid, _vault: Uuid) -> Result<()> {
+        Ok(())
+    }
+
+    /// Does nothing, as a local vault has no ACLs to inherit.
+    async fn unlink_parent(&mut self, _id: Uuid, _vault: Uuid) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An item in a [`LocalStorage`].
+pub struct LocalItem<'a, S> {
+    storage: &'a mut LocalStorage<S>,
+    id: Uuid,
+}
+
+impl<S: SymmetricCipherSpi + Send + Sync> LocalItem<'_, S> {
+    fn vault_item(&self) -> Result<&VaultItem> {
+        self.storage
+            .auth
+            .vault
+            .as_ref()
+            .and_then(|vault| vault.contents.items.get(&self.id))
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("no item {}", self.id)))
+    }
+
+    fn vault_item_mut(&mut self) -> Result<&mut VaultItem> {
+        self.storage
+            .auth
+            .vault
+            .as_mut()
+            .and_then(|vault| vault.contents.items.get_mut(&self.id))
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("no item {}", self.id)))
+    }
+
+    async fn item_key(&mut self, item: &VaultItem) -> Result<Bytes> {
+        let root_key = self.storage.root_info()?.root_key;
+        let auth = &mut self.storage.auth;
+        let vault_key = auth.vault.as_ref().unwrap().key.clone();
+
+        let info = item.key_info.get(&root_key).ok_or_else(|| {
+            Error::new(
+                ErrorCode::Crypto,
+                format!("no usable key for item {}", self.id),
+            )
+        })?;
+        Ok(unwrap_item_key(&mut auth.cipher, &vault_key, info).await?)
+    }
+
+    pub fn keys(&self) -> Result<ItemKeys> {
+        Ok(self.vault_item()?.keys.clone())
+    }
+}
+
+#[async_trait]
+impl<S: SymmetricCipherSpi + Send + Sync> Item for LocalItem<'_, S> {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn metadata(&mut self) -> Result<ItemMetadata> {
+        Ok(self.vault_item()?.metadata.clone())
+    }
+
+    async fn read(&mut self) -> Result<Box<dyn Read + Unpin + Send + '_>> {
+        let item = self.vault_item()?.clone();
+        let item_key = self.item_key(&item).await?;
+        let body = decrypt_item(
+            &mut self.storage.auth.cipher,
+            &item.keys,
+            &item_key,
+            &item.contents,
+        )
+        .await?;
+        Ok(Box::new(Cursor::new(body.into_inner())))
+    }
+
+    /// Reloads the vault, re-encrypts the item with its existing key, and saves the vault.
+    async fn write(&mut self, contents: &mut (dyn Read + Unpin + Send)) -> Result<()> {
+        let body = read_contents(contents).await?;
+        let lock = self.storage.auth.begin_change().await?;
+        let item = self.vault_item()?.clone();
+        let item_key = self.item_key(&item).await?;
+
+        let mut keys = item.keys;
+        let contents =
+            encrypt_item(&mut self.storage.auth.cipher, &mut keys, &item_key, &body).await?;
+        let item = self.vault_item_mut()?;
+        item.keys = keys;
+        item.contents = contents;
+        item.metadata.mtime = now();
+        self.storage.auth.save(&lock).await
+    }
+
+    /// Reloads the vault, applies `update` and saves the vault. The vault file is locked throughout, so `update`
+    ///  always sees the latest contents.
+    async fn update(
+        &mut self,
+        update: &mut (dyn for<'b> FnMut(&'b [u8]) -> Result<Vec<u8>> + Send),
+    ) -> Result<()> {
+        let lock = self.storage.auth.begin_change().await?;
+        let item = self.vault_item()?.clone();
+        let item_key = self.item_key(&item).await?;
+        let body = decrypt_item(
+            &mut self.storage.auth.cipher,
+            &item.keys,
+            &item_key,
+            &item.contents,
+        )
+        .await?;
+
+        let body = update(&body)?;
+        let mut keys = item.keys;
+        let contents =
+            encrypt_item(&mut self.storage.auth.cipher, &mut keys, &item_key, &body).await?;
+        let item = self.vault_item_mut()?;
+        item.keys = keys;
+        item.contents = contents;
+        item.metadata.mtime = now();
+        self.storage.auth.save(&lock).await
+    }
+
+    /// Reloads the vault, removes the item from it, and saves the vault.
+    async fn delete(self: Box<Self>) -> Result<()> {
+        let auth = &mut self.storage.auth;
+        let lock = auth.begin_change().await?;
+        auth.vault
+            .as_mut()
+            .and_then(|vault| vault.contents.items.shift_remove(&self.id))
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("no item {}", self.id)))?;
+        auth.save(&lock).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use async_std::io::{Cursor, ReadExt};
+    use common::{
+        error::ErrorCode,
+        http::api::{
+            auth::{KdfFunction, KdfParams},
+            item::NewItemRequest,
+        },
+        item,
+        suite::DigestAlgorithm,
+    };
+    use uuid::Uuid;
+
+    use super::{LocalStorage, Storage};
+    use crate::cipher::kdf;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    /// PBKDF2 with few iterations, so that tests do not spend their time deriving keys.
+    pub(crate) fn kdf_params() -> KdfParams {
+        let mut params = kdf::pbkdf2_params();
+        if let KdfFunction::Pbkdf2 { iterations, .. } = &mut params.function {
+            *iterations = 1000;
+        }
+        params
+    }
+
+    /// A directory for a vault file, which is removed when dropped.
+    pub(crate) struct TempVault {
+        dir: std::path::PathBuf,
+    }
+
+    impl TempVault {
+        pub(crate) fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("passman-test-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self { dir }
+        }
+
+        pub(crate) fn path(&self) -> std::path::PathBuf {
+            self.dir.join("vault.json")
+        }
+
+        /// Creates an empty vault, with cheap KDF parameters.
+        pub(crate) async fn create(&self) -> LocalStorage {
+            LocalStorage::create(self.path(), PASSWORD, DigestAlgorithm::Sha256, kdf_params())
+                .await
+                .unwrap()
+        }
+
+        /// Opens and unlocks another handle to the vault.
+        pub(crate) async fn open(&self) -> LocalStorage {
+            let mut storage = LocalStorage::open(self.path(), PASSWORD);
+            storage.authentication().authenticate().await.unwrap();
+            storage
+        }
+    }
+
+    impl Drop for TempVault {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Creates an item with `content_type` that holds `body`.
+    pub(crate) async fn create_raw(
+        storage: &mut LocalStorage,
+        content_type: &str,
+        body: &[u8],
+    ) -> Uuid {
+        storage
+            .create_item(
+                NewItemRequest {
+                    content_type: content_type.to_string(),
+                    base_acl: Vec::new(),
+                },
+                &mut Cursor::new(body.to_vec()),
+            )
+            .await
+            .unwrap()
+            .id()
+    }
+
+    /// Creates an item holding the JSON form of `item`.
+    pub(crate) async fn create_json(storage: &mut LocalStorage, item: &item::Item) -> Uuid {
+        let content_type = format!("{}{}", item.content_type(), item::JSON_FORMAT_SUFFIX);
+        create_raw(storage, &content_type, &item.to_json().unwrap()).await
+    }
+
+    async fn create_text(storage: &mut LocalStorage, text: &str) -> Uuid {
+        create_raw(storage, "text/plain", text.as_bytes()).await
+    }
+
+    async fn read_text(storage: &mut LocalStorage, id: Uuid) -> String {
+        let mut item = storage.item(id).await.unwrap();
+        let mut text = String::new();
+        item.read()
+            .await
+            .unwrap()
+            .read_to_string(&mut text)
+            .await
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn stale_handles_keep_concurrent_changes() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut first = vault.create().await;
+            let mut second = vault.open().await;
+
+            let a = create_text(&mut first, "a").await;
+            let b = create_text(&mut second, "b").await;
+            first
+                .item(a)
+                .await
+                .unwrap()
+                .write(&mut Cursor::new(b"a2".to_vec()))
+                .await
+                .unwrap();
+            second.item(b).await.unwrap().delete().await.unwrap();
+
+            let mut reopened = vault.open().await;
+            assert_eq!(read_text(&mut reopened, a).await, "a2");
+            assert!(reopened.item(b).await.is_err());
+            assert!(!reopened.item_ids().unwrap().contains(&b));
+        });
+    }
+
+    #[test]
+    fn create_existing_vault() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            vault.create().await;
+            let err = LocalStorage::create(
+                vault.path(),
+                PASSWORD,
+                DigestAlgorithm::Sha256,
+                kdf_params(),
+            )
+            .await
+            .err()
+            .unwrap();
+            assert_eq!(*err.code(), ErrorCode::Conflict);
+        });
+    }
+
+    #[test]
+    fn wrong_password() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut storage = vault.create().await;
+            let id = create_text(&mut storage, "secret").await;
+
+            let mut storage = LocalStorage::open(vault.path(), "wrong password");
+            let err = storage.authentication().authenticate().await.err().unwrap();
+            assert_eq!(*err.code(), ErrorCode::Crypto);
+            assert!(!storage.is_unlocked());
+            assert!(storage.root_info().is_err());
+            let err = storage.item(id).await.err().unwrap();
+            assert_eq!(*err.code(), ErrorCode::NotAuthenticated);
+
+            storage = vault.open().await;
+            assert_eq!(read_text(&mut storage, id).await, "secret");
+        });
+    }
+
+    #[test]
+    fn no_plaintext_on_disk() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            let mut storage = vault.create().await;
+            let item = item::Item::Password(item::PasswordItem {
+                display_name: Some("Plaintext display name".into()),
+                url: item::UrlMatcher::parse("plaintext.example.com").unwrap(),
+                login_id: Some("plaintext-login".into()),
+                login_password: "plaintext-password".into(),
+            });
+            let id = create_json(&mut storage, &item).await;
+            let text = create_text(&mut storage, "plaintext text item").await;
+
+            let file = std::fs::read_to_string(vault.path()).unwrap();
+            for plaintext in [
+                "plaintext",
+                item::PASSWORD_ITEM_TYPE,
+                "text/plain",
+                &id.to_string(),
+                &text.to_string(),
+                &storage.root_info().unwrap().root_object.to_string(),
+            ] {
+                assert!(
+                    !file.contains(plaintext),
+                    "{plaintext} is stored in the clear"
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn unreadable_vault_files() {
+        async_std::task::block_on(async {
+            let vault = TempVault::new();
+            std::fs::write(vault.path(), b"{\"format\": 1").unwrap();
+            let mut storage = LocalStorage::open(vault.path(), PASSWORD);
+            let err = storage.authentication().authenticate().await.err().unwrap();
+            assert_eq!(*err.code(), ErrorCode::InvalidData);
+
+            std::fs::remove_file(vault.path()).unwrap();
+            std::fs::create_dir(vault.path()).unwrap();
+            let err = storage.authentication().authenticate().await.err().unwrap();
+            assert_eq!(*err.code(), ErrorCode::Storage);
+        });
+    }
+}
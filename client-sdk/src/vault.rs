@@ -0,0 +1,802 @@
+//! Traversal of the vault hierarchy.
+//!
+//! Every item a user can reach is referenced from the `content` of a [`Vault`], starting at the user's
+//!  [`root_object`][common::http::api::user::UserRootInfo::root_object]. Vaults can be shared and nested, so the
+//!  references form a graph rather than a tree, and may contain cycles or point at items that no longer exist.
+
+use std::collections::{HashMap, HashSet};
+
+use async_std::io::ReadExt;
+use common::{
+    error::{Error, ErrorCode, Result},
+    item::{Item, Vault},
+};
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+/// The separator between the names in a path, as in `Work/Cloud/AWS`.
+pub const PATH_SEPARATOR: char = '/';
+
+fn invalid_item(id: Uuid, e: impl core::fmt::Display) -> Error {
+    Error::new(
+        ErrorCode::InvalidData,
+        format!("item {id} is not valid: {e}"),
+    )
+}
+
+fn children(item: &Item) -> Vec<Uuid> {
+    match item {
+        Item::Vault(vault) => vault.content.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Applies `change` to the `content` of the vault `vault`, with [`Item::update`](crate::storage::Item::update).
+async fn update_content<S: Storage + Send + ?Sized>(
+    storage: &mut S,
+    vault: Uuid,
+    mut change: impl FnMut(&mut Vec<Uuid>) + Send,
+) -> Result<()> {
+    let mut item = storage.item(vault).await?;
+    item.update(&mut |contents| {
+        let item = Item::from_json(contents).map_err(|e| invalid_item(vault, e))?;
+        let Item::Vault(mut parsed) = item else {
+            return Err(Error::new(
+                ErrorCode::InvalidRequest,
+                format!("item {vault} is not a vault"),
+            ));
+        };
+        change(&mut parsed.content);
+        Item::Vault(parsed)
+            .to_json()
+            .map_err(|e| Error::new(ErrorCode::InvalidRequest, e.to_string()))
+    })
+    .await
+}
+
+/// Adds the item `id` to the `content` of `vault`, unless it is already there, then records the link with
+///  [`Storage::link_parent`] so that the item inherits the vault's ACL.
+pub async fn add_to_vault<S: Storage + Send + ?Sized>(
+    storage: &mut S,
+    vault: Uuid,
+    id: Uuid,
+) -> Result<()> {
+    update_content(storage, vault, |content| {
+        if !content.contains(&id) {
+            content.push(id);
+        }
+    })
+    .await?;
+    storage.link_parent(id, vault).await
+}
+
+/// Removes the link made by [`add_to_vault`], then every reference to the item `id` from the `content` of
+///  `vault`.
+///
+/// The link is removed first, so that a failure never leaves the item inheriting from a vault it is not in.
+pub async fn remove_from_vault<S: Storage + Send + ?Sized>(
+    storage: &mut S,
+    vault: Uuid,
+    id: Uuid,
+) -> Result<()> {
+    match storage.unlink_parent(id, vault).await {
+        Err(e) if *e.code() != ErrorCode::NotFound => return Err(e),
+        _ => {}
+    }
+    update_content(storage, vault, |content| {
+        content.retain(|child| *child != id)
+    })
+    .await
+}
+
+/// What a reference resolves to, as loaded by [`VaultWalker::load`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Loaded {
+    Item(Item),
+    /// The item does not exist.
+    Missing,
+    /// The item exists, but cannot be read with the user's permissions or keys.
+    Inaccessible(Error),
+    /// The item was read, but is not a valid item, such as one that is not stored as JSON.
+    Invalid(Error),
+}
+
+/// Walks the vault hierarchy of a [`Storage`], loading and parsing each item the first time it is reached.
+///
+/// References to items that are missing, cannot be read or are not valid are reported along the way, rather than
+///  ending the walk.
+pub struct VaultWalker<'a, S: ?Sized> {
+    storage: &'a mut S,
+    root: Uuid,
+    loaded: HashMap<Uuid, Loaded>,
+}
+
+impl<'a, S: Storage + Send + ?Sized> VaultWalker<'a, S> {
+    /// Creates a walker starting at `root`, which is usually the user's `root_object`.
+    ///
+    /// A root that does not exist yet is treated as an empty vault.
+    pub fn new(storage: &'a mut S, root: Uuid) -> Self {
+        Self {
+            storage,
+            root,
+            loaded: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> Uuid {
+        self.root
+    }
+
+    /// Loads the item `id`.
+    ///
+    /// Fails only on errors that are not about the item itself, such as a locked storage or a failed request.
+    pub async fn load(&mut self, id: Uuid) -> Result<&Loaded> {
+        if !self.loaded.contains_key(&id) {
+            let loaded = match self.read(id).await {
+                Ok(json) => match Item::from_json(&json) {
+                    Ok(item) => Loaded::Item(item),
+                    Err(e) => Loaded::Invalid(invalid_item(id, e)),
+                },
+                Err(e) if *e.code() == ErrorCode::NotFound && id == self.root => {
+                    Loaded::Item(Item::Vault(Vault {
+                        display_name: None,
+                        content: Vec::new(),
+                    }))
+                }
+                Err(e) => match e.code() {
+                    ErrorCode::NotFound => Loaded::Missing,
+                    ErrorCode::PermissionDenied | ErrorCode::Crypto => Loaded::Inaccessible(e),
+                    _ => return Err(e),
+                },
+            };
+            self.loaded.insert(id, loaded);
+        }
+        Ok(&self.loaded[&id])
+    }
+
+    /// Loads the item `id`, returning `None` if it is not a readable, valid item.
+    pub async fn get(&mut self, id: Uuid) -> Result<Option<&Item>> {
+        Ok(match self.load(id).await? {
+            Loaded::Item(item) => Some(item),
+            _ => None,
+        })
+    }
+
+    async fn read(&mut self, id: Uuid) -> Result<Vec<u8>> {
+        let mut item = self.storage.item(id).await?;
+        let mut json = Vec::new();
+        item.read()
+            .await?
+            .read_to_end(&mut json)
+            .await
+            .map_err(|e| Error::new(ErrorCode::Transport, e.to_string()))?;
+        Ok(json)
+    }
+
+    /// Iterates over the hierarchy depth-first, starting with the root.
+    pub fn depth_first(&mut self) -> DepthFirst<'_, 'a, S> {
+        DepthFirst {
+            walker: self,
+            stack: Vec::new(),
+            visited: HashSet::new(),
+            started: false,
+        }
+    }
+
+    /// Finds an item by the `display_name`s of the vaults leading to it, such as `Work/Cloud/AWS`.
+    ///
+    /// Where several items in a vault have the same name, the first one in its `content` is used.
+    pub async fn find_path(&mut self, path: &str) -> Result<Option<Uuid>> {
+        let mut current = self.root;
+        for name in path.split(PATH_SEPARATOR).filter(|name| !name.is_empty()) {
+            let Some(item) = self.get(current).await? else {
+                return Ok(None);
+            };
+
+            let mut found = None;
+            for child in children(item) {
+                let Some(item) = self.get(child).await? else {
+                    continue;
+                };
+                if item.display_name() == Some(name) {
+                    found = Some(child);
+                    break;
+                }
+            }
+
+            match found {
+                Some(child) => current = child,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+
+    /// Walks every vault reachable from the root once, counting the references to each item.
+    ///
+    /// `items` is every item the user can access; those that are not referenced from any vault are reported as
+    ///  orphans.
+    pub async fn reference_report(
+        &mut self,
+        items: impl IntoIterator<Item = Uuid>,
+    ) -> Result<ReferenceReport> {
+        let mut report = ReferenceReport::default();
+        let mut in_progress = HashSet::new();
+        let mut done = HashSet::new();
+
+        let root = self.root;
+        let root_children = self.get(root).await?.map(children).unwrap_or_default();
+        in_progress.insert(root);
+        let mut stack = vec![(root, root_children, 0)];
+
+        while let Some((parent, content, next)) = stack.last_mut() {
+            let Some(&id) = content.get(*next) else {
+                in_progress.remove(parent);
+                done.insert(*parent);
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            let parent = *parent;
+
+            *report.references.entry(id).or_default() += 1;
+            if in_progress.contains(&id) {
+                report.cycles.push((parent, id));
+                continue;
+            }
+            if done.contains(&id) {
+                continue;
+            }
+
+            let problems = match self.load(id).await? {
+                Loaded::Item(item) => {
+                    let content = children(item);
+                    in_progress.insert(id);
+                    stack.push((id, content, 0));
+                    continue;
+                }
+                Loaded::Missing => &mut report.dangling,
+                Loaded::Inaccessible(_) => &mut report.inaccessible,
+                Loaded::Invalid(_) => &mut report.invalid,
+            };
+            problems.push((parent, id));
+            done.insert(id);
+        }
+
+        report.orphans = items
+            .into_iter()
+            .filter(|id| *id != root && !report.references.contains_key(id))
+            .collect();
+        Ok(report)
+    }
+}
+
+/// An item reached by [`DepthFirst`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Visit {
+    Item {
+        id: Uuid,
+        /// The vaults leading to the item, starting with the root. Empty for the root itself.
+        path: Vec<Uuid>,
+        item: Item,
+    },
+    /// A reference to a vault that contains the vault holding the reference. The cycle is not followed.
+    Cycle { id: Uuid, path: Vec<Uuid> },
+    /// A further reference to a vault that was already visited along another path. Its content is not visited
+    ///  again.
+    Shared { id: Uuid, path: Vec<Uuid> },
+    /// A reference to an item that does not exist.
+    Dangling { id: Uuid, path: Vec<Uuid> },
+    /// A reference to an item that cannot be read with the user's permissions or keys.
+    Inaccessible {
+        id: Uuid,
+        path: Vec<Uuid>,
+        error: Error,
+    },
+    /// A reference to an item that is not a valid item.
+    Invalid {
+        id: Uuid,
+        path: Vec<Uuid>,
+        error: Error,
+    },
+}
+
+impl Visit {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Visit::Item { id, .. }
+            | Visit::Cycle { id, .. }
+            | Visit::Shared { id, .. }
+            | Visit::Dangling { id, .. }
+            | Visit::Inaccessible { id, .. }
+            | Visit::Invalid { id, .. } => *id,
+        }
+    }
+
+    /// The vaults leading to the item, starting with the root.
+    pub fn path(&self) -> &[Uuid] {
+        match self {
+            Visit::Item { path, .. }
+            | Visit::Cycle { path, .. }
+            | Visit::Shared { path, .. }
+            | Visit::Dangling { path, .. }
+            | Visit::Inaccessible { path, .. }
+            | Visit::Invalid { path, .. } => path,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.path().len()
+    }
+}
+
+struct Frame {
+    id: Uuid,
+    content: Vec<Uuid>,
+    next: usize,
+}
+
+/// A lazy depth-first iteration over the vault hierarchy, created by [`VaultWalker::depth_first`].
+///
+/// The content of each vault is visited once, so that vaults shared along many paths do not make the iteration
+///  exponential. Every further reference to a visited vault is reported as [`Visit::Shared`], while items that are
+///  not vaults are visited once for each reference.
+pub struct DepthFirst<'w, 'a, S: ?Sized> {
+    walker: &'w mut VaultWalker<'a, S>,
+    stack: Vec<Frame>,
+    /// The vaults whose content was visited.
+    visited: HashSet<Uuid>,
+    started: bool,
+}
+
+impl<S: Storage + Send + ?Sized> DepthFirst<'_, '_, S> {
+    /// Returns the next item, loading it if needed, or `None` once the whole hierarchy was visited.
+    pub async fn next(&mut self) -> Option<Result<Visit>> {
+        if !self.started {
+            self.started = true;
+            let root = self.walker.root;
+            return Some(self.enter(root, Vec::new()).await);
+        }
+
+        loop {
+            let frame = self.stack.last_mut()?;
+            let Some(&id) = frame.content.get(frame.next) else {
+                self.stack.pop();
+                continue;
+            };
+            frame.next += 1;
+
+            let path: Vec<Uuid> = self.stack.iter().map(|frame| frame.id).collect();
+            if path.contains(&id) {
+                return Some(Ok(Visit::Cycle { id, path }));
+            }
+            if self.visited.contains(&id) {
+                return Some(Ok(Visit::Shared { id, path }));
+            }
+            return Some(self.enter(id, path).await);
+        }
+    }
+
+    async fn enter(&mut self, id: Uuid, path: Vec<Uuid>) -> Result<Visit> {
+        match self.walker.load(id).await? {
+            Loaded::Item(item) => {
+                let item = item.clone();
+                if let Item::Vault(_) = item {
+                    self.visited.insert(id);
+                }
+                self.stack.push(Frame {
+                    id,
+                    content: children(&item),
+                    next: 0,
+                });
+                Ok(Visit::Item { id, path, item })
+            }
+            Loaded::Missing => Ok(Visit::Dangling { id, path }),
+            Loaded::Inaccessible(error) => Ok(Visit::Inaccessible {
+                id,
+                path,
+                error: error.clone(),
+            }),
+            Loaded::Invalid(error) => Ok(Visit::Invalid {
+                id,
+                path,
+                error: error.clone(),
+            }),
+        }
+    }
+}
+
+/// The result of [`VaultWalker::reference_report`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReferenceReport {
+    /// The number of vaults that reference each reachable item.
+    pub references: HashMap<Uuid, usize>,
+    /// Items that are not referenced from any reachable vault.
+    pub orphans: Vec<Uuid>,
+    /// References to items that do not exist, as `(vault, item)`.
+    pub dangling: Vec<(Uuid, Uuid)>,
+    /// References to items that cannot be read with the user's permissions or keys, as `(vault, item)`.
+    pub inaccessible: Vec<(Uuid, Uuid)>,
+    /// References to items that are not valid items, as `(vault, item)`.
+    pub invalid: Vec<(Uuid, Uuid)>,
+    /// References that close a cycle, as `(vault, ancestor)`.
+    pub cycles: Vec<(Uuid, Uuid)>,
+}
+
+#[cfg(test)]
+mod test {
+    use async_std::io::{Cursor, Read};
+    use common::{http::api::item::NewItemRequest, item::PasswordItem};
+
+    use super::*;
+    use crate::{
+        macros::async_trait,
+        storage::{
+            local::{
+                test::{create_json, create_raw, TempVault},
+                LocalStorage,
+            },
+            Authentication, Item as StorageItem,
+        },
+    };
+
+    fn vault(name: &str, content: Vec<Uuid>) -> Item {
+        Item::Vault(Vault {
+            display_name: Some(name.to_string()),
+            content,
+        })
+    }
+
+    fn password(name: &str) -> Item {
+        Item::Password(PasswordItem {
+            display_name: Some(name.to_string()),
+            url: "example.com".parse().unwrap(),
+            login_id: None,
+            login_password: "hunter2".to_string(),
+        })
+    }
+
+    async fn set(storage: &mut LocalStorage, id: Uuid, item: &Item) {
+        storage
+            .item(id)
+            .await
+            .unwrap()
+            .write(&mut Cursor::new(item.to_json().unwrap()))
+            .await
+            .unwrap();
+    }
+
+    /// The IDs of the items in [`fixture`].
+    struct Ids {
+        root: Uuid,
+        work: Uuid,
+        cloud: Uuid,
+        aws: Uuid,
+        shared: Uuid,
+        missing: Uuid,
+        orphan: Uuid,
+    }
+
+    /// Builds this hierarchy, where `Cloud` references its parent and `Shared` is reachable along two paths:
+    ///
+    /// ```text
+    /// root
+    /// ├── Work
+    /// │   ├── Cloud
+    /// │   │   ├── AWS
+    /// │   │   └── Work (cycle)
+    /// │   └── Shared
+    /// │       └── AWS
+    /// ├── (missing)
+    /// └── Shared
+    /// ```
+    ///
+    /// An `Orphan` item is not referenced from any vault.
+    async fn fixture(storage: &mut LocalStorage) -> Ids {
+        let root = storage.root_info().unwrap().root_object;
+        let aws = create_json(storage, &password("AWS")).await;
+        let shared = create_json(storage, &vault("Shared", vec![aws])).await;
+        let work = create_json(storage, &vault("Work", Vec::new())).await;
+        let cloud = create_json(storage, &vault("Cloud", vec![aws, work])).await;
+        let orphan = create_json(storage, &password("Orphan")).await;
+        let missing = Uuid::new_v4();
+        set(storage, work, &vault("Work", vec![cloud, shared])).await;
+        set(
+            storage,
+            root,
+            &Item::Vault(Vault {
+                display_name: None,
+                content: vec![work, missing, shared],
+            }),
+        )
+        .await;
+        Ids {
+            root,
+            work,
+            cloud,
+            aws,
+            shared,
+            missing,
+            orphan,
+        }
+    }
+
+    /// A storage that refuses to read `denied`, as a server does for items the user has no `Read` permission on.
+    struct Denying<'a> {
+        inner: &'a mut LocalStorage,
+        denied: Uuid,
+    }
+
+    #[async_trait]
+    impl Storage for Denying<'_> {
+        fn is_unlocked(&self) -> bool {
+            self.inner.is_unlocked()
+        }
+
+        fn authentication(&mut self) -> Box<dyn Authentication + '_> {
+            self.inner.authentication()
+        }
+
+        async fn item(&mut self, id: Uuid) -> Result<Box<dyn StorageItem + '_>> {
+            if id == self.denied {
+                return Err(Error::new(ErrorCode::PermissionDenied, "denied"));
+            }
+            self.inner.item(id).await
+        }
+
+        async fn create_item(
+            &mut self,
+            request: NewItemRequest,
+            contents: &mut (dyn Read + Unpin + Send),
+        ) -> Result<Box<dyn StorageItem + '_>> {
+            self.inner.create_item(request, contents).await
+        }
+
+        async fn link_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()> {
+            self.inner.link_parent(id, vault).await
+        }
+
+        async fn unlink_parent(&mut self, id: Uuid, vault: Uuid) -> Result<()> {
+            self.inner.unlink_parent(id, vault).await
+        }
+    }
+
+    async fn visits<S: Storage + Send + ?Sized>(
+        walker: &mut VaultWalker<'_, S>,
+    ) -> Vec<(&'static str, Uuid, Vec<Uuid>)> {
+        let mut visits = Vec::new();
+        let mut iter = walker.depth_first();
+        while let Some(visit) = iter.next().await {
+            let visit = visit.unwrap();
+            let kind = match visit {
+                Visit::Item { .. } => "item",
+                Visit::Cycle { .. } => "cycle",
+                Visit::Shared { .. } => "shared",
+                Visit::Dangling { .. } => "dangling",
+                Visit::Inaccessible { .. } => "inaccessible",
+                Visit::Invalid { .. } => "invalid",
+            };
+            visits.push((kind, visit.id(), visit.path().to_vec()));
+        }
+        visits
+    }
+
+    #[test]
+    fn depth_first() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let ids = fixture(&mut storage).await;
+            let mut walker = VaultWalker::new(&mut storage, ids.root);
+
+            let Ids {
+                root,
+                work,
+                cloud,
+                aws,
+                shared,
+                missing,
+                ..
+            } = ids;
+            assert_eq!(
+                visits(&mut walker).await,
+                [
+                    ("item", root, vec![]),
+                    ("item", work, vec![root]),
+                    ("item", cloud, vec![root, work]),
+                    ("item", aws, vec![root, work, cloud]),
+                    ("cycle", work, vec![root, work, cloud]),
+                    ("item", shared, vec![root, work]),
+                    ("item", aws, vec![root, work, shared]),
+                    ("dangling", missing, vec![root]),
+                    ("shared", shared, vec![root]),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn reference_report() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let ids = fixture(&mut storage).await;
+            let items = storage.item_ids().unwrap();
+            let report = VaultWalker::new(&mut storage, ids.root)
+                .reference_report(items)
+                .await
+                .unwrap();
+
+            assert_eq!(
+                report.references,
+                HashMap::from([
+                    (ids.work, 2),
+                    (ids.cloud, 1),
+                    (ids.aws, 2),
+                    (ids.shared, 2),
+                    (ids.missing, 1),
+                ])
+            );
+            assert_eq!(report.cycles, [(ids.cloud, ids.work)]);
+            assert_eq!(report.dangling, [(ids.root, ids.missing)]);
+            assert_eq!(report.orphans, [ids.orphan]);
+        });
+    }
+
+    #[test]
+    fn find_path() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let ids = fixture(&mut storage).await;
+            let mut walker = VaultWalker::new(&mut storage, ids.root);
+
+            assert_eq!(walker.find_path("").await.unwrap(), Some(ids.root));
+            assert_eq!(
+                walker.find_path("Work/Cloud/AWS").await.unwrap(),
+                Some(ids.aws)
+            );
+            assert_eq!(
+                walker.find_path("/Work//Cloud/").await.unwrap(),
+                Some(ids.cloud)
+            );
+            assert_eq!(
+                walker.find_path("Work/Cloud/Work/Shared").await.unwrap(),
+                Some(ids.shared)
+            );
+            assert_eq!(walker.find_path("Work/Missing").await.unwrap(), None);
+            assert_eq!(
+                walker.find_path("Work/Cloud/AWS/Deeper").await.unwrap(),
+                None
+            );
+        });
+    }
+
+    #[test]
+    fn add_and_remove() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let ids = fixture(&mut storage).await;
+
+            add_to_vault(&mut storage, ids.work, ids.orphan)
+                .await
+                .unwrap();
+            add_to_vault(&mut storage, ids.work, ids.orphan)
+                .await
+                .unwrap();
+            let mut walker = VaultWalker::new(&mut storage, ids.root);
+            assert_eq!(
+                walker.find_path("Work/Orphan").await.unwrap(),
+                Some(ids.orphan)
+            );
+            assert_eq!(
+                walker.get(ids.work).await.unwrap(),
+                Some(&vault("Work", vec![ids.cloud, ids.shared, ids.orphan]))
+            );
+
+            remove_from_vault(&mut storage, ids.work, ids.cloud)
+                .await
+                .unwrap();
+            let mut walker = VaultWalker::new(&mut storage, ids.root);
+            assert_eq!(
+                walker.get(ids.work).await.unwrap(),
+                Some(&vault("Work", vec![ids.shared, ids.orphan]))
+            );
+
+            let err = add_to_vault(&mut storage, ids.aws, ids.orphan)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(*err.code(), ErrorCode::InvalidRequest);
+        });
+    }
+
+    #[test]
+    fn unreadable_references() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let ids = fixture(&mut storage).await;
+            let text = create_raw(&mut storage, "text/plain", b"not an item").await;
+            let secret = create_json(&mut storage, &password("Secret")).await;
+            let cloud = vault("Cloud", vec![ids.aws, ids.work, secret]);
+            set(&mut storage, ids.cloud, &cloud).await;
+            set(
+                &mut storage,
+                ids.root,
+                &Item::Vault(Vault {
+                    display_name: None,
+                    content: vec![ids.work, text, secret],
+                }),
+            )
+            .await;
+            let items = storage.item_ids().unwrap();
+            let mut storage = Denying {
+                inner: &mut storage,
+                denied: secret,
+            };
+            let mut walker = VaultWalker::new(&mut storage, ids.root);
+
+            let (root, work, cloud) = (ids.root, ids.work, ids.cloud);
+            let visited = visits(&mut walker).await;
+            assert_eq!(
+                visited,
+                [
+                    ("item", root, vec![]),
+                    ("item", work, vec![root]),
+                    ("item", cloud, vec![root, work]),
+                    ("item", ids.aws, vec![root, work, cloud]),
+                    ("cycle", work, vec![root, work, cloud]),
+                    ("inaccessible", secret, vec![root, work, cloud]),
+                    ("item", ids.shared, vec![root, work]),
+                    ("item", ids.aws, vec![root, work, ids.shared]),
+                    ("invalid", text, vec![root]),
+                    ("inaccessible", secret, vec![root]),
+                ]
+            );
+            assert!(matches!(
+                walker.load(text).await.unwrap(),
+                Loaded::Invalid(e) if *e.code() == ErrorCode::InvalidData
+            ));
+            assert_eq!(
+                walker.find_path("Work/Cloud/AWS").await.unwrap(),
+                Some(ids.aws)
+            );
+            assert_eq!(walker.find_path("Secret").await.unwrap(), None);
+
+            let report = walker.reference_report(items).await.unwrap();
+            assert_eq!(report.invalid, [(root, text)]);
+            assert_eq!(report.inaccessible, [(cloud, secret)]);
+            assert_eq!(report.references[&secret], 2);
+            assert_eq!(report.orphans, [ids.orphan]);
+        });
+    }
+
+    #[test]
+    fn missing_root() {
+        async_std::task::block_on(async {
+            let temp = TempVault::new();
+            let mut storage = temp.create().await;
+            let orphan = create_json(&mut storage, &password("Orphan")).await;
+            let items = storage.item_ids().unwrap();
+            let root = Uuid::new_v4();
+            let mut walker = VaultWalker::new(&mut storage, root);
+
+            assert_eq!(
+                walker.get(root).await.unwrap(),
+                Some(&Item::Vault(Vault {
+                    display_name: None,
+                    content: Vec::new(),
+                }))
+            );
+            assert_eq!(visits(&mut walker).await, [("item", root, vec![])]);
+            assert_eq!(walker.find_path("Orphan").await.unwrap(), None);
+
+            let report = walker.reference_report(items).await.unwrap();
+            assert!(report.references.is_empty());
+            assert!(report.orphans.contains(&orphan));
+        });
+    }
+}
@@ -1,3 +1,4 @@
+use base64::{prelude::BASE64_STANDARD_NO_PAD, Engine};
 use std::{
     borrow::{Borrow, BorrowMut},
     num::ParseIntError,
@@ -31,6 +32,19 @@ impl Bytes {
     pub fn into_inner(self) -> Vec<u8> {
         self.0
     }
+
+    /// Encodes the bytes as unpadded base64 using the standard alphabet, the same as the human readable serialized form.
+    pub fn to_base64(&self) -> String {
+        BASE64_STANDARD_NO_PAD.encode(self)
+    }
+
+    /// Decodes padded or unpadded base64 using the standard alphabet.
+    pub fn from_base64(s: &str) -> Option<Self> {
+        BASE64_STANDARD_NO_PAD
+            .decode(s.trim_end_matches('='))
+            .ok()
+            .map(Self)
+    }
 }
 
 impl<I: SliceIndex<[u8]>> Index<I> for Bytes {
@@ -155,3 +169,246 @@ impl FromStr for Version {
         }
     }
 }
+
+/// A [`time::Duration`] that is written in the ISO 8601 duration format, such as `PT30S` or `P1DT12H`.
+///
+/// Only weeks, days, hours, minutes and seconds are supported, as years and months do not have a fixed length.
+/// The seconds component may have a fractional part. Durations in items are never negative, so a leading `-` is
+///  rejected when parsing.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IsoDuration(pub time::Duration);
+
+impl IsoDuration {
+    pub const fn seconds(seconds: i64) -> Self {
+        Self(time::Duration::seconds(seconds))
+    }
+}
+
+impl From<time::Duration> for IsoDuration {
+    fn from(value: time::Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl From<IsoDuration> for time::Duration {
+    fn from(value: IsoDuration) -> Self {
+        value.0
+    }
+}
+
+impl core::fmt::Display for IsoDuration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut duration = self.0;
+        if duration.is_negative() {
+            f.write_str("-")?;
+            duration = duration.abs();
+        }
+        f.write_str("P")?;
+
+        let days = duration.whole_days();
+        if days != 0 {
+            write!(f, "{days}D")?;
+        }
+
+        let hours = duration.whole_hours() % 24;
+        let minutes = duration.whole_minutes() % 60;
+        let seconds = duration.whole_seconds() % 60;
+        let nanos = duration.subsec_nanoseconds();
+        if days != 0 && hours == 0 && minutes == 0 && seconds == 0 && nanos == 0 {
+            return Ok(());
+        }
+
+        f.write_str("T")?;
+        if hours != 0 {
+            write!(f, "{hours}H")?;
+        }
+        if minutes != 0 {
+            write!(f, "{minutes}M")?;
+        }
+        if seconds != 0 || nanos != 0 || (hours == 0 && minutes == 0) {
+            if nanos != 0 {
+                let frac = format!("{nanos:09}");
+                write!(f, "{seconds}.{}S", frac.trim_end_matches('0'))?;
+            } else {
+                write!(f, "{seconds}S")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DurationFromStringError {
+    /// The string does not start with `P`, or has no components.
+    MissingDesignator,
+    /// A component is not a number followed by a unit designator.
+    InvalidComponent,
+    /// A unit is used that this implementation does not support (years or months), or is in the wrong place.
+    UnsupportedUnit(char),
+    /// The duration does not fit in a [`time::Duration`].
+    Overflow,
+    /// The duration starts with `-`.
+    Negative,
+}
+
+impl core::fmt::Display for DurationFromStringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingDesignator => f.write_str("not an ISO 8601 duration"),
+            Self::InvalidComponent => f.write_str("invalid ISO 8601 duration component"),
+            Self::UnsupportedUnit(unit) => write!(f, "unsupported ISO 8601 duration unit {unit}"),
+            Self::Overflow => f.write_str("duration is too large"),
+            Self::Negative => f.write_str("duration is negative"),
+        }
+    }
+}
+
+impl std::error::Error for DurationFromStringError {}
+
+impl FromStr for IsoDuration {
+    type Err = DurationFromStringError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with('-') {
+            return Err(DurationFromStringError::Negative);
+        }
+        let s = s
+            .strip_prefix('+')
+            .unwrap_or(s)
+            .strip_prefix('P')
+            .ok_or(DurationFromStringError::MissingDesignator)?;
+        if s.is_empty() || s.ends_with('T') {
+            return Err(DurationFromStringError::MissingDesignator);
+        }
+
+        let mut total = time::Duration::ZERO;
+        let mut in_time = false;
+        let mut rest = s;
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix('T') {
+                if in_time {
+                    return Err(DurationFromStringError::InvalidComponent);
+                }
+                in_time = true;
+                rest = r;
+                continue;
+            }
+
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+                .ok_or(DurationFromStringError::InvalidComponent)?;
+            let (number, unit) = (&rest[..end], rest[end..].chars().next().unwrap());
+            rest = &rest[end + unit.len_utf8()..];
+            if number.is_empty() {
+                return Err(DurationFromStringError::InvalidComponent);
+            }
+
+            let unit_seconds = match (in_time, unit) {
+                (false, 'W') => 7 * 86400,
+                (false, 'D') => 86400,
+                (true, 'H') => 3600,
+                (true, 'M') => 60,
+                (true, 'S') => 1,
+                (_, unit) => return Err(DurationFromStringError::UnsupportedUnit(unit)),
+            };
+
+            let component = if unit == 'S' {
+                let (whole, frac) = number.split_once(['.', ',']).unwrap_or((number, ""));
+                let whole: i64 = whole
+                    .parse()
+                    .map_err(|_| DurationFromStringError::InvalidComponent)?;
+                if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(DurationFromStringError::InvalidComponent);
+                }
+                let nanos = if frac.is_empty() {
+                    0
+                } else {
+                    format!("{frac:0<9}")
+                        .parse()
+                        .map_err(|_| DurationFromStringError::InvalidComponent)?
+                };
+                time::Duration::new(whole, nanos)
+            } else {
+                let count: i64 = number
+                    .parse()
+                    .map_err(|_| DurationFromStringError::InvalidComponent)?;
+                count
+                    .checked_mul(unit_seconds)
+                    .map(time::Duration::seconds)
+                    .ok_or(DurationFromStringError::Overflow)?
+            };
+            total = total
+                .checked_add(component)
+                .ok_or(DurationFromStringError::Overflow)?;
+        }
+
+        Ok(Self(total))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Result<time::Duration, DurationFromStringError> {
+        s.parse::<IsoDuration>().map(time::Duration::from)
+    }
+
+    #[test]
+    fn iso_duration_from_str() {
+        assert_eq!(parse("PT30S"), Ok(time::Duration::seconds(30)));
+        assert_eq!(parse("PT1M"), Ok(time::Duration::minutes(1)));
+        assert_eq!(parse("PT1.5S"), Ok(time::Duration::milliseconds(1500)));
+        assert_eq!(parse("PT0,25S"), Ok(time::Duration::milliseconds(250)));
+        assert_eq!(parse("PT1H2M3S"), Ok(time::Duration::seconds(3723)));
+        assert_eq!(parse("P1D"), Ok(time::Duration::days(1)));
+        assert_eq!(parse("P1DT12H"), Ok(time::Duration::hours(36)));
+        assert_eq!(parse("P2W"), Ok(time::Duration::weeks(2)));
+        assert_eq!(parse("P1W1D"), Ok(time::Duration::days(8)));
+        assert_eq!(parse("+PT30S"), Ok(time::Duration::seconds(30)));
+    }
+
+    #[test]
+    fn iso_duration_from_str_rejects() {
+        use DurationFromStringError::*;
+
+        assert_eq!(parse("P"), Err(MissingDesignator));
+        assert_eq!(parse("PT"), Err(MissingDesignator));
+        assert_eq!(parse("P1DT"), Err(MissingDesignator));
+        assert_eq!(parse("30S"), Err(MissingDesignator));
+        assert_eq!(parse("-PT30S"), Err(Negative));
+        assert_eq!(parse("-P1D"), Err(Negative));
+        assert_eq!(parse("P1Y"), Err(UnsupportedUnit('Y')));
+        assert_eq!(parse("P1M"), Err(UnsupportedUnit('M')));
+        assert_eq!(parse("PT1D"), Err(UnsupportedUnit('D')));
+        assert_eq!(parse("PTS"), Err(InvalidComponent));
+        assert_eq!(parse("PT1"), Err(InvalidComponent));
+        assert_eq!(parse("PT1.5M"), Err(InvalidComponent));
+        assert_eq!(parse("PT0.0000000001S"), Err(InvalidComponent));
+        assert_eq!(parse("PT1HT1S"), Err(InvalidComponent));
+        assert_eq!(parse(&format!("P{}W", i64::MAX)), Err(Overflow));
+    }
+
+    #[test]
+    fn iso_duration_display() {
+        let display = |duration| IsoDuration(duration).to_string();
+        assert_eq!(display(time::Duration::seconds(30)), "PT30S");
+        assert_eq!(display(time::Duration::minutes(1)), "PT1M");
+        assert_eq!(display(time::Duration::milliseconds(1500)), "PT1.5S");
+        assert_eq!(display(time::Duration::seconds(3723)), "PT1H2M3S");
+        assert_eq!(display(time::Duration::ZERO), "PT0S");
+        assert_eq!(display(time::Duration::days(1)), "P1D");
+        assert_eq!(display(time::Duration::hours(36)), "P1DT12H");
+        assert_eq!(display(time::Duration::weeks(2)), "P14D");
+
+        for s in [
+            "PT30S",
+            "PT1M",
+            "PT1.5S",
+            "PT0.000000001S",
+            "P1DT12H",
+            "P3DT4H5M6.07S",
+        ] {
+            assert_eq!(display(parse(s).unwrap()), s);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use base64::{prelude::BASE64_STANDARD_NO_PAD, Engine};
 use serde::{
-    de::{Deserialize, Deserializer, Error, SeqAccess, Unexpected, Visitor},
+    de::{Deserialize, Deserializer, Error, Unexpected, Visitor},
     ser::{Serialize, Serializer},
 };
 
-use super::{Bytes, Version};
+use super::{Bytes, IsoDuration, Version};
 
 impl Serialize for Bytes {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -43,7 +43,7 @@ impl<'de> Deserialize<'de> for Bytes {
                 let engine = BASE64_STANDARD_NO_PAD;
                 engine
                     .decode(v)
-                    .map_err(|e| {
+                    .map_err(|_| {
                         E::invalid_value(
                             Unexpected::Str(v),
                             &"a base64 encoded string using the standard alphabet",
@@ -140,3 +140,38 @@ impl<'de> Deserialize<'de> for Version {
         }
     }
 }
+
+impl Serialize for IsoDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IsoDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        pub struct IsoDurationVisitor;
+
+        impl<'de> Visitor<'de> for IsoDurationVisitor {
+            type Value = IsoDuration;
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an ISO 8601 duration")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                v.parse()
+                    .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_str(IsoDurationVisitor)
+    }
+}
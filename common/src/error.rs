@@ -1,13 +1,185 @@
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::suite::UnknownAlgorithm;
+
+/// An error, as returned by the SDK and sent by the server.
+///
+/// ## Error Body
+///
+/// Failed requests respond with an error status, and a JSON body of the form
+///
+/// ```json
+/// {"code": "not-found", "message": "no such user"}
+/// ```
+///
+/// The status is chosen by [`ErrorCode::http_status`]. Clients should prefer the `code` of the body over the status,
+///  and use [`ErrorCode::from_http_status`] only when there is no body.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Error {
-    text: String,
+    #[serde(rename = "code")]
     mach_code: ErrorCode,
+    #[serde(rename = "message")]
+    text: String,
+}
+
+impl Error {
+    pub fn new(mach_code: ErrorCode, text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            mach_code,
+        }
+    }
+
+    pub fn code(&self) -> &ErrorCode {
+        &self.mach_code
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.mach_code, self.text)
+    }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+impl std::error::Error for Error {}
+
+impl From<UnknownAlgorithm> for Error {
+    fn from(value: UnknownAlgorithm) -> Self {
+        Error::new(ErrorCode::UnsupportedAlgorithm, value.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum ErrorCode {
+    /// The request has no valid session, or authentication failed.
     NotAuthenticated,
+    /// The authenticated user is not allowed to perform the request.
+    PermissionDenied,
     NotFound,
+    /// The object was changed by someone else since it was read, so the change was not applied, or the object
+    ///  already exists.
+    Conflict,
+    /// The request is malformed or has invalid parameters.
+    InvalidRequest,
+    /// The request uses an algorithm that is not supported.
+    UnsupportedAlgorithm,
+    /// Too many requests were made. The request can be retried later.
+    RateLimited,
+    /// The request or stored data uses a protocol or format version that is not supported.
+    VersionMismatch,
+    /// A cryptographic operation failed.
+    Crypto,
+    /// The request could not be sent, or the response could not be understood.
+    Transport,
+    /// The server is not the one that was expected, as identified by its server ID.
+    ServerMismatch,
+    /// Stored data, such as an item or a search index, could not be parsed.
+    InvalidData,
+    /// Local storage, such as a vault file, could not be read or written.
+    Storage,
+    /// The server failed to handle the request.
+    Internal,
+    /// A code sent by a newer server, which this version does not know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// The name of the code in error bodies.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCode::NotAuthenticated => "not-authenticated",
+            ErrorCode::PermissionDenied => "permission-denied",
+            ErrorCode::NotFound => "not-found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::InvalidRequest => "invalid-request",
+            ErrorCode::UnsupportedAlgorithm => "unsupported-algorithm",
+            ErrorCode::RateLimited => "rate-limited",
+            ErrorCode::VersionMismatch => "version-mismatch",
+            ErrorCode::Crypto => "crypto",
+            ErrorCode::Transport => "transport",
+            ErrorCode::ServerMismatch => "server-mismatch",
+            ErrorCode::InvalidData => "invalid-data",
+            ErrorCode::Storage => "storage",
+            ErrorCode::Internal => "internal",
+            ErrorCode::Unknown => "unknown",
+        }
+    }
+
+    /// The HTTP status the server responds with for this code.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            ErrorCode::NotAuthenticated => 401,
+            ErrorCode::PermissionDenied => 403,
+            ErrorCode::NotFound => 404,
+            ErrorCode::Conflict => 409,
+            ErrorCode::InvalidRequest | ErrorCode::Crypto => 400,
+            ErrorCode::UnsupportedAlgorithm => 422,
+            ErrorCode::RateLimited => 429,
+            ErrorCode::VersionMismatch => 426,
+            ErrorCode::Transport | ErrorCode::ServerMismatch => 502,
+            ErrorCode::InvalidData
+            | ErrorCode::Storage
+            | ErrorCode::Internal
+            | ErrorCode::Unknown => 500,
+        }
+    }
+
+    /// The code for an error response that has no error body.
+    ///
+    /// Responses without a body are not produced by the passman handlers, so `422` is taken to be a request body
+    ///  that failed to parse, as the server's catcher reports it, rather than [`ErrorCode::UnsupportedAlgorithm`].
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            401 => ErrorCode::NotAuthenticated,
+            403 => ErrorCode::PermissionDenied,
+            404 | 410 => ErrorCode::NotFound,
+            409 | 412 => ErrorCode::Conflict,
+            429 => ErrorCode::RateLimited,
+            426 | 505 => ErrorCode::VersionMismatch,
+            502..=504 => ErrorCode::Transport,
+            400..=499 => ErrorCode::InvalidRequest,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
+
+#[cfg(test)]
+mod test {
+    use super::ErrorCode;
+
+    #[test]
+    fn http_status_round_trip() {
+        for code in [
+            ErrorCode::NotAuthenticated,
+            ErrorCode::PermissionDenied,
+            ErrorCode::NotFound,
+            ErrorCode::Conflict,
+            ErrorCode::InvalidRequest,
+            ErrorCode::RateLimited,
+            ErrorCode::VersionMismatch,
+            ErrorCode::Transport,
+            ErrorCode::Internal,
+        ] {
+            assert_eq!(ErrorCode::from_http_status(code.http_status()), code);
+        }
+    }
+
+    #[test]
+    fn unprocessable_entity() {
+        assert_eq!(ErrorCode::from_http_status(422), ErrorCode::InvalidRequest);
+    }
+}
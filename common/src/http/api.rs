@@ -2,10 +2,7 @@ use serde::{Deserialize, Serialize};
 use time::PrimitiveDateTime;
 use uuid::{uuid, Uuid};
 
-use crate::{
-    data::{Bytes, Version},
-    suite::AsymmetricCipherAlgorithm,
-};
+use crate::data::Version;
 
 pub const PROTOCOL_ID_PASSMAN: Uuid = uuid!("019038bd-15b8-75b5-8de3-9e6dfd801916");
 
@@ -29,7 +26,13 @@ pub mod acl {
     use serde::{Deserialize, Serialize};
     use uuid::Uuid;
 
-    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    /// How an [`AclRow`] affects its subject's permission.
+    ///
+    /// Permissions are resolved starting at the object itself, then its parent vaults, then the global
+    ///  permissions, and the first level with an `Allow` or `Deny` row decides. `Deny` wins over `Allow` at the
+    ///  same level. `Forbid` at any level denies the permission, and cannot be overridden. `Inherit` rows have no
+    ///  effect. If nothing decides, the permission is denied.
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Hash, PartialEq, Eq)]
     #[serde(rename_all = "kebab-case")]
     pub enum AclMode {
         Inherit,
@@ -38,25 +41,181 @@ pub mod acl {
         Forbid,
     }
 
+    impl AclMode {
+        /// The name of the mode, as used by the serialized form.
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::Inherit => "inherit",
+                Self::Allow => "allow",
+                Self::Deny => "deny",
+                Self::Forbid => "forbid",
+            }
+        }
+    }
+
+    impl core::fmt::Display for AclMode {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str(self.name())
+        }
+    }
+
+    /// Error returned when parsing an unknown [`AclMode`].
+    #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct UnknownAclMode(pub String);
+
+    impl core::fmt::Display for UnknownAclMode {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_fmt(format_args!("unknown ACL mode `{}`", self.0))
+        }
+    }
+
+    impl std::error::Error for UnknownAclMode {}
+
+    impl core::str::FromStr for AclMode {
+        type Err = UnknownAclMode;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "inherit" => Ok(Self::Inherit),
+                "allow" => Ok(Self::Allow),
+                "deny" => Ok(Self::Deny),
+                "forbid" => Ok(Self::Forbid),
+                _ => Err(UnknownAclMode(s.to_string())),
+            }
+        }
+    }
+
+    /// The separator between the namespace and the name of a custom [`AclAction`], as in `com.example:Approve`.
+    pub const CUSTOM_ACTION_SEPARATOR: char = ':';
+
+    /// An action that [`AclRow`]s grant.
+    ///
+    /// Actions are serialized by the names of the variants, such as `WriteKeys`. Other names are kept as
+    ///  [`AclAction::Other`], so that rows written by newer servers can still be read. Servers only accept other
+    ///  actions that are namespaced custom actions, of the form `<namespace>:<name>`.
+    #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+    pub enum AclAction {
+        Read,
+        Write,
+        Delete,
+        WriteKeys,
+        DeleteKeys,
+        ReadAcl,
+        WriteAcl,
+        /// Implies every other action on the same object.
+        Owner,
+        /// Global permission to change the `Owner` rows of any object.
+        TakeOwnership,
+        ReadRootInfo,
+        WriteRootInfo,
+        ReadClientPersistentStorage,
+        WriteClientPersistentStorage,
+        Other(String),
+    }
+
+    impl AclAction {
+        /// The name of the action, as used by the serialized form.
+        pub fn name(&self) -> &str {
+            match self {
+                Self::Read => "Read",
+                Self::Write => "Write",
+                Self::Delete => "Delete",
+                Self::WriteKeys => "WriteKeys",
+                Self::DeleteKeys => "DeleteKeys",
+                Self::ReadAcl => "ReadAcl",
+                Self::WriteAcl => "WriteAcl",
+                Self::Owner => "Owner",
+                Self::TakeOwnership => "TakeOwnership",
+                Self::ReadRootInfo => "ReadRootInfo",
+                Self::WriteRootInfo => "WriteRootInfo",
+                Self::ReadClientPersistentStorage => "ReadClientPersistentStorage",
+                Self::WriteClientPersistentStorage => "WriteClientPersistentStorage",
+                Self::Other(name) => name,
+            }
+        }
+
+        /// Whether the action is a namespaced custom action, such as `com.example:Approve`.
+        pub fn is_custom(&self) -> bool {
+            let Self::Other(name) = self else {
+                return false;
+            };
+            let valid = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+            };
+            match name.split_once(CUSTOM_ACTION_SEPARATOR) {
+                Some((namespace, name)) => valid(namespace) && valid(name),
+                None => false,
+            }
+        }
+
+        /// Whether the action is one of the named variants, or a namespaced custom action.
+        pub fn is_valid(&self) -> bool {
+            !matches!(self, Self::Other(_)) || self.is_custom()
+        }
+    }
+
+    impl core::fmt::Display for AclAction {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str(self.name())
+        }
+    }
+
+    impl core::str::FromStr for AclAction {
+        type Err = core::convert::Infallible;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Ok(match s {
+                "Read" => Self::Read,
+                "Write" => Self::Write,
+                "Delete" => Self::Delete,
+                "WriteKeys" => Self::WriteKeys,
+                "DeleteKeys" => Self::DeleteKeys,
+                "ReadAcl" => Self::ReadAcl,
+                "WriteAcl" => Self::WriteAcl,
+                "Owner" => Self::Owner,
+                "TakeOwnership" => Self::TakeOwnership,
+                "ReadRootInfo" => Self::ReadRootInfo,
+                "WriteRootInfo" => Self::WriteRootInfo,
+                "ReadClientPersistentStorage" => Self::ReadClientPersistentStorage,
+                "WriteClientPersistentStorage" => Self::WriteClientPersistentStorage,
+                other => Self::Other(other.to_string()),
+            })
+        }
+    }
+
+    impl Serialize for AclAction {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.name())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for AclAction {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let name = String::deserialize(deserializer)?;
+            Ok(name.parse().unwrap_or_else(|e| match e {}))
+        }
+    }
+
     /// ## Get All ACL Rows for an object
     ///
     /// `GET /<object-type>/<uuid>/acl`
     ///
-    /// Where object-type is either `item` or `user`.
+    /// Where object-type is one of `item`, `user` or `group`.
     ///
     /// Requires: ACL Permission `ReadAcl` on `uuid`.
     ///
     /// ## Get All ACL Rows for an object associated with a given user
     ///
     /// `GET /<object-type>/<object-uuid>/acl?subject=<subject-uuid>`
-    /// Where object-type is either `item` or `user`.
+    /// Where object-type is one of `item`, `user` or `group`.
     ///
     /// Requires: ACL Permission `ReadAcl` on `uuid`.
     ///
     /// ## Create or modify a new ACL Row or Rows
     ///
-    /// `POST /<object-type>/<uuid>/acl`
-    /// Where object-type is either `item` or `user`.
+    /// `POST /<object-type>/<uuid>/acl` Array of [`AclRow`]
+    /// Where object-type is one of `item`, `user` or `group`.
     ///
     /// Row is checked by subject id and action. Identical rows are replaced.
     ///
@@ -67,16 +226,23 @@ pub mod acl {
     /// ## Replace Entire Object ACL
     ///
     /// `PUT /<object-type>/<uuid>/acl` Array of [`AclRow`]
-    ///  Where object-type is either `item` or `user`.
+    ///  Where object-type is one of `item`, `user` or `group`.
     ///
     /// Requires: ACL Permission `Owner` on `uuid`.
     ///
+    /// A user is implicitly the `Owner` of their own user object.
+    ///
+    /// The object must exist. The nil UUID is rejected, as global permissions are only managed through
+    ///  `/server/permissions`.
+    ///
     /// ## Get Global Permission set
     ///
-    /// `GET /server/permissions`
+    /// `GET /server/permissions`, or `GET /server/permissions?subject=<subject-uuid>`
     ///
     /// Requires: `ReadAcl` global permission
     ///
+    /// The first user to register, or the admin configured on the server, is made a global `Owner`.
+    ///
     /// ## Update Global Permission set
     ///
     /// `POST /server/permissions`
@@ -91,11 +257,13 @@ pub mod acl {
     /// `PUT /server/permissions` Array of [`AclRow`]
     ///
     /// Requires: `Owner` global permission
+    ///
+    /// Updates that would leave no global `Owner` are rejected.
     #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     #[serde(rename_all = "kebab-case")]
     pub struct AclRow {
         pub subject: Uuid,
-        pub action: String,
+        pub action: AclAction,
         pub mode: AclMode,
     }
 }
@@ -199,9 +367,10 @@ pub mod user {
 pub mod auth {
     use crate::{
         data::Bytes,
-        suite::{AsymmetricCipherAlgorithm, DigestAlgorithm},
+        suite::{AsymmetricCipherAlgorithm, DigestAlgorithm, SymmetricCipherAlgorithm},
     };
     use serde::{Deserialize, Serialize};
+    use time::PrimitiveDateTime;
     use uuid::Uuid;
 
     /// ## Retreiving auth info
@@ -216,12 +385,49 @@ pub mod auth {
     #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     pub struct UserAuth {
         pub kdf_base_digest_alg: DigestAlgorithm,
+        /// The parameters of the KDF that derives the key sealing `secured_private_key` from the master password.
+        ///
+        /// When absent, PBKDF2 with `kdf_base_digest_alg`, 600000 rounds and the user address as the salt is used.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub kdf_params: Option<KdfParams>,
+        /// The cipher that seals `secured_private_key` with the derived key. The authentication tag is appended to
+        ///  the ciphertext.
+        ///
+        /// When absent, `aes256-gcm` is used.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub priv_key_cipher: Option<SymmetricCipherAlgorithm>,
         pub auth_key_alg: AsymmetricCipherAlgorithm,
         pub pub_key: Bytes,
         pub priv_key_iv: Bytes,
         pub secured_private_key: Bytes,
     }
 
+    /// The current version of [`KdfParams`].
+    pub const KDF_PARAMS_VERSION: u32 = 1;
+
+    /// A versioned set of parameters for a password based key derivation function.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct KdfParams {
+        pub version: u32,
+        #[serde(flatten)]
+        pub function: KdfFunction,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    #[non_exhaustive]
+    #[serde(tag = "kdf", rename_all = "kebab-case")]
+    pub enum KdfFunction {
+        /// PBKDF2, with HMAC using the `kdf_base_digest_alg` of the [`UserAuth`] as the PRF.
+        Pbkdf2 { iterations: u32, salt: Bytes },
+        /// Argon2id, version 0x13. `memory` is in KiB.
+        Argon2id {
+            memory: u32,
+            iterations: u32,
+            parallelism: u32,
+            salt: Bytes,
+        },
+    }
+
     /// # Starting authentication
     ///
     /// `POST /auth/challenge`
@@ -244,33 +450,96 @@ pub mod auth {
     ///
     /// `Authorization: Bearer <challenge_session_id>`
     ///
+    /// `challenge_signature` is the signature of `challenge_bytes` with the user's private key,
+    ///  using `challenge_digest` as the hash for algorithms that require one.
+    ///
+    /// Returns an [`AuthSession`] on success.
     #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     pub struct AuthResponse {
         pub challenge_signature: Bytes,
     }
+
+    /// A session obtained by authenticating.
+    ///
+    /// `session_token` is sent base64 encoded as `Authorization: Bearer <session_token>` on authenticated requests.
+    ///
+    /// ## Refreshing a Session
+    ///
+    /// `POST /auth/session/refresh`
+    ///
+    /// `Authorization: Bearer <session_token>`
+    ///
+    /// Returns a new [`AuthSession`] with the same `session_id`, a new `session_token` and a new expiry.
+    /// The previous token stops being valid.
+    ///
+    /// ## Ending a Session
+    ///
+    /// `DELETE /auth/session`
+    ///
+    /// `Authorization: Bearer <session_token>`
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct AuthSession {
+        pub session_id: Uuid,
+        pub session_token: Bytes,
+        pub expires: PrimitiveDateTime,
+    }
+
+    /// ## Listing Active Sessions
+    ///
+    /// `GET /users/<uuid>/sessions`
+    ///
+    /// Returns an Array of [`SessionInfo`].
+    ///
+    /// Requires: Authenticated as `<uuid>`.
+    ///
+    /// ## Revoking a Session
+    ///
+    /// `DELETE /users/<uuid>/sessions/<session-uuid>`
+    ///
+    /// Requires: Authenticated as `<uuid>`.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct SessionInfo {
+        pub session_id: Uuid,
+        pub created: PrimitiveDateTime,
+        pub last_used: Option<PrimitiveDateTime>,
+        pub expires: PrimitiveDateTime,
+        /// Whether this is the session used to make the request.
+        pub current: bool,
+    }
 }
 
+/// Items, their keys and their metadata.
 ///
-/// ## Retrieve an Item
+/// Item contents are encrypted with the item key as described by [`ItemKeys`][item::ItemKeys], and are always
+///  exchanged together with their key list as [`ItemContents`][item::ItemContents].
 ///
-/// `GET /item/<uuid>`
+/// ## Delete an Item
 ///
-/// Accept: application/octet-stream
+/// `DELETE /items/<uuid>`
 ///
-/// Requires: ACL Permission `Read`
+/// Requires: ACL Permission `Delete`.
 ///
-/// ## Update an Item's Contents
+/// ## Place an Item in a Vault
 ///
-/// `PUT /items/<uuid>`
+/// `PUT /items/<item-uuid>/parents/<vault-uuid>`
 ///
-/// Content-Type: application/octet-stream
+/// Requires: ACL Permission `Write` for the vault, and `WriteAcl` for the item.
 ///
-/// Requires: ACL Permission `Write`
-/// ## Delete an Item
+/// Records that the vault contains the item, so that the item inherits the ACL of the vault (see
+///  [`AclMode`][acl::AclMode]). The server cannot read the `content` of the vault, so the client adds the item to it
+///  separately.
 ///
-/// `DELETE /items/<uuid>`
+/// ## Remove an Item from a Vault
 ///
-/// Requires: ACL Permission `Delete`.
+/// `DELETE /items/<item-uuid>/parents/<vault-uuid>`
+///
+/// Requires: ACL Permission `Write` for the vault, or `WriteAcl` for the item.
+///
+/// ## List the Vaults containing an Item
+///
+/// `GET /items/<item-uuid>/parents` responds with the UUIDs of the vaults.
+///
+/// Requires: ACL Permission `Read` for the item.
 pub mod item {
     use serde::{Deserialize, Serialize};
     use time::PrimitiveDateTime;
@@ -282,6 +551,9 @@ pub mod item {
 
     use super::acl::AclRow;
 
+    /// The cipher used to wrap item keys with symmetric keys.
+    pub const KEY_WRAP_CIPHER: SymmetricCipherAlgorithm = SymmetricCipherAlgorithm::Aes256Gcm;
+
     /// ## Retrieve Item Key Information for a given key
     ///
     /// `GET /items/<item-uuid>/keys/<key-uuid>`
@@ -299,6 +571,15 @@ pub mod item {
     /// `DELETE /items/<item-uuid>/keys/<key-uuid>`
     ///
     /// Requires: ACL Permission `DeleteKeys` for `item`.
+    ///
+    /// ## Key Wrapping
+    ///
+    /// If `<key-uuid>` is the `root_key` of a user (see [`UserRootInfo`][super::user::UserRootInfo]),
+    ///  `secured_item_key` is the item key sealed to that user's public key, `item_key_iv` is empty
+    ///  and `item_auth_tag` is absent.
+    ///
+    /// Otherwise `<key-uuid>` names a 256-bit symmetric key held by the client (such as a shared group key),
+    ///  and `secured_item_key` is the item key encrypted with that key using [`KEY_WRAP_CIPHER`].
     #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     pub struct ItemKeyInfo {
         pub secured_item_key: Bytes,
@@ -318,6 +599,9 @@ pub mod item {
     ///
     /// Requires: ACL Permission `WriteKeys` for `item`.
     ///
+    /// Only `key_refs` can be changed this way; the other fields describe the contents, and are replaced together
+    ///  with them by `PUT /items/<item-uuid>`.
+    ///
     /// ## Remove all keys from an item
     ///
     /// `DELETE /items/<item-uuid>/keys`
@@ -331,11 +615,35 @@ pub mod item {
         pub item_auth_tag: Option<Bytes>,
     }
 
+    /// ## Retrieve an Item
+    ///
+    /// `GET /items/<uuid>`
+    ///
+    /// Requires: ACL Permission `Read`.
+    ///
+    /// The response carries an `ETag` that changes whenever the contents or the key list are replaced. Fails with `not-found` if
+    ///  no contents were written yet.
+    ///
+    /// ## Replace an Item's Contents
+    ///
+    /// `PUT /items/<uuid>`
+    ///
+    /// Requires: ACL Permission `Write`, and `WriteKeys` if `keys.key_refs` changes.
+    ///
+    /// With `If-Match: <etag>`, the contents are only replaced if the `ETag` still matches, and the request
+    ///  otherwise fails with `conflict`. The response carries the new `ETag`.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct ItemContents {
+        pub keys: ItemKeys,
+        pub contents: Bytes,
+    }
+
     /// ## Retrieve Item Metadata
     ///
     /// `GET /items/<item-uuid>/metadata`
     ///
-    /// Requires: ACL Permission
+    /// Requires: ACL Permission `Read` for `item`.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     pub struct ItemMetadata {
         pub content_type: String,
         pub mtime: PrimitiveDateTime,
@@ -346,8 +654,158 @@ pub mod item {
     /// ## Create new Item
     ///
     /// `POST /items/new`
+    ///
+    /// Requires: Authenticated.
+    ///
+    /// The creator is made the `Owner` of the new item, and `base_acl` is added to its ACL. The new item has no
+    ///  contents and no keys. The client uploads the wrapped item key for each key it will use, then the encrypted
+    ///  contents and key list with `PUT /items/<uuid>`.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
     pub struct NewItemRequest {
         pub content_type: String,
         pub base_acl: Vec<AclRow>,
     }
+
+    /// The response to `POST /items/new`.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct NewItemResponse {
+        pub item_id: Uuid,
+    }
+}
+
+/// Groups of users, and of other groups, that can be the subject of [`AclRow`][acl::AclRow]s.
+///
+/// A user is a member of every group it is added to, and transitively of every group those groups are added to.
+///
+/// Each group has a 256-bit symmetric group key, which items are shared with by wrapping their item key with it
+///  (see [`ItemKeyInfo`][item::ItemKeyInfo]). The server only stores the group key wrapped for each member: sealed
+///  to the public key of a user, or encrypted with the group key of a group using
+///  [`KEY_WRAP_CIPHER`][item::KEY_WRAP_CIPHER]. Removing a member does not make it forget the key, so the group key
+///  should be replaced after members are removed.
+///
+/// ## Delete a Group
+///
+/// `DELETE /groups/<uuid>`
+///
+/// Requires: ACL Permission `Delete` for the group.
+///
+/// The group is removed from every group it is a member of, and every ACL row granted to it is removed. Deleting a
+///  group that is the last global `Owner` is rejected.
+pub mod group {
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use super::item::ItemKeyInfo;
+
+    /// ## Create a Group
+    ///
+    /// `POST /groups/new`
+    ///
+    /// Requires: Authenticated.
+    ///
+    /// The creator is made the `Owner` of the group and its first member, with the group key sealed to it as
+    ///  `creator_key`. `display_name` is stored in the clear.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct NewGroupRequest {
+        pub display_name: Option<String>,
+        pub key_id: Uuid,
+        pub creator_key: ItemKeyInfo,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct NewGroupResponse {
+        pub group_id: Uuid,
+    }
+
+    /// The kind of a [`GroupMember`].
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum PrincipalKind {
+        User,
+        Group,
+    }
+
+    impl PrincipalKind {
+        /// The name of the kind, as used by the serialized form.
+        pub const fn name(self) -> &'static str {
+            match self {
+                Self::User => "user",
+                Self::Group => "group",
+            }
+        }
+    }
+
+    impl core::fmt::Display for PrincipalKind {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_str(self.name())
+        }
+    }
+
+    /// Error returned when parsing an unknown [`PrincipalKind`].
+    #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+    pub struct UnknownPrincipalKind(pub String);
+
+    impl core::fmt::Display for UnknownPrincipalKind {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            f.write_fmt(format_args!("unknown member kind `{}`", self.0))
+        }
+    }
+
+    impl std::error::Error for UnknownPrincipalKind {}
+
+    impl core::str::FromStr for PrincipalKind {
+        type Err = UnknownPrincipalKind;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "user" => Ok(Self::User),
+                "group" => Ok(Self::Group),
+                _ => Err(UnknownPrincipalKind(s.to_string())),
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct GroupMember {
+        pub member_id: Uuid,
+        pub kind: PrincipalKind,
+    }
+
+    /// ## Retrieve a Group
+    ///
+    /// `GET /groups/<uuid>`
+    ///
+    /// Requires: Being a member of the group, or ACL Permission `Read` for the group.
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct GroupInfo {
+        pub display_name: Option<String>,
+        /// The ID of the group key, as used in item key lists.
+        pub key_id: Uuid,
+        pub members: Vec<GroupMember>,
+    }
+
+    /// ## Add a Member to a Group, or replace its Key
+    ///
+    /// `PUT /groups/<group-uuid>/members/<member-uuid>` [`ItemKeyInfo`]
+    ///
+    /// Requires: ACL Permission `Write` for the group.
+    ///
+    /// The body is the group key wrapped for the member, which is either a user or another group. Adding a group
+    ///  that contains this group fails with `invalid-request`.
+    ///
+    /// ## Remove a Member from a Group
+    ///
+    /// `DELETE /groups/<group-uuid>/members/<member-uuid>`
+    ///
+    /// Requires: ACL Permission `Write` for the group, or being the member.
+    ///
+    /// ## Retrieve the Group Key of a Member
+    ///
+    /// `GET /groups/<group-uuid>/members/<member-uuid>/key` responds with an [`ItemKeyInfo`]
+    ///
+    /// Requires: Being the member, or a member of the member group, or ACL Permission `Read` for the group.
+    pub type GroupKeyInfo = ItemKeyInfo;
 }
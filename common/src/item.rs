@@ -0,0 +1,434 @@
+//! Typed forms of the item interfaces in `protocol-spec/src/client/items.md`.
+//!
+//! Items are JSON objects whose `type` field names the item type. [`Item`] dispatches on that field, and keeps
+//!  the raw JSON of any type it does not know, so that items created by newer clients survive being read and
+//!  written back by older ones.
+
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::data::{Bytes, IsoDuration};
+
+/// The `type` of a [`Vault`].
+pub const VAULT_TYPE: &str = "application/x-passman-vault";
+
+/// The `type` of a [`PasswordItem`].
+pub const PASSWORD_ITEM_TYPE: &str = "application/x-passman-login-password";
+
+/// The `type` of a [`TotpGeneratorItem`].
+pub const TOTP_GENERATOR_ITEM_TYPE: &str = "application/x-passman-totp-generator";
+
+/// The `type` of a [`HotpGeneratorItem`].
+pub const HOTP_GENERATOR_ITEM_TYPE: &str = "application/x-passman-hotp-generator";
+
+/// The format suffix of the `Content-Type` of items stored as JSON.
+pub const JSON_FORMAT_SUFFIX: &str = "+json";
+
+/// Whether an item stored with `content_type` is JSON, and so can be read as an [`Item`].
+pub fn is_json_content_type(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .next()
+        .is_some_and(|media_type| media_type.trim_end().ends_with(JSON_FORMAT_SUFFIX))
+}
+
+pub use crate::matcher::UrlMatcher;
+
+/// A vault, which stores a collection of items.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Vault {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub content: Vec<Uuid>,
+}
+
+/// A password for a website, and typically the User ID used with it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PasswordItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub url: UrlMatcher,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login_id: Option<String>,
+    pub login_password: String,
+}
+
+/// The hash algorithm of a one-time password generator.
+///
+/// `sha512` is an extension to the algorithms in the protocol spec, used by some `otpauth://` URIs.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// The information needed to generate Time-based One Time Passwords, according to RFC 6238.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TotpGeneratorItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub url: UrlMatcher,
+    pub alg: TotpAlgorithm,
+    /// Defaults to the Unix Epoch.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "time::serde::rfc3339::option"
+    )]
+    pub totp_epoch: Option<OffsetDateTime>,
+    pub totp_step: IsoDuration,
+    pub totp_digits: u32,
+    pub totp_key: Bytes,
+}
+
+/// The information needed to generate HMAC-based One Time Passwords, according to RFC 4226.
+///
+/// `hotp_counter` is the counter of the next code. It must be incremented and written back each time a code is
+///  generated, without overwriting a concurrent increment.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HotpGeneratorItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub url: UrlMatcher,
+    pub alg: TotpAlgorithm,
+    pub hotp_counter: u64,
+    pub hotp_digits: u32,
+    pub hotp_key: Bytes,
+}
+
+/// An item of a type this version does not know.
+///
+/// The whole JSON object, including `type`, is kept as-is, in the order it was read, so that writing the item back
+///  produces the same document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownItem {
+    content_type: String,
+    raw: Map<String, Value>,
+}
+
+impl UnknownItem {
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        self.raw.get("display_name").and_then(Value::as_str)
+    }
+
+    /// The JSON object the item was read from.
+    pub fn raw(&self) -> &Map<String, Value> {
+        &self.raw
+    }
+
+    pub fn into_raw(self) -> Map<String, Value> {
+        self.raw
+    }
+}
+
+/// Any item, dispatched by its `type`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Vault(Vault),
+    Password(PasswordItem),
+    TotpGenerator(TotpGeneratorItem),
+    HotpGenerator(HotpGeneratorItem),
+    Unknown(UnknownItem),
+}
+
+impl Item {
+    /// The `type` of the item, which is the `Content-Type` it is stored with, without the format suffix.
+    pub fn content_type(&self) -> &str {
+        match self {
+            Item::Vault(_) => VAULT_TYPE,
+            Item::Password(_) => PASSWORD_ITEM_TYPE,
+            Item::TotpGenerator(_) => TOTP_GENERATOR_ITEM_TYPE,
+            Item::HotpGenerator(_) => HOTP_GENERATOR_ITEM_TYPE,
+            Item::Unknown(item) => item.content_type(),
+        }
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        match self {
+            Item::Vault(item) => item.display_name.as_deref(),
+            Item::Password(item) => item.display_name.as_deref(),
+            Item::TotpGenerator(item) => item.display_name.as_deref(),
+            Item::HotpGenerator(item) => item.display_name.as_deref(),
+            Item::Unknown(item) => item.display_name(),
+        }
+    }
+
+    /// The URL matcher of items that apply to a website.
+    pub fn url(&self) -> Option<&UrlMatcher> {
+        match self {
+            Item::Password(item) => Some(&item.url),
+            Item::TotpGenerator(item) => Some(&item.url),
+            Item::HotpGenerator(item) => Some(&item.url),
+            _ => None,
+        }
+    }
+
+    /// Parses an item from a JSON document.
+    pub fn from_json(json: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(json)
+    }
+
+    /// Writes the item as a JSON document, to be stored with the `Content-Type` `<type>` followed by
+    ///  [`JSON_FORMAT_SUFFIX`].
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+}
+
+impl From<Vault> for Item {
+    fn from(value: Vault) -> Self {
+        Item::Vault(value)
+    }
+}
+
+impl From<PasswordItem> for Item {
+    fn from(value: PasswordItem) -> Self {
+        Item::Password(value)
+    }
+}
+
+impl From<TotpGeneratorItem> for Item {
+    fn from(value: TotpGeneratorItem) -> Self {
+        Item::TotpGenerator(value)
+    }
+}
+
+impl From<HotpGeneratorItem> for Item {
+    fn from(value: HotpGeneratorItem) -> Self {
+        Item::HotpGenerator(value)
+    }
+}
+
+fn with_type<T: Serialize>(item: &T, content_type: &str) -> serde_json::Result<Map<String, Value>> {
+    let Value::Object(fields) = serde_json::to_value(item)? else {
+        return Err(<serde_json::Error as serde::ser::Error>::custom(
+            "item is not an object",
+        ));
+    };
+    let mut map = Map::new();
+    map.insert("type".to_string(), Value::String(content_type.to_string()));
+    map.extend(fields);
+    Ok(map)
+}
+
+impl Serialize for Item {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let map = match self {
+            Item::Vault(item) => with_type(item, VAULT_TYPE),
+            Item::Password(item) => with_type(item, PASSWORD_ITEM_TYPE),
+            Item::TotpGenerator(item) => with_type(item, TOTP_GENERATOR_ITEM_TYPE),
+            Item::HotpGenerator(item) => with_type(item, HOTP_GENERATOR_ITEM_TYPE),
+            Item::Unknown(item) => return item.raw.serialize(serializer),
+        }
+        .map_err(S::Error::custom)?;
+        map.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Item {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = Map::deserialize(deserializer)?;
+        let content_type = match raw.get("type") {
+            Some(Value::String(content_type)) => content_type.clone(),
+            Some(_) => return Err(D::Error::custom("item `type` is not a string")),
+            None => return Err(D::Error::missing_field("type")),
+        };
+
+        fn parse<T: for<'a> Deserialize<'a>, E: serde::de::Error>(
+            mut raw: Map<String, Value>,
+        ) -> Result<T, E> {
+            raw.remove("type");
+            serde_json::from_value(Value::Object(raw)).map_err(E::custom)
+        }
+
+        match content_type.as_str() {
+            VAULT_TYPE => parse(raw).map(Item::Vault),
+            PASSWORD_ITEM_TYPE => parse(raw).map(Item::Password),
+            TOTP_GENERATOR_ITEM_TYPE => parse(raw).map(Item::TotpGenerator),
+            HOTP_GENERATOR_ITEM_TYPE => parse(raw).map(Item::HotpGenerator),
+            _ => Ok(Item::Unknown(UnknownItem { content_type, raw })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn url(matcher: &str) -> UrlMatcher {
+        UrlMatcher::parse(matcher).unwrap()
+    }
+
+    fn round_trip(item: Item, json: &str) {
+        assert_eq!(Item::from_json(json.as_bytes()).unwrap(), item);
+        assert_eq!(String::from_utf8(item.to_json().unwrap()).unwrap(), json);
+    }
+
+    #[test]
+    fn vault() {
+        let content = vec![Uuid::from_u128(1), Uuid::from_u128(2)];
+        round_trip(
+            Vault {
+                display_name: Some("Work".into()),
+                content: content.clone(),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-vault","display_name":"Work","#,
+                r#""content":["00000000-0000-0000-0000-000000000001","00000000-0000-0000-0000-000000000002"]}"#
+            ),
+        );
+        round_trip(
+            Vault {
+                display_name: None,
+                content: Vec::new(),
+            }
+            .into(),
+            r#"{"type":"application/x-passman-vault","content":[]}"#,
+        );
+    }
+
+    #[test]
+    fn password() {
+        round_trip(
+            PasswordItem {
+                display_name: Some("Example".into()),
+                url: url("example.com"),
+                login_id: Some("alice".into()),
+                login_password: "hunter2".into(),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-login-password","display_name":"Example","#,
+                r#""url":"example.com","login_id":"alice","login_password":"hunter2"}"#
+            ),
+        );
+        round_trip(
+            PasswordItem {
+                display_name: None,
+                url: url("localhost:8080"),
+                login_id: None,
+                login_password: "admin".into(),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-login-password","url":"localhost:8080","#,
+                r#""login_password":"admin"}"#
+            ),
+        );
+    }
+
+    #[test]
+    fn totp_generator() {
+        round_trip(
+            TotpGeneratorItem {
+                display_name: Some("Example".into()),
+                url: url("example.com"),
+                alg: TotpAlgorithm::Sha1,
+                totp_epoch: None,
+                totp_step: IsoDuration::seconds(30),
+                totp_digits: 6,
+                totp_key: Bytes::new(b"12345678901234567890".to_vec()),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-totp-generator","display_name":"Example","url":"example.com","#,
+                r#""alg":"sha1","totp_step":"PT30S","totp_digits":6,"totp_key":"MTIzNDU2Nzg5MDEyMzQ1Njc4OTA"}"#
+            ),
+        );
+        round_trip(
+            TotpGeneratorItem {
+                display_name: None,
+                url: url("example.com"),
+                alg: TotpAlgorithm::Sha512,
+                totp_epoch: Some(OffsetDateTime::from_unix_timestamp(1_000_000_000).unwrap()),
+                totp_step: IsoDuration::seconds(60),
+                totp_digits: 8,
+                totp_key: Bytes::new(vec![0xff]),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-totp-generator","url":"example.com","alg":"sha512","#,
+                r#""totp_epoch":"2001-09-09T01:46:40Z","totp_step":"PT1M","totp_digits":8,"totp_key":"/w"}"#
+            ),
+        );
+    }
+
+    #[test]
+    fn hotp_generator() {
+        round_trip(
+            HotpGeneratorItem {
+                display_name: None,
+                url: url("example.com"),
+                alg: TotpAlgorithm::Sha256,
+                hotp_counter: 42,
+                hotp_digits: 6,
+                hotp_key: Bytes::new(vec![1, 2, 3]),
+            }
+            .into(),
+            concat!(
+                r#"{"type":"application/x-passman-hotp-generator","url":"example.com","alg":"sha256","#,
+                r#""hotp_counter":42,"hotp_digits":6,"hotp_key":"AQID"}"#
+            ),
+        );
+    }
+
+    #[test]
+    fn unknown_items_are_kept() {
+        let json = concat!(
+            r#"{"z":1,"display_name":"Note","type":"application/x-example-note","#,
+            r#""nested":{"b":[true,null,"é"],"a":-2.5},"a":"last"}"#
+        );
+        let item = Item::from_json(json.as_bytes()).unwrap();
+        let Item::Unknown(unknown) = &item else {
+            panic!("{item:?} is not unknown");
+        };
+        assert_eq!(item.content_type(), "application/x-example-note");
+        assert_eq!(item.display_name(), Some("Note"));
+        assert_eq!(item.url(), None);
+        assert_eq!(unknown.raw()["type"], "application/x-example-note");
+        assert_eq!(String::from_utf8(item.to_json().unwrap()).unwrap(), json);
+    }
+
+    #[test]
+    fn invalid_items() {
+        for json in [
+            r#"[]"#,
+            r#"{"content":[]}"#,
+            r#"{"type":1,"content":[]}"#,
+            r#"{"type":"application/x-passman-vault","content":["not a uuid"]}"#,
+            r#"{"type":"application/x-passman-vault","display_name":"Missing content"}"#,
+            r#"{"type":"application/x-passman-login-password","url":"example.com","login_password":1}"#,
+            r#"{"type":"application/x-passman-totp-generator","url":"example.com","alg":"md5","totp_step":"PT30S","totp_digits":6,"totp_key":""}"#,
+            r#"{"type":"application/x-passman-totp-generator","url":"example.com","alg":"sha1","totp_step":"-PT30S","totp_digits":6,"totp_key":""}"#,
+            r#"{"type":"application/x-passman-hotp-generator","url":"example.com","alg":"sha1","hotp_counter":-1,"hotp_digits":6,"hotp_key":""}"#,
+        ] {
+            assert!(Item::from_json(json.as_bytes()).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn json_content_types() {
+        assert!(is_json_content_type("application/x-passman-vault+json"));
+        assert!(is_json_content_type(
+            "application/x-passman-vault+json; charset=utf-8"
+        ));
+        assert!(!is_json_content_type("application/x-passman-vault"));
+        assert!(!is_json_content_type("text/plain"));
+        assert!(!is_json_content_type("text/plain; format=+json"));
+    }
+}
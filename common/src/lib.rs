@@ -1,4 +1,6 @@
 pub mod data;
 pub mod error;
 pub mod http;
+pub mod item;
+pub mod matcher;
 pub mod suite;
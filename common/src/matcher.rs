@@ -0,0 +1,437 @@
+//! The `UrlMatcher` syntax of `protocol-spec/src/client/items.md`.
+//!
+//! A matcher is either `re:<regex>`, matched against the whole URL, or a URL pattern of the form
+//!  `[scheme://]host-pattern[:port][/path-prefix]`, where `host-pattern` is one of
+//!
+//! * `=host`, matching exactly that host,
+//! * `*.domain`, matching any subdomain of `domain`,
+//! * `host`, matching any host with the same registrable domain, or only that host if it is a single label that is not
+//!   a public suffix, such as `localhost`.
+
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::{
+    de::{Deserialize, Deserializer},
+    ser::{Serialize, Serializer},
+};
+use url::{Host, Url};
+
+/// The prefix of a regular expression matcher.
+pub const REGEX_PREFIX: &str = "re:";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UrlMatcherError {
+    Empty,
+    InvalidScheme(String),
+    InvalidHost(String),
+    /// The host pattern is a public suffix, which would match unrelated sites.
+    PublicSuffix(String),
+    InvalidPort(String),
+    InvalidRegex(String),
+}
+
+impl core::fmt::Display for UrlMatcherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("empty URL matcher"),
+            Self::InvalidScheme(scheme) => write!(f, "invalid URL scheme {scheme}"),
+            Self::InvalidHost(host) => write!(f, "invalid host {host}"),
+            Self::PublicSuffix(host) => write!(f, "{host} is a public suffix"),
+            Self::InvalidPort(port) => write!(f, "invalid port {port}"),
+            Self::InvalidRegex(e) => write!(f, "invalid regular expression: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for UrlMatcherError {}
+
+#[derive(Clone, Debug)]
+enum HostPattern {
+    Exact(String),
+    Subdomains(String),
+    RegistrableDomain(String),
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    /// A source that failed to parse when it was deserialized, which matches nothing.
+    Invalid(UrlMatcherError),
+    Regex(Regex),
+    Pattern {
+        scheme: Option<String>,
+        host: HostPattern,
+        port: Option<u16>,
+        path_prefix: Option<String>,
+    },
+}
+
+/// A parsed `UrlMatcher`, which describes the URLs an item applies to.
+///
+/// Matchers compare equal, and serialize, as the string they were parsed from.
+///
+/// A matcher that fails to parse when it is deserialized, such as one written by a newer client, is kept as it is and
+///  matches nothing, so that the item it belongs to can still be read and written back.
+#[derive(Clone, Debug)]
+pub struct UrlMatcher {
+    source: String,
+    matcher: Matcher,
+}
+
+/// Normalizes a host to its ASCII (Punycode) form, lowercased and without a trailing `.`.
+fn normalize_host(host: &str) -> Result<String, UrlMatcherError> {
+    let trimmed = host.strip_suffix('.').unwrap_or(host);
+    match Host::parse(trimmed) {
+        Ok(host) => Ok(host.to_string()),
+        Err(_) => Err(UrlMatcherError::InvalidHost(host.to_string())),
+    }
+}
+
+fn registrable_domain(host: &str) -> Option<&str> {
+    psl::domain_str(host)
+}
+
+/// Whether `host` is a suffix of the Public Suffix List, rather than one that is only assumed by its wildcard rule.
+fn is_known_suffix(host: &str) -> bool {
+    psl::suffix(host.as_bytes()).is_some_and(|suffix| suffix.is_known())
+}
+
+fn is_ip(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok()
+}
+
+fn parse_host_pattern(pattern: &str) -> Result<HostPattern, UrlMatcherError> {
+    if let Some(host) = pattern.strip_prefix('=') {
+        return Ok(HostPattern::Exact(normalize_host(host)?));
+    }
+
+    if let Some(domain) = pattern.strip_prefix("*.") {
+        let domain = normalize_host(domain)?;
+        if is_ip(&domain) {
+            return Err(UrlMatcherError::InvalidHost(pattern.to_string()));
+        }
+        // A wildcard is allowed on a registrable domain or any of its subdomains, but not on a public suffix.
+        if registrable_domain(&domain).is_none() {
+            return Err(UrlMatcherError::PublicSuffix(domain));
+        }
+        return Ok(HostPattern::Subdomains(domain));
+    }
+
+    let host = normalize_host(pattern)?;
+    if is_ip(&host) {
+        return Ok(HostPattern::Exact(host));
+    }
+    match registrable_domain(&host) {
+        Some(domain) => Ok(HostPattern::RegistrableDomain(domain.to_string())),
+        // A single label such as `localhost` or `nas` names a host on the local network, not a public suffix
+        None if !host.contains('.') && !is_known_suffix(&host) => Ok(HostPattern::Exact(host)),
+        None => Err(UrlMatcherError::PublicSuffix(host)),
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<Matcher, UrlMatcherError> {
+    let (scheme, rest) = match pattern.split_once("://") {
+        Some((scheme, rest)) => {
+            let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+            if !valid {
+                return Err(UrlMatcherError::InvalidScheme(scheme.to_string()));
+            }
+            (Some(scheme.to_ascii_lowercase()), rest)
+        }
+        None => (None, pattern),
+    };
+
+    let (authority, path_prefix) = match rest.find('/') {
+        Some(idx) => (&rest[..idx], Some(rest[idx..].to_string())),
+        None => (rest, None),
+    };
+
+    // The port follows the last `:`, unless that is inside an IPv6 address.
+    let (host, port) = match authority.rfind(':') {
+        Some(idx) if !authority[idx..].contains(']') => {
+            let port = &authority[idx + 1..];
+            let port = port
+                .parse()
+                .map_err(|_| UrlMatcherError::InvalidPort(port.to_string()))?;
+            (&authority[..idx], Some(port))
+        }
+        _ => (authority, None),
+    };
+    if host.is_empty() {
+        return Err(UrlMatcherError::InvalidHost(host.to_string()));
+    }
+
+    Ok(Matcher::Pattern {
+        scheme,
+        host: parse_host_pattern(host)?,
+        port,
+        path_prefix,
+    })
+}
+
+fn path_matches(prefix: &str, path: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+impl UrlMatcher {
+    pub fn parse(s: &str) -> Result<Self, UrlMatcherError> {
+        let source = s.trim();
+        if source.is_empty() {
+            return Err(UrlMatcherError::Empty);
+        }
+
+        let matcher = match source.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => Matcher::Regex(
+                Regex::new(regex).map_err(|e| UrlMatcherError::InvalidRegex(e.to_string()))?,
+            ),
+            None => parse_pattern(source)?,
+        };
+
+        Ok(Self {
+            source: source.to_string(),
+            matcher,
+        })
+    }
+
+    /// The string the matcher was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Why the matcher failed to parse, if it was deserialized from an invalid source.
+    pub fn error(&self) -> Option<&UrlMatcherError> {
+        match &self.matcher {
+            Matcher::Invalid(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        match &self.matcher {
+            Matcher::Invalid(_) => false,
+            Matcher::Regex(regex) => regex.is_match(url.as_str()),
+            Matcher::Pattern {
+                scheme,
+                host,
+                port,
+                path_prefix,
+            } => {
+                let scheme_matches = match scheme {
+                    Some(scheme) => url.scheme() == scheme,
+                    None => matches!(url.scheme(), "http" | "https"),
+                };
+                if !scheme_matches {
+                    return false;
+                }
+
+                let Some(url_host) = url.host_str() else {
+                    return false;
+                };
+                let url_host = url_host.strip_suffix('.').unwrap_or(url_host);
+                let host_matches = match host {
+                    HostPattern::Exact(host) => url_host == host,
+                    HostPattern::Subdomains(domain) => url_host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                    HostPattern::RegistrableDomain(domain) => {
+                        !is_ip(url_host) && registrable_domain(url_host) == Some(domain.as_str())
+                    }
+                };
+
+                host_matches
+                    && port.is_none_or(|port| url.port_or_known_default() == Some(port))
+                    && path_prefix
+                        .as_deref()
+                        .is_none_or(|prefix| path_matches(prefix, url.path()))
+            }
+        }
+    }
+}
+
+impl PartialEq for UrlMatcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for UrlMatcher {}
+
+impl core::hash::Hash for UrlMatcher {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.source.hash(state)
+    }
+}
+
+impl core::fmt::Display for UrlMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for UrlMatcher {
+    type Err = UrlMatcherError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for UrlMatcher {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for UrlMatcher {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let source = String::deserialize(deserializer)?;
+        Ok(Self::parse(&source).unwrap_or_else(|e| Self {
+            source,
+            matcher: Matcher::Invalid(e),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn matches(matcher: &str, url: &str) -> bool {
+        UrlMatcher::parse(matcher)
+            .unwrap()
+            .matches(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn registrable_domain() {
+        assert!(matches("example.com", "https://example.com/"));
+        assert!(matches("example.com", "https://login.example.com/"));
+        assert!(matches("www.example.com", "http://login.example.com/x"));
+        assert!(!matches("example.com", "https://example.org/"));
+        assert!(!matches("example.com", "https://notexample.com/"));
+        assert!(!matches("example.com", "ftp://example.com/"));
+
+        assert!(matches("shop.example.co.uk", "https://www.example.co.uk/"));
+        assert!(!matches("example.co.uk", "https://other.co.uk/"));
+
+        // github.io is in the private section of the Public Suffix List.
+        assert!(matches("alice.github.io", "https://alice.github.io/"));
+        assert!(!matches("alice.github.io", "https://bob.github.io/"));
+    }
+
+    #[test]
+    fn exact_and_wildcard_hosts() {
+        assert!(matches("=login.example.com", "https://login.example.com/"));
+        assert!(!matches("=login.example.com", "https://example.com/"));
+        assert!(!matches(
+            "=login.example.com",
+            "https://a.login.example.com/"
+        ));
+
+        assert!(matches("*.example.com", "https://a.example.com/"));
+        assert!(matches("*.example.com", "https://a.b.example.com/"));
+        assert!(!matches("*.example.com", "https://example.com/"));
+        assert!(!matches("*.example.com", "https://badexample.com/"));
+
+        assert!(matches("=Example.COM.", "https://example.com./"));
+        assert!(matches("192.168.1.1", "http://192.168.1.1:8080/"));
+        assert!(matches("[::1]:8080", "http://[::1]:8080/"));
+        assert!(!matches("[::1]:8080", "http://[::1]/"));
+    }
+
+    #[test]
+    fn single_label_hosts() {
+        assert!(matches("localhost", "http://localhost/"));
+        assert!(matches("localhost:8080", "http://localhost:8080/"));
+        assert!(!matches("localhost:8080", "http://localhost/"));
+        assert!(!matches("localhost", "http://a.localhost/"));
+        assert!(matches("router", "https://router/admin"));
+        assert!(matches("nas:5000", "http://nas:5000/"));
+        assert!(matches("=nas", "http://NAS/"));
+        assert!(!matches("nas", "http://nas.example.com/"));
+    }
+
+    #[test]
+    fn public_suffixes_are_rejected() {
+        for matcher in ["com", "co.uk", "*.com", "*.co.uk", "github.io", "=", ""] {
+            assert!(UrlMatcher::parse(matcher).is_err(), "{matcher}");
+        }
+    }
+
+    #[test]
+    fn idn() {
+        assert!(matches("münchen.de", "https://www.xn--mnchen-3ya.de/"));
+        assert!(matches("xn--mnchen-3ya.de", "https://www.münchen.de/"));
+        assert!(matches("=BÜCHER.example", "https://bücher.example/"));
+        assert!(!matches("münchen.de", "https://www.munchen.de/"));
+    }
+
+    #[test]
+    fn scheme_port_and_path() {
+        assert!(matches("https://example.com", "https://example.com/"));
+        assert!(!matches("https://example.com", "http://example.com/"));
+        assert!(matches("ssh://example.com", "ssh://git.example.com/"));
+
+        assert!(matches("example.com:443", "https://example.com/"));
+        assert!(!matches("example.com:443", "http://example.com/"));
+        assert!(matches("example.com:8443", "https://example.com:8443/"));
+        assert!(!matches("example.com:8443", "https://example.com/"));
+
+        assert!(matches("example.com/login", "https://example.com/login"));
+        assert!(matches(
+            "example.com/login",
+            "https://example.com/login/sso?x=1"
+        ));
+        assert!(!matches("example.com/login", "https://example.com/loginx"));
+        assert!(matches("example.com/app/", "https://example.com/app/x"));
+        assert!(!matches("example.com/app/", "https://example.com/app"));
+
+        assert!(UrlMatcher::parse("example.com:http").is_err());
+        assert!(UrlMatcher::parse("1http://example.com").is_err());
+    }
+
+    #[test]
+    fn regex() {
+        assert!(matches(
+            r"re:^https://[a-z]+\.example\.com/",
+            "https://abc.example.com/x"
+        ));
+        assert!(!matches(
+            r"re:^https://[a-z]+\.example\.com/",
+            "https://abc.example.com.evil/"
+        ));
+        assert!(UrlMatcher::parse("re:(").is_err());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let matcher = UrlMatcher::parse("https://*.example.com:8443/app").unwrap();
+        let json = serde_json::to_string(&matcher).unwrap();
+        assert_eq!(json, r#""https://*.example.com:8443/app""#);
+        assert_eq!(serde_json::from_str::<UrlMatcher>(&json).unwrap(), matcher);
+        assert!(matcher.error().is_none());
+    }
+
+    #[test]
+    fn invalid_sources_are_kept() {
+        for source in ["*.co.uk", "re:(", "example.com:http"] {
+            let json = serde_json::to_string(source).unwrap();
+            let matcher: UrlMatcher = serde_json::from_str(&json).unwrap();
+            assert_eq!(matcher.as_str(), source);
+            assert_eq!(matcher.error(), UrlMatcher::parse(source).err().as_ref());
+            assert!(!matcher.matches(&Url::parse("https://www.co.uk/").unwrap()));
+            assert_eq!(serde_json::to_string(&matcher).unwrap(), json);
+        }
+    }
+}
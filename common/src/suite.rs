@@ -37,3 +37,81 @@ pub enum DigestAlgorithm {
     #[serde(rename = "sha3-512")]
     Sha3_512,
 }
+
+impl DigestAlgorithm {
+    /// The length of the digest produced by the algorithm, in bytes.
+    pub const fn output_len(self) -> usize {
+        match self {
+            Self::Sha224 | Self::Sha512_224 => 28,
+            Self::Sha256 | Self::Sha512_256 | Self::Sha3_256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 | Self::Sha3_512 => 64,
+        }
+    }
+}
+
+/// Error returned when parsing an algorithm name that is not known to this implementation.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct UnknownAlgorithm(pub String);
+
+impl core::fmt::Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_fmt(format_args!("unknown algorithm `{}`", self.0))
+    }
+}
+
+impl std::error::Error for UnknownAlgorithm {}
+
+macro_rules! algorithm_names {
+    ($ty:ident { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl $ty {
+            /// The name of the algorithm, as used by the serialized form.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => $name,)*
+                }
+            }
+        }
+
+        impl core::fmt::Display for $ty {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+
+        impl core::str::FromStr for $ty {
+            type Err = UnknownAlgorithm;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok(Self::$variant),)*
+                    _ => Err(UnknownAlgorithm(s.to_string())),
+                }
+            }
+        }
+    };
+}
+
+algorithm_names!(SymmetricCipherAlgorithm {
+    Aes128Gcm => "aes128-gcm",
+    Aes128Cbc => "aes128-cbc",
+    Aes256Gcm => "aes256-gcm",
+    Aes256Cbc => "aes256-cbc",
+    Chacha20 => "chacha20",
+});
+
+algorithm_names!(AsymmetricCipherAlgorithm {
+    Rsa2048 => "rsa2048",
+    Rsa4096 => "rsa4096",
+    Ec25519 => "ec25519",
+});
+
+algorithm_names!(DigestAlgorithm {
+    Sha256 => "sha256",
+    Sha224 => "sha224",
+    Sha384 => "sha384",
+    Sha512 => "sha512",
+    Sha512_256 => "sha512/256",
+    Sha512_224 => "sha512/224",
+    Sha3_256 => "sha3-256",
+    Sha3_512 => "sha3-512",
+});
@@ -0,0 +1,1071 @@
+//! Evaluation of ACL rows.
+//!
+//! See [`AclMode`] for how rows at the different levels are combined.
+
+use std::collections::HashSet;
+
+use common::{
+    error::ErrorCode,
+    http::api::acl::{AclAction, AclMode, AclRow, CUSTOM_ACTION_SEPARATOR},
+};
+use rocket::{
+    get, http::Status, post, put, request::FromParam, routes, serde::json::Json, Route, State,
+};
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{ApiError, ApiResult},
+    groups::GroupRecord,
+    items::ItemRecord,
+    sessions::Session,
+    users::{get_parsed, UserRecord},
+};
+
+/// The object that global permissions are stored on.
+pub const SERVER_OBJECT: Uuid = Uuid::nil();
+
+/// Where the evaluator reads ACL rows, the vault hierarchy and group memberships from.
+pub trait AclSource {
+    /// The ACL rows of `object`. The global permissions are the rows of [`SERVER_OBJECT`].
+    fn rows(&self, object: Uuid) -> rusqlite::Result<Vec<AclRow>>;
+
+    /// The vaults that contain `object`, which it inherits permissions from.
+    fn parents(&self, object: Uuid) -> rusqlite::Result<Vec<Uuid>>;
+
+    /// The groups that `subject` was added to directly.
+    fn groups(&self, subject: Uuid) -> rusqlite::Result<Vec<Uuid>>;
+}
+
+impl AclSource for Connection {
+    fn rows(&self, object: Uuid) -> rusqlite::Result<Vec<AclRow>> {
+        let mut stmt = self.prepare_cached(
+            "SELECT subject_id, action, mode FROM acl WHERE object_id = ?1 ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([object], |row| {
+            Ok(AclRow {
+                subject: row.get(0)?,
+                action: get_parsed(row, 1)?,
+                mode: get_parsed(row, 2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    fn parents(&self, object: Uuid) -> rusqlite::Result<Vec<Uuid>> {
+        let mut stmt =
+            self.prepare_cached("SELECT parent_id FROM acl_parents WHERE object_id = ?1")?;
+        let parents = stmt.query_map([object], |row| row.get(0))?;
+        parents.collect()
+    }
+
+    fn groups(&self, subject: Uuid) -> rusqlite::Result<Vec<Uuid>> {
+        let mut stmt =
+            self.prepare_cached("SELECT group_id FROM group_members WHERE member_id = ?1")?;
+        let groups = stmt.query_map([subject], |row| row.get(0))?;
+        groups.collect()
+    }
+}
+
+/// `user`, and every group it is a member of, directly or through other groups.
+pub fn subjects(source: &(impl AclSource + ?Sized), user: Uuid) -> rusqlite::Result<HashSet<Uuid>> {
+    let mut subjects = HashSet::from([user]);
+    let mut pending = vec![user];
+    while let Some(subject) = pending.pop() {
+        for group in source.groups(subject)? {
+            if subjects.insert(group) {
+                pending.push(group);
+            }
+        }
+    }
+    Ok(subjects)
+}
+
+/// Combines two decisions made at the same level. `Forbid` wins over `Deny`, which wins over `Allow`.
+fn strongest(a: Option<AclMode>, b: Option<AclMode>) -> Option<AclMode> {
+    fn rank(mode: Option<AclMode>) -> u8 {
+        match mode {
+            None | Some(AclMode::Inherit) => 0,
+            Some(AclMode::Allow) => 1,
+            Some(AclMode::Deny) => 2,
+            Some(AclMode::Forbid) => 3,
+        }
+    }
+    if rank(b) > rank(a) {
+        b
+    } else {
+        a
+    }
+}
+
+/// The decision of the rows of a single object, or `None` if they do not decide.
+///
+/// An `Allow` row for `Owner` allows every action, but `Deny` and `Forbid` rows for `Owner` only affect `Owner`.
+///  Rows for the user and for its groups are treated alike.
+fn decide(rows: &[AclRow], subjects: &HashSet<Uuid>, action: &AclAction) -> Option<AclMode> {
+    rows.iter()
+        .filter(|row| subjects.contains(&row.subject))
+        .filter_map(|row| match row.mode {
+            _ if row.action == *action => Some(row.mode),
+            AclMode::Allow if row.action == AclAction::Owner => Some(AclMode::Allow),
+            _ => None,
+        })
+        .fold(None, |decision, mode| strongest(decision, Some(mode)))
+        .filter(|mode| *mode != AclMode::Inherit)
+}
+
+/// Resolves the permission of `subject` to perform `action` on `object`, through its own rows and the rows of the
+///  groups it is a member of.
+///
+/// Returns `Allow`, `Deny` or `Forbid`, never `Inherit`. The parents of `object` are visited level by level, so
+///  the nearest vault that decides wins, and a vault that is reachable along several paths is only visited once.
+pub fn resolve(
+    source: &(impl AclSource + ?Sized),
+    subject: Uuid,
+    object: Uuid,
+    action: &AclAction,
+) -> rusqlite::Result<AclMode> {
+    let subjects = subjects(source, subject)?;
+    let mut decision = None;
+    let mut seen = HashSet::from([object]);
+    let mut level = vec![object];
+
+    while !level.is_empty() {
+        let mut level_decision = None;
+        let mut next = Vec::new();
+        for id in level {
+            level_decision =
+                strongest(level_decision, decide(&source.rows(id)?, &subjects, action));
+            next.extend(
+                source
+                    .parents(id)?
+                    .into_iter()
+                    .filter(|parent| seen.insert(*parent)),
+            );
+        }
+        if level_decision == Some(AclMode::Forbid) {
+            return Ok(AclMode::Forbid);
+        }
+        decision = decision.or(level_decision);
+        level = next;
+    }
+
+    if object != SERVER_OBJECT {
+        match decide(&source.rows(SERVER_OBJECT)?, &subjects, action) {
+            Some(AclMode::Forbid) => return Ok(AclMode::Forbid),
+            global => decision = decision.or(global),
+        }
+    }
+
+    Ok(decision.unwrap_or(AclMode::Deny))
+}
+
+/// Whether `subject` may perform `action` on `object`.
+pub fn is_allowed(
+    source: &(impl AclSource + ?Sized),
+    subject: Uuid,
+    object: Uuid,
+    action: &AclAction,
+) -> rusqlite::Result<bool> {
+    Ok(resolve(source, subject, object, action)? == AclMode::Allow)
+}
+
+/// Fails with [`ErrorCode::PermissionDenied`] unless `subject` may perform `action` on `object`.
+pub fn require(
+    source: &(impl AclSource + ?Sized),
+    subject: Uuid,
+    object: Uuid,
+    action: &AclAction,
+) -> Result<(), ApiError> {
+    if is_allowed(source, subject, object, action)? {
+        Ok(())
+    } else {
+        Err(denied(object, action))
+    }
+}
+
+fn denied(object: Uuid, action: &AclAction) -> ApiError {
+    ApiError::new(
+        ErrorCode::PermissionDenied,
+        format!("permission `{action}` on {object} is required"),
+    )
+}
+
+/// Rejects rows with actions that are neither known nor namespaced custom actions, so that typos are not stored
+///  as rows that never match.
+pub fn validate_rows(rows: &[AclRow]) -> ApiResult<()> {
+    match rows.iter().find(|row| !row.action.is_valid()) {
+        Some(row) => Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!(
+                "unknown action `{}`, custom actions must be of the form `<namespace>{CUSTOM_ACTION_SEPARATOR}<name>`",
+                row.action
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Like [`upsert_rows`], for callers that are already in a transaction.
+pub fn insert_rows(conn: &Connection, object: Uuid, rows: &[AclRow]) -> rusqlite::Result<()> {
+    for row in rows {
+        conn.execute(
+            "INSERT INTO acl (object_id, subject_id, action, mode) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (object_id, subject_id, action) DO UPDATE SET mode = excluded.mode",
+            rusqlite::params![object, row.subject, row.action.name(), row.mode.name()],
+        )?;
+    }
+    Ok(())
+}
+
+/// Adds `rows` to the ACL of `object`, replacing existing rows with the same subject and action.
+pub fn upsert_rows(conn: &Connection, object: Uuid, rows: &[AclRow]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    insert_rows(&tx, object, rows)?;
+    tx.commit()
+}
+
+/// Replaces the whole ACL of `object` with `rows`.
+pub fn replace_rows(conn: &Connection, object: Uuid, rows: &[AclRow]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM acl WHERE object_id = ?1", [object])?;
+    insert_rows(&tx, object, rows)?;
+    tx.commit()
+}
+
+/// Records that the vault `parent` contains `object`, so that `object` inherits its rows.
+pub fn add_parent(conn: &Connection, object: Uuid, parent: Uuid) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO acl_parents (object_id, parent_id) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+        [object, parent],
+    )?;
+    Ok(())
+}
+
+/// Removes a link made by [`add_parent`]. Returns whether it existed.
+pub fn remove_parent(conn: &Connection, object: Uuid, parent: Uuid) -> rusqlite::Result<bool> {
+    let rows = conn.execute(
+        "DELETE FROM acl_parents WHERE object_id = ?1 AND parent_id = ?2",
+        [object, parent],
+    )?;
+    Ok(rows > 0)
+}
+
+/// The kinds of objects that have an ACL, as named in `/<object-type>/<uuid>/acl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectType {
+    Item,
+    User,
+    Group,
+}
+
+impl<'a> FromParam<'a> for ObjectType {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        match param {
+            "item" | "items" => Ok(Self::Item),
+            "user" | "users" => Ok(Self::User),
+            "group" | "groups" => Ok(Self::Group),
+            _ => Err(param),
+        }
+    }
+}
+
+/// Whether the session may perform `action` on an object.
+///
+/// Users are implicitly the `Owner` of their own user object.
+fn session_allowed(
+    conn: &Connection,
+    session: &Session,
+    object_type: ObjectType,
+    object: Uuid,
+    action: &AclAction,
+) -> rusqlite::Result<bool> {
+    if object_type == ObjectType::User && object == session.user_id {
+        return Ok(true);
+    }
+    is_allowed(conn, session.user_id, object, action)
+}
+
+fn session_require(
+    conn: &Connection,
+    session: &Session,
+    object_type: ObjectType,
+    object: Uuid,
+    action: &AclAction,
+) -> ApiResult<()> {
+    if session_allowed(conn, session, object_type, object, action)? {
+        Ok(())
+    } else {
+        Err(denied(object, action))
+    }
+}
+
+/// Fails unless `object` exists. The global permissions on [`SERVER_OBJECT`] are only reachable through
+///  `/server/permissions`.
+fn check_object(conn: &Connection, object_type: ObjectType, object: Uuid) -> ApiResult<()> {
+    if object == SERVER_OBJECT {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "use /server/permissions for global permissions",
+        ));
+    }
+    let exists = match object_type {
+        ObjectType::Item => ItemRecord::find_by_id(conn, object)?.is_some(),
+        ObjectType::User => UserRecord::find_by_id(conn, object)?.is_some(),
+        ObjectType::Group => GroupRecord::find_by_id(conn, object)?.is_some(),
+    };
+    if !exists {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no {object_type:?} {object}").to_lowercase(),
+        ));
+    }
+    Ok(())
+}
+
+#[get("/<object_type>/<object>/acl?<subject>")]
+fn get_acl(
+    object_type: ObjectType,
+    object: Uuid,
+    subject: Option<Uuid>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<Vec<AclRow>>> {
+    let conn = db.lock();
+    check_object(&conn, object_type, object)?;
+    session_require(&conn, &session, object_type, object, &AclAction::ReadAcl)?;
+
+    let mut rows = conn.rows(object)?;
+    if let Some(subject) = subject {
+        rows.retain(|row| row.subject == subject);
+    }
+    Ok(Json(rows))
+}
+
+#[post("/<object_type>/<object>/acl", data = "<rows>")]
+fn update_acl(
+    object_type: ObjectType,
+    object: Uuid,
+    rows: Json<Vec<AclRow>>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    check_object(&conn, object_type, object)?;
+    session_require(&conn, &session, object_type, object, &AclAction::WriteAcl)?;
+
+    if rows.iter().any(|row| row.action == AclAction::Owner)
+        && !session_allowed(&conn, &session, object_type, object, &AclAction::Owner)?
+        && !is_allowed(
+            &*conn,
+            session.user_id,
+            SERVER_OBJECT,
+            &AclAction::TakeOwnership,
+        )?
+    {
+        return Err(ApiError::new(
+            ErrorCode::PermissionDenied,
+            format!(
+                "changing `{}` requires `{}` on {object}, or the global `{}` permission",
+                AclAction::Owner,
+                AclAction::Owner,
+                AclAction::TakeOwnership
+            ),
+        ));
+    }
+
+    validate_rows(&rows)?;
+    upsert_rows(&conn, object, &rows)?;
+    Ok(Status::NoContent)
+}
+
+#[put("/<object_type>/<object>/acl", data = "<rows>")]
+fn replace_acl(
+    object_type: ObjectType,
+    object: Uuid,
+    rows: Json<Vec<AclRow>>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    check_object(&conn, object_type, object)?;
+    session_require(&conn, &session, object_type, object, &AclAction::Owner)?;
+
+    validate_rows(&rows)?;
+    replace_rows(&conn, object, &rows)?;
+    Ok(Status::NoContent)
+}
+
+/// Makes `user` a global `Owner`, unless the server ACL already has an `Owner` row for `user`, so that a `Deny` or
+///  `Forbid` set deliberately is not undone.
+pub fn grant_global_owner(conn: &Connection, user: Uuid) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO acl (object_id, subject_id, action, mode) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (object_id, subject_id, action) DO NOTHING",
+        rusqlite::params![
+            SERVER_OBJECT,
+            user,
+            AclAction::Owner.name(),
+            AclMode::Allow.name()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Fails unless some user is still a global `Owner`, so that the server cannot be locked out of its own
+///  permissions.
+pub fn require_global_owner(conn: &Connection) -> ApiResult<()> {
+    let owners: usize = conn.query_row(
+        "SELECT COUNT(*) FROM acl WHERE object_id = ?1 AND action = ?2 AND mode = ?3",
+        rusqlite::params![
+            SERVER_OBJECT,
+            AclAction::Owner.name(),
+            AclMode::Allow.name()
+        ],
+        |row| row.get(0),
+    )?;
+    if owners == 0 {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("at least one global `{}` is required", AclAction::Owner),
+        ));
+    }
+    Ok(())
+}
+
+#[get("/server/permissions?<subject>")]
+fn get_permissions(
+    subject: Option<Uuid>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<Vec<AclRow>>> {
+    let conn = db.lock();
+    require(&*conn, session.user_id, SERVER_OBJECT, &AclAction::ReadAcl)?;
+
+    let mut rows = conn.rows(SERVER_OBJECT)?;
+    if let Some(subject) = subject {
+        rows.retain(|row| row.subject == subject);
+    }
+    Ok(Json(rows))
+}
+
+#[post("/server/permissions", data = "<rows>")]
+fn update_permissions(
+    rows: Json<Vec<AclRow>>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    require(&*conn, session.user_id, SERVER_OBJECT, &AclAction::WriteAcl)?;
+    if rows.iter().any(|row| row.action == AclAction::Owner) {
+        require(&*conn, session.user_id, SERVER_OBJECT, &AclAction::Owner)?;
+    }
+
+    validate_rows(&rows)?;
+    let tx = conn.unchecked_transaction()?;
+    insert_rows(&tx, SERVER_OBJECT, &rows)?;
+    require_global_owner(&tx)?;
+    tx.commit()?;
+    Ok(Status::NoContent)
+}
+
+#[put("/server/permissions", data = "<rows>")]
+fn replace_permissions(
+    rows: Json<Vec<AclRow>>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    require(&*conn, session.user_id, SERVER_OBJECT, &AclAction::Owner)?;
+
+    validate_rows(&rows)?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM acl WHERE object_id = ?1", [SERVER_OBJECT])?;
+    insert_rows(&tx, SERVER_OBJECT, &rows)?;
+    require_global_owner(&tx)?;
+    tx.commit()?;
+    Ok(Status::NoContent)
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        get_acl,
+        update_acl,
+        replace_acl,
+        get_permissions,
+        update_permissions,
+        replace_permissions
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use common::http::api::acl::{AclAction, AclMode, AclRow};
+    use uuid::Uuid;
+
+    use rocket::http::{ContentType, Status};
+
+    use super::{
+        add_parent, grant_global_owner, remove_parent, resolve, routes, subjects, upsert_rows,
+        validate_rows, AclSource, SERVER_OBJECT,
+    };
+    use crate::{db::Database, now, testing};
+
+    const USER: Uuid = Uuid::from_u128(1);
+    const OTHER_USER: Uuid = Uuid::from_u128(2);
+    const ITEM: Uuid = Uuid::from_u128(10);
+    const VAULT: Uuid = Uuid::from_u128(11);
+    const OUTER_VAULT: Uuid = Uuid::from_u128(12);
+    const OTHER_VAULT: Uuid = Uuid::from_u128(13);
+    const GROUP: Uuid = Uuid::from_u128(20);
+    const OUTER_GROUP: Uuid = Uuid::from_u128(21);
+
+    const MODES: [Option<AclMode>; 5] = [
+        None,
+        Some(AclMode::Inherit),
+        Some(AclMode::Allow),
+        Some(AclMode::Deny),
+        Some(AclMode::Forbid),
+    ];
+
+    #[derive(Default)]
+    struct Source {
+        rows: HashMap<Uuid, Vec<AclRow>>,
+        parents: HashMap<Uuid, Vec<Uuid>>,
+        groups: HashMap<Uuid, Vec<Uuid>>,
+    }
+
+    impl Source {
+        fn row(&mut self, object: Uuid, subject: Uuid, action: AclAction, mode: AclMode) {
+            self.rows.entry(object).or_default().push(AclRow {
+                subject,
+                action,
+                mode,
+            });
+        }
+
+        fn parent(&mut self, object: Uuid, parent: Uuid) {
+            self.parents.entry(object).or_default().push(parent);
+        }
+
+        fn member(&mut self, subject: Uuid, group: Uuid) {
+            self.groups.entry(subject).or_default().push(group);
+        }
+
+        fn resolve(&self, object: Uuid, action: AclAction) -> AclMode {
+            resolve(self, USER, object, &action).unwrap()
+        }
+    }
+
+    impl AclSource for Source {
+        fn rows(&self, object: Uuid) -> rusqlite::Result<Vec<AclRow>> {
+            Ok(self.rows.get(&object).cloned().unwrap_or_default())
+        }
+
+        fn parents(&self, object: Uuid) -> rusqlite::Result<Vec<Uuid>> {
+            Ok(self.parents.get(&object).cloned().unwrap_or_default())
+        }
+
+        fn groups(&self, subject: Uuid) -> rusqlite::Result<Vec<Uuid>> {
+            Ok(self.groups.get(&subject).cloned().unwrap_or_default())
+        }
+    }
+
+    /// Every combination of modes on the item, its vault, the vault's vault, and the global permissions.
+    #[test]
+    fn truth_table_levels() {
+        for item in MODES {
+            for vault in MODES {
+                for outer in MODES {
+                    for global in MODES {
+                        let levels = [item, vault, outer, global];
+                        let mut source = Source::default();
+                        source.parent(ITEM, VAULT);
+                        source.parent(VAULT, OUTER_VAULT);
+                        for (object, mode) in [ITEM, VAULT, OUTER_VAULT, SERVER_OBJECT]
+                            .into_iter()
+                            .zip(levels)
+                        {
+                            if let Some(mode) = mode {
+                                source.row(object, USER, AclAction::Read, mode);
+                            }
+                        }
+
+                        let expected = if levels.contains(&Some(AclMode::Forbid)) {
+                            AclMode::Forbid
+                        } else {
+                            levels
+                                .into_iter()
+                                .flatten()
+                                .find(|mode| *mode != AclMode::Inherit)
+                                .unwrap_or(AclMode::Deny)
+                        };
+                        assert_eq!(
+                            source.resolve(ITEM, AclAction::Read),
+                            expected,
+                            "levels {levels:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Every pair of rows for the same action on the same object.
+    #[test]
+    fn truth_table_same_level() {
+        use AclMode::*;
+        let table = [
+            (Inherit, Inherit, Deny),
+            (Inherit, Allow, Allow),
+            (Inherit, Deny, Deny),
+            (Inherit, Forbid, Forbid),
+            (Allow, Allow, Allow),
+            (Allow, Deny, Deny),
+            (Allow, Forbid, Forbid),
+            (Deny, Deny, Deny),
+            (Deny, Forbid, Forbid),
+            (Forbid, Forbid, Forbid),
+        ];
+        for (a, b, expected) in table {
+            for (first, second) in [(a, b), (b, a)] {
+                let mut source = Source::default();
+                source.row(ITEM, USER, AclAction::Read, first);
+                source.row(ITEM, USER, AclAction::Read, second);
+                assert_eq!(
+                    source.resolve(ITEM, AclAction::Read),
+                    expected,
+                    "{first:?} {second:?}"
+                );
+            }
+        }
+    }
+
+    /// Every combination of modes on two vaults that both contain the item.
+    #[test]
+    fn truth_table_sibling_parents() {
+        for a in MODES {
+            for b in MODES {
+                let mut source = Source::default();
+                source.parent(ITEM, VAULT);
+                source.parent(ITEM, OTHER_VAULT);
+                for (vault, mode) in [(VAULT, a), (OTHER_VAULT, b)] {
+                    if let Some(mode) = mode {
+                        source.row(vault, USER, AclAction::Read, mode);
+                    }
+                }
+
+                let expected = [a, b]
+                    .into_iter()
+                    .flatten()
+                    .max_by_key(|mode| match mode {
+                        AclMode::Inherit => 0,
+                        AclMode::Allow => 1,
+                        AclMode::Deny => 2,
+                        AclMode::Forbid => 3,
+                    })
+                    .filter(|mode| *mode != AclMode::Inherit)
+                    .unwrap_or(AclMode::Deny);
+                assert_eq!(
+                    source.resolve(ITEM, AclAction::Read),
+                    expected,
+                    "{a:?} {b:?}"
+                );
+            }
+        }
+    }
+
+    /// Every mode of an `Owner` row against every mode of a row for another action.
+    #[test]
+    fn truth_table_owner() {
+        use AclMode::*;
+        let table = [
+            (None, None, Deny),
+            (None, Some(Allow), Allow),
+            (None, Some(Deny), Deny),
+            (None, Some(Forbid), Forbid),
+            (Some(Allow), None, Allow),
+            (Some(Allow), Some(Inherit), Allow),
+            (Some(Allow), Some(Allow), Allow),
+            (Some(Allow), Some(Deny), Deny),
+            (Some(Allow), Some(Forbid), Forbid),
+            (Some(Deny), None, Deny),
+            (Some(Deny), Some(Allow), Allow),
+            (Some(Forbid), None, Deny),
+            (Some(Forbid), Some(Allow), Allow),
+            (Some(Inherit), Some(Allow), Allow),
+        ];
+        for (owner, write, expected) in table {
+            let mut source = Source::default();
+            if let Some(mode) = owner {
+                source.row(ITEM, USER, AclAction::Owner, mode);
+            }
+            if let Some(mode) = write {
+                source.row(ITEM, USER, AclAction::Write, mode);
+            }
+            assert_eq!(
+                source.resolve(ITEM, AclAction::Write),
+                expected,
+                "{owner:?} {write:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn owner_is_inherited() {
+        let mut source = Source::default();
+        source.parent(ITEM, VAULT);
+        source.row(VAULT, USER, AclAction::Owner, AclMode::Allow);
+        assert_eq!(source.resolve(ITEM, AclAction::DeleteKeys), AclMode::Allow);
+        assert_eq!(source.resolve(ITEM, AclAction::Owner), AclMode::Allow);
+    }
+
+    #[test]
+    fn other_subjects_and_actions_are_ignored() {
+        let mut source = Source::default();
+        source.row(ITEM, OTHER_USER, AclAction::Read, AclMode::Allow);
+        source.row(ITEM, USER, AclAction::Write, AclMode::Allow);
+        assert_eq!(source.resolve(ITEM, AclAction::Read), AclMode::Deny);
+        assert_eq!(
+            resolve(&source, OTHER_USER, ITEM, &AclAction::Write).unwrap(),
+            AclMode::Deny
+        );
+    }
+
+    #[test]
+    fn cycles_terminate() {
+        let mut source = Source::default();
+        source.parent(ITEM, VAULT);
+        source.parent(VAULT, OUTER_VAULT);
+        source.parent(OUTER_VAULT, VAULT);
+        source.parent(OUTER_VAULT, ITEM);
+        assert_eq!(source.resolve(ITEM, AclAction::Read), AclMode::Deny);
+        source.row(OUTER_VAULT, USER, AclAction::Read, AclMode::Allow);
+        assert_eq!(source.resolve(ITEM, AclAction::Read), AclMode::Allow);
+    }
+
+    /// Every mode of a row for the user against every mode of a row for a group it is in through another group.
+    #[test]
+    fn truth_table_groups() {
+        for user in MODES {
+            for group in MODES {
+                let mut source = Source::default();
+                source.member(USER, GROUP);
+                source.member(GROUP, OUTER_GROUP);
+                for (subject, mode) in [(USER, user), (OUTER_GROUP, group)] {
+                    if let Some(mode) = mode {
+                        source.row(ITEM, subject, AclAction::Read, mode);
+                    }
+                }
+
+                let expected = [user, group]
+                    .into_iter()
+                    .flatten()
+                    .max_by_key(|mode| match mode {
+                        AclMode::Inherit => 0,
+                        AclMode::Allow => 1,
+                        AclMode::Deny => 2,
+                        AclMode::Forbid => 3,
+                    })
+                    .filter(|mode| *mode != AclMode::Inherit)
+                    .unwrap_or(AclMode::Deny);
+                assert_eq!(
+                    source.resolve(ITEM, AclAction::Read),
+                    expected,
+                    "{user:?} {group:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn group_membership() {
+        let mut source = Source::default();
+        source.member(USER, GROUP);
+        source.member(GROUP, OUTER_GROUP);
+        source.member(OUTER_GROUP, GROUP);
+        source.parent(ITEM, VAULT);
+        source.row(VAULT, OUTER_GROUP, AclAction::Owner, AclMode::Allow);
+        assert_eq!(source.resolve(ITEM, AclAction::Write), AclMode::Allow);
+        assert_eq!(
+            resolve(&source, OTHER_USER, ITEM, &AclAction::Write).unwrap(),
+            AclMode::Deny
+        );
+
+        source.row(ITEM, USER, AclAction::Write, AclMode::Deny);
+        assert_eq!(source.resolve(ITEM, AclAction::Write), AclMode::Deny);
+        assert_eq!(source.resolve(ITEM, AclAction::Read), AclMode::Allow);
+    }
+
+    #[test]
+    fn subjects_are_transitive() {
+        let mut source = Source::default();
+        assert_eq!(subjects(&source, USER).unwrap(), [USER].into());
+
+        source.member(USER, GROUP);
+        source.member(OTHER_USER, OUTER_GROUP);
+        assert_eq!(subjects(&source, USER).unwrap(), [USER, GROUP].into());
+
+        source.member(GROUP, OUTER_GROUP);
+        source.member(OUTER_GROUP, GROUP);
+        assert_eq!(
+            subjects(&source, USER).unwrap(),
+            [USER, GROUP, OUTER_GROUP].into()
+        );
+        assert_eq!(
+            subjects(&source, OTHER_USER).unwrap(),
+            [OTHER_USER, GROUP, OUTER_GROUP].into()
+        );
+        assert_eq!(
+            subjects(&source, OUTER_GROUP).unwrap(),
+            [GROUP, OUTER_GROUP].into()
+        );
+    }
+
+    #[test]
+    fn custom_actions() {
+        for (name, valid) in [
+            ("Read", true),
+            ("WriteClientPersistentStorage", true),
+            ("com.example:Approve", true),
+            ("acme-corp:share_vault", true),
+            ("Reed", false),
+            ("read", false),
+            (":Approve", false),
+            ("com.example:", false),
+            ("com example:Approve", false),
+        ] {
+            let action: AclAction = name.parse().unwrap();
+            assert_eq!(action.name(), name);
+            assert_eq!(
+                validate_rows(&[AclRow {
+                    subject: USER,
+                    action,
+                    mode: AclMode::Allow,
+                }])
+                .is_ok(),
+                valid,
+                "{name}"
+            );
+        }
+        assert!(!AclAction::Other("Read".into()).is_valid());
+    }
+
+    #[test]
+    fn global_permissions() {
+        let mut source = Source::default();
+        source.row(SERVER_OBJECT, USER, AclAction::ReadAcl, AclMode::Allow);
+        assert_eq!(
+            source.resolve(SERVER_OBJECT, AclAction::ReadAcl),
+            AclMode::Allow
+        );
+        assert_eq!(
+            source.resolve(SERVER_OBJECT, AclAction::WriteAcl),
+            AclMode::Deny
+        );
+        assert_eq!(source.resolve(ITEM, AclAction::ReadAcl), AclMode::Allow);
+
+        source.row(SERVER_OBJECT, USER, AclAction::Owner, AclMode::Allow);
+        assert_eq!(
+            source.resolve(SERVER_OBJECT, AclAction::WriteAcl),
+            AclMode::Allow
+        );
+        source.row(ITEM, USER, AclAction::WriteAcl, AclMode::Deny);
+        assert_eq!(source.resolve(ITEM, AclAction::WriteAcl), AclMode::Deny);
+    }
+
+    #[test]
+    fn sqlite_source() {
+        let db = Database::open(":memory:").unwrap();
+        let conn = db.lock();
+        let allow = |action| AclRow {
+            subject: GROUP,
+            action,
+            mode: AclMode::Allow,
+        };
+        upsert_rows(
+            &conn,
+            VAULT,
+            &[allow(AclAction::Read), allow(AclAction::Write)],
+        )
+        .unwrap();
+        upsert_rows(
+            &conn,
+            ITEM,
+            &[AclRow {
+                subject: USER,
+                action: AclAction::Write,
+                mode: AclMode::Deny,
+            }],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO group_members
+                (group_id, member_id, member_kind, secured_group_key, group_key_iv)
+                VALUES (?1, ?2, 'user', x'', x'')",
+            [GROUP, USER],
+        )
+        .unwrap();
+        let check = |action| resolve(&*conn, USER, ITEM, &action).unwrap();
+
+        assert_eq!(check(AclAction::Read), AclMode::Deny);
+        add_parent(&conn, ITEM, VAULT).unwrap();
+        add_parent(&conn, ITEM, VAULT).unwrap();
+        assert_eq!(conn.parents(ITEM).unwrap(), [VAULT]);
+        assert_eq!(check(AclAction::Read), AclMode::Allow);
+        assert_eq!(check(AclAction::Write), AclMode::Deny);
+        assert_eq!(
+            resolve(&*conn, OTHER_USER, ITEM, &AclAction::Read).unwrap(),
+            AclMode::Deny
+        );
+
+        assert!(remove_parent(&conn, ITEM, VAULT).unwrap());
+        assert!(!remove_parent(&conn, ITEM, VAULT).unwrap());
+        assert_eq!(check(AclAction::Read), AclMode::Deny);
+    }
+
+    #[test]
+    fn acl_routes_check_objects() {
+        let db = testing::database();
+        {
+            let conn = db.lock();
+            grant_global_owner(&conn, USER).unwrap();
+            conn.execute(
+                "INSERT INTO items (item_id, content_type, ctime, mtime, atime)
+                    VALUES (?1, 'text/plain', ?2, ?2, ?2)",
+                rusqlite::params![ITEM, now()],
+            )
+            .unwrap();
+        }
+        let auth = testing::login(&db, USER);
+        let auth = || auth.clone();
+        let client = testing::client(db, routes());
+        let rows = rocket::serde::json::to_string(&[AclRow {
+            subject: OTHER_USER,
+            action: AclAction::Owner,
+            mode: AclMode::Allow,
+        }])
+        .unwrap();
+
+        for object in [SERVER_OBJECT, ITEM] {
+            let expected = if object == ITEM {
+                Status::Ok
+            } else {
+                Status::BadRequest
+            };
+            let status = client
+                .get(format!("/item/{object}/acl"))
+                .header(auth())
+                .dispatch()
+                .status();
+            assert_eq!(status, expected, "{object}");
+        }
+        for (object, expected) in [
+            (SERVER_OBJECT, Status::BadRequest),
+            (VAULT, Status::NotFound),
+        ] {
+            let status = client
+                .post(format!("/item/{object}/acl"))
+                .header(auth())
+                .header(ContentType::JSON)
+                .body(&rows)
+                .dispatch()
+                .status();
+            assert_eq!(status, expected, "{object}");
+            let status = client
+                .put(format!("/items/{object}/acl"))
+                .header(auth())
+                .header(ContentType::JSON)
+                .body(&rows)
+                .dispatch()
+                .status();
+            assert_eq!(status, expected, "{object}");
+        }
+        assert_eq!(
+            client
+                .get(format!("/item/{VAULT}/acl"))
+                .header(auth())
+                .dispatch()
+                .status(),
+            Status::NotFound
+        );
+
+        let db = testing::served(&client);
+        let global = db.lock().rows(SERVER_OBJECT).unwrap();
+        assert_eq!(global.len(), 1);
+        assert_eq!(global[0].subject, USER);
+        assert!(db.lock().rows(VAULT).unwrap().is_empty());
+    }
+
+    #[test]
+    fn owner_rows_require_owner() {
+        const TAKER: Uuid = Uuid::from_u128(3);
+        let row = |subject, action, mode| AclRow {
+            subject,
+            action,
+            mode,
+        };
+        let db = testing::database();
+        {
+            let conn = db.lock();
+            conn.execute(
+                "INSERT INTO items (item_id, content_type, ctime, mtime, atime)
+                    VALUES (?1, 'text/plain', ?2, ?2, ?2)",
+                rusqlite::params![ITEM, now()],
+            )
+            .unwrap();
+            upsert_rows(
+                &conn,
+                ITEM,
+                &[
+                    row(USER, AclAction::WriteAcl, AclMode::Allow),
+                    row(TAKER, AclAction::WriteAcl, AclMode::Allow),
+                ],
+            )
+            .unwrap();
+            upsert_rows(
+                &conn,
+                SERVER_OBJECT,
+                &[row(TAKER, AclAction::TakeOwnership, AclMode::Allow)],
+            )
+            .unwrap();
+        }
+        let (user, taker) = (testing::login(&db, USER), testing::login(&db, TAKER));
+        let client = testing::client(db, routes());
+        let send = |put: bool, auth: &rocket::http::Header<'static>, rows: &[AclRow]| {
+            let uri = format!("/item/{ITEM}/acl");
+            let request = if put {
+                client.put(uri)
+            } else {
+                client.post(uri)
+            };
+            request
+                .header(auth.clone())
+                .header(ContentType::JSON)
+                .body(rocket::serde::json::to_string(&rows).unwrap())
+                .dispatch()
+                .status()
+        };
+        let owner = [row(OTHER_USER, AclAction::Owner, AclMode::Allow)];
+        let read = [row(OTHER_USER, AclAction::Read, AclMode::Allow)];
+
+        assert_eq!(send(false, &user, &read), Status::NoContent);
+        assert_eq!(send(false, &user, &owner), Status::Forbidden);
+        assert_eq!(send(true, &user, &read), Status::Forbidden);
+        assert_eq!(send(true, &taker, &read), Status::Forbidden);
+
+        assert_eq!(send(false, &taker, &owner), Status::NoContent);
+        let owner = [row(USER, AclAction::Owner, AclMode::Allow)];
+        assert_eq!(send(false, &taker, &owner), Status::NoContent);
+        assert_eq!(send(false, &user, &owner), Status::NoContent);
+        assert_eq!(send(true, &user, &owner), Status::NoContent);
+
+        let db = testing::served(&client);
+        assert_eq!(db.lock().rows(ITEM).unwrap(), owner);
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use client_sdk::cipher::{asymmetric::DefaultAsymmetricCipher, AsymmetricCipherSpi};
+use common::{
+    data::Bytes,
+    error::ErrorCode,
+    http::api::auth::{AuthChallengeRequest, AuthChallengeResponse, AuthResponse, AuthSession},
+    suite::DigestAlgorithm,
+};
+use rand::{rngs::OsRng, RngCore};
+use rocket::{post, routes, serde::json::Json, Route, State};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{ApiError, ApiResult},
+    sessions::{create_session, BearerToken},
+    users::UserRecord,
+    ServerConfig,
+};
+
+/// How long a client has to answer a challenge.
+pub const CHALLENGE_LIFETIME: Duration = Duration::from_secs(60);
+
+/// How many unanswered challenges a user can have. Issuing another drops the oldest, so that callers who know a user ID
+///  cannot grow [`PendingChallenges`] without bound.
+pub const MAX_PENDING_CHALLENGES: usize = 8;
+
+const CHALLENGE_DIGEST: DigestAlgorithm = DigestAlgorithm::Sha256;
+
+const CHALLENGE_LEN: usize = 32;
+
+struct PendingChallenge {
+    user_id: Uuid,
+    digest: DigestAlgorithm,
+    bytes: Vec<u8>,
+    expires: Instant,
+}
+
+/// Challenges that have been issued but not yet answered, keyed by `challenge_session_id`.
+///
+/// Expired challenges are dropped whenever a new one is issued, and each user has at most [`MAX_PENDING_CHALLENGES`].
+#[derive(Default)]
+pub struct PendingChallenges(Mutex<HashMap<Uuid, PendingChallenge>>);
+
+#[post("/auth/challenge", data = "<req>")]
+fn auth_challenge(
+    req: Json<AuthChallengeRequest>,
+    db: &State<Database>,
+    pending: &State<PendingChallenges>,
+) -> ApiResult<Json<AuthChallengeResponse>> {
+    let AuthChallengeRequest {
+        user_id,
+        challenge_session_id,
+    } = req.into_inner();
+
+    if UserRecord::find_by_id(&db.lock(), user_id)?.is_none() {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no user {user_id}"),
+        ));
+    }
+
+    let mut bytes = vec![0u8; CHALLENGE_LEN];
+    OsRng.fill_bytes(&mut bytes);
+
+    let mut pending = pending.0.lock().unwrap_or_else(|e| e.into_inner());
+    let time = Instant::now();
+    pending.retain(|_, challenge| challenge.expires > time);
+
+    if pending.contains_key(&challenge_session_id) {
+        return Err(ApiError::new(
+            ErrorCode::Conflict,
+            "challenge session ID is already in use",
+        ));
+    }
+
+    let issued = pending
+        .values()
+        .filter(|challenge| challenge.user_id == user_id)
+        .count();
+    if issued >= MAX_PENDING_CHALLENGES {
+        let oldest = pending
+            .iter()
+            .filter(|(_, challenge)| challenge.user_id == user_id)
+            .min_by_key(|(_, challenge)| challenge.expires)
+            .map(|(id, _)| *id);
+        if let Some(oldest) = oldest {
+            pending.remove(&oldest);
+        }
+    }
+
+    pending.insert(
+        challenge_session_id,
+        PendingChallenge {
+            user_id,
+            digest: CHALLENGE_DIGEST,
+            bytes: bytes.clone(),
+            expires: time + CHALLENGE_LIFETIME,
+        },
+    );
+
+    Ok(Json(AuthChallengeResponse {
+        challenge_digest: CHALLENGE_DIGEST,
+        challenge_bytes: Bytes::new(bytes),
+    }))
+}
+
+#[post("/auth/response", data = "<res>")]
+async fn auth_response(
+    token: BearerToken<'_>,
+    res: Json<AuthResponse>,
+    db: &State<Database>,
+    config: &State<ServerConfig>,
+    pending: &State<PendingChallenges>,
+) -> ApiResult<Json<AuthSession>> {
+    let failed = || ApiError::new(ErrorCode::NotAuthenticated, "challenge failed");
+    let challenge_session_id: Uuid = token.0.parse().map_err(|_| failed())?;
+
+    // A challenge can only be answered once, whether or not the answer is correct
+    let challenge = pending
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&challenge_session_id)
+        .filter(|challenge| challenge.expires > Instant::now())
+        .ok_or_else(failed)?;
+
+    let user = UserRecord::find_by_id(&db.lock(), challenge.user_id)?.ok_or_else(failed)?;
+
+    let mut cipher = DefaultAsymmetricCipher::new();
+    cipher
+        .init(user.key_pair_algorithm)
+        .await
+        .map_err(|_| failed())?;
+    let valid = cipher
+        .verify(
+            &user.pubkey,
+            challenge.digest,
+            &challenge.bytes,
+            &res.challenge_signature,
+        )
+        .await
+        .map_err(|_| failed())?;
+
+    if !valid {
+        return Err(failed());
+    }
+
+    Ok(Json(create_session(
+        &db.lock(),
+        user.userid,
+        config.session_lifetime(),
+    )?))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![auth_challenge, auth_response]
+}
+
+#[cfg(test)]
+mod test {
+    use client_sdk::cipher::KeyPair;
+    use common::{
+        http::api::auth::{AuthChallengeRequest, AuthChallengeResponse, AuthResponse, AuthSession},
+        suite::AsymmetricCipherAlgorithm,
+    };
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::blocking::Client,
+        serde::json,
+    };
+
+    use super::*;
+    use crate::testing;
+
+    const ALG: AsymmetricCipherAlgorithm = AsymmetricCipherAlgorithm::Ec25519;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        rocket::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn sign(key_pair: &KeyPair, message: &[u8]) -> Bytes {
+        block_on(async {
+            let mut cipher = DefaultAsymmetricCipher::new();
+            cipher.init(ALG).await.unwrap();
+            cipher
+                .sign(&key_pair.private_key, CHALLENGE_DIGEST, message)
+                .await
+                .unwrap()
+        })
+    }
+
+    /// A client for the auth routes, with one user whose key pair is returned.
+    fn client() -> (Client, Uuid, KeyPair) {
+        let key_pair = block_on(async {
+            let mut cipher = DefaultAsymmetricCipher::new();
+            cipher.init(ALG).await.unwrap();
+            cipher.generate_key_pair().await.unwrap()
+        });
+        let db = testing::database();
+        let user = UserRecord {
+            userid: Uuid::new_v4(),
+            address_digest_algorithm: DigestAlgorithm::Sha256,
+            address_hash: vec![0; 32],
+            key_pair_algorithm: ALG,
+            pubkey: key_pair.public_key.to_vec(),
+            kdf_base_digest_algorithm: DigestAlgorithm::Sha256,
+            kdf_params: None,
+            priv_key_cipher: None,
+            priv_key_iv: Vec::new(),
+            sealed_priv_key: Vec::new(),
+            root_key_id: Uuid::new_v4(),
+            root_object_id: Uuid::new_v4(),
+        };
+        user.insert(&db.lock()).unwrap();
+        (testing::client(db, routes()), user.userid, key_pair)
+    }
+
+    fn challenge(client: &Client, user_id: Uuid, challenge_session_id: Uuid) -> Option<Vec<u8>> {
+        let response = client
+            .post("/auth/challenge")
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&AuthChallengeRequest {
+                    user_id,
+                    challenge_session_id,
+                })
+                .unwrap(),
+            )
+            .dispatch();
+        (response.status() == Status::Ok).then(|| {
+            let challenge: AuthChallengeResponse = response.into_json().unwrap();
+            assert_eq!(challenge.challenge_digest, CHALLENGE_DIGEST);
+            challenge.challenge_bytes.into_inner()
+        })
+    }
+
+    fn respond(client: &Client, challenge_session_id: Uuid, signature: Bytes) -> Status {
+        client
+            .post("/auth/response")
+            .header(Header::new(
+                "Authorization",
+                format!("Bearer {challenge_session_id}"),
+            ))
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&AuthResponse {
+                    challenge_signature: signature,
+                })
+                .unwrap(),
+            )
+            .dispatch()
+            .status()
+    }
+
+    fn pending(client: &Client) -> std::sync::MutexGuard<'_, HashMap<Uuid, PendingChallenge>> {
+        client
+            .rocket()
+            .state::<PendingChallenges>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap()
+    }
+
+    #[test]
+    fn sign_in() {
+        let (client, user, key_pair) = client();
+        let id = Uuid::new_v4();
+        let bytes = challenge(&client, user, id).unwrap();
+        assert_eq!(bytes.len(), CHALLENGE_LEN);
+
+        let response = client
+            .post("/auth/response")
+            .header(Header::new("Authorization", format!("Bearer {id}")))
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&AuthResponse {
+                    challenge_signature: sign(&key_pair, &bytes),
+                })
+                .unwrap(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let session: AuthSession = response.into_json().unwrap();
+        assert!(!session.session_token.is_empty());
+
+        // The challenge was consumed
+        assert_eq!(
+            respond(&client, id, sign(&key_pair, &bytes)),
+            Status::Unauthorized
+        );
+        assert!(pending(&client).is_empty());
+    }
+
+    #[test]
+    fn bad_signature() {
+        let (client, user, key_pair) = client();
+        let id = Uuid::new_v4();
+        let bytes = challenge(&client, user, id).unwrap();
+
+        assert_eq!(
+            respond(&client, id, sign(&key_pair, b"something else")),
+            Status::Unauthorized
+        );
+        assert_eq!(
+            respond(&client, id, sign(&key_pair, &bytes)),
+            Status::Unauthorized
+        );
+    }
+
+    #[test]
+    fn expired_challenge() {
+        let (client, user, key_pair) = client();
+        let id = Uuid::new_v4();
+        let bytes = challenge(&client, user, id).unwrap();
+        pending(&client).get_mut(&id).unwrap().expires = Instant::now();
+
+        assert_eq!(
+            respond(&client, id, sign(&key_pair, &bytes)),
+            Status::Unauthorized
+        );
+    }
+
+    #[test]
+    fn unknown_challenge() {
+        let (client, user, key_pair) = client();
+        assert_eq!(challenge(&client, Uuid::new_v4(), Uuid::new_v4()), None);
+        assert!(pending(&client).is_empty());
+
+        let id = Uuid::new_v4();
+        let bytes = challenge(&client, user, id).unwrap();
+        assert_eq!(challenge(&client, user, id), None);
+        assert_eq!(
+            respond(&client, Uuid::new_v4(), sign(&key_pair, &bytes)),
+            Status::Unauthorized
+        );
+        assert_eq!(respond(&client, id, sign(&key_pair, &bytes)), Status::Ok);
+    }
+
+    #[test]
+    fn pending_challenges_are_capped() {
+        let (client, user, key_pair) = client();
+        let ids = (0..MAX_PENDING_CHALLENGES + 2)
+            .map(|_| Uuid::new_v4())
+            .collect::<Vec<_>>();
+        let challenges = ids
+            .iter()
+            .map(|id| challenge(&client, user, *id).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pending(&client).len(), MAX_PENDING_CHALLENGES);
+
+        assert_eq!(
+            respond(&client, ids[0], sign(&key_pair, &challenges[0])),
+            Status::Unauthorized
+        );
+        let last = ids.len() - 1;
+        assert_eq!(
+            respond(&client, ids[last], sign(&key_pair, &challenges[last])),
+            Status::Ok
+        );
+    }
+}
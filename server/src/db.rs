@@ -0,0 +1,207 @@
+use std::{
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+use rusqlite::{Connection, OptionalExtension};
+use uuid::Uuid;
+
+/// Schema migrations, applied in order.
+///
+/// The number of applied migrations is tracked in `PRAGMA user_version`.
+/// Existing entries must never be modified, only appended to.
+const MIGRATIONS: &[&str] = &[
+    // 1: Server Identity
+    "CREATE TABLE IF NOT EXISTS server_info (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        server_id BLOB NOT NULL
+    );",
+    // 2: Users
+    "CREATE TABLE users (
+        user_id BLOB PRIMARY KEY NOT NULL,
+        address_digest_algorithm TEXT NOT NULL,
+        address_hash BLOB NOT NULL,
+        key_pair_algorithm TEXT NOT NULL,
+        pubkey BLOB NOT NULL,
+        sealed_priv_key BLOB NOT NULL,
+        root_key_id BLOB NOT NULL,
+        root_object_id BLOB NOT NULL,
+        UNIQUE (address_digest_algorithm, address_hash)
+    );",
+    // 3: Private key wrapping parameters
+    "ALTER TABLE users ADD COLUMN kdf_base_digest_algorithm TEXT NOT NULL DEFAULT 'sha256';
+    ALTER TABLE users ADD COLUMN priv_key_iv BLOB NOT NULL DEFAULT x'';",
+    // 4: Sessions
+    "CREATE TABLE sessions (
+        session_id BLOB PRIMARY KEY NOT NULL,
+        user_id BLOB NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+        token_hash BLOB NOT NULL UNIQUE,
+        created_at TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );",
+    // 5: Session usage tracking
+    "ALTER TABLE sessions ADD COLUMN last_used_at TEXT;",
+    // 6: Versioned KDF parameters, stored as JSON
+    "ALTER TABLE users ADD COLUMN kdf_params TEXT;",
+    // 7: ACLs, and the vaults that objects inherit them from
+    "CREATE TABLE acl (
+        object_id BLOB NOT NULL,
+        subject_id BLOB NOT NULL,
+        action TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        PRIMARY KEY (object_id, subject_id, action)
+    );
+    CREATE TABLE acl_parents (
+        object_id BLOB NOT NULL,
+        parent_id BLOB NOT NULL,
+        PRIMARY KEY (object_id, parent_id)
+    );",
+    // 8: Groups, and the group key wrapped for each member
+    "CREATE TABLE groups (
+        group_id BLOB PRIMARY KEY NOT NULL,
+        display_name TEXT,
+        key_id BLOB NOT NULL
+    );
+    CREATE TABLE group_members (
+        group_id BLOB NOT NULL REFERENCES groups (group_id) ON DELETE CASCADE,
+        member_id BLOB NOT NULL,
+        member_kind TEXT NOT NULL,
+        secured_group_key BLOB NOT NULL,
+        group_key_iv BLOB NOT NULL,
+        group_auth_tag BLOB,
+        PRIMARY KEY (group_id, member_id)
+    );
+    CREATE INDEX group_members_by_member ON group_members (member_id);",
+    // 9: Private key sealing cipher
+    "ALTER TABLE users ADD COLUMN priv_key_cipher TEXT;",
+    // 10: Items, with their key list stored as JSON, and the item key wrapped for each key
+    "CREATE TABLE items (
+        item_id BLOB PRIMARY KEY NOT NULL,
+        content_type TEXT NOT NULL,
+        keys TEXT,
+        contents BLOB,
+        revision INTEGER NOT NULL DEFAULT 0,
+        ctime TEXT NOT NULL,
+        mtime TEXT NOT NULL,
+        atime TEXT NOT NULL
+    );
+    CREATE TABLE item_keys (
+        item_id BLOB NOT NULL REFERENCES items (item_id) ON DELETE CASCADE,
+        key_id BLOB NOT NULL,
+        secured_item_key BLOB NOT NULL,
+        item_key_iv BLOB NOT NULL,
+        item_auth_tag BLOB,
+        PRIMARY KEY (item_id, key_id)
+    );",
+];
+
+/// The server's SQLite database.
+///
+/// A single connection is shared between all requests.
+pub struct Database(Mutex<Connection>);
+
+impl Database {
+    pub fn open<P: AsRef<Path>>(path: P) -> rusqlite::Result<Self> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+        Ok(Self(Mutex::new(conn)))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the persistent ID of this server, generating and storing a new one on first start.
+    pub fn server_id(&self) -> rusqlite::Result<Uuid> {
+        let conn = self.lock();
+        let existing = conn
+            .query_row(
+                "SELECT server_id FROM server_info WHERE id = 0",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match existing {
+            Some(id) => Ok(id),
+            None => {
+                let id = Uuid::new_v4();
+                conn.execute(
+                    "INSERT INTO server_info (id, server_id) VALUES (0, ?1)",
+                    [id],
+                )?;
+                Ok(id)
+            }
+        }
+    }
+}
+
+/// Applies every migration in [`MIGRATIONS`] that has not yet been applied to `conn`.
+///
+/// Each migration runs in its own transaction together with the version bump.
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    if applied > MIGRATIONS.len() {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISMATCH),
+            Some(format!(
+                "database schema version {} is newer than the newest known version {}",
+                applied,
+                MIGRATIONS.len()
+            )),
+        ));
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn fresh_database_is_migrated() {
+        let db = Database::open(":memory:").unwrap();
+        let mut conn = db.lock();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn reopen_is_idempotent() {
+        let dir = std::env::temp_dir().join(format!("passman-server-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.db");
+
+        let server_id = Database::open(&path).unwrap().server_id().unwrap();
+        let db = Database::open(&path).unwrap();
+        assert_eq!(user_version(&db.lock()), MIGRATIONS.len());
+        assert_eq!(db.server_id().unwrap(), server_id);
+        drop(db);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        assert!(migrate(&mut conn).is_err());
+    }
+}
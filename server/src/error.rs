@@ -0,0 +1,61 @@
+use client_sdk::cipher::CipherSuiteError;
+use common::error::{Error, ErrorCode};
+use rocket::{
+    catch,
+    http::Status,
+    response::{self, Responder},
+    serde::json::Json,
+    Request,
+};
+
+/// An error response, sent as the JSON error body described in [`common::error::Error`].
+#[derive(Debug)]
+pub struct ApiError(pub Error);
+
+impl ApiError {
+    pub fn new(code: ErrorCode, text: impl Into<String>) -> Self {
+        Self(Error::new(code, text))
+    }
+
+    pub fn status(&self) -> Status {
+        Status::new(self.0.code().http_status())
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(value: Error) -> Self {
+        Self(value)
+    }
+}
+
+impl From<CipherSuiteError> for ApiError {
+    fn from(value: CipherSuiteError) -> Self {
+        Self(value.into())
+    }
+}
+
+/// Database errors are logged, but not sent to the client.
+impl From<rusqlite::Error> for ApiError {
+    fn from(value: rusqlite::Error) -> Self {
+        rocket::error!("Database error: {}", value);
+        Self::new(ErrorCode::Internal, "database error")
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        (status, Json(self.0)).respond_to(req)
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Renders errors that are not produced by a handler, such as failed request guards or unknown routes, as an
+///  error body. The status is kept as is.
+#[catch(default)]
+pub fn default_catcher(status: Status, _req: &Request) -> (Status, Json<Error>) {
+    let code = ErrorCode::from_http_status(status.code);
+    let text = status.reason().unwrap_or("request failed").to_lowercase();
+    (status, Json(Error::new(code, text)))
+}
@@ -0,0 +1,443 @@
+use common::{
+    error::ErrorCode,
+    http::api::{
+        acl::{AclAction, AclMode, AclRow},
+        group::{
+            GroupInfo, GroupKeyInfo, GroupMember, NewGroupRequest, NewGroupResponse, PrincipalKind,
+        },
+    },
+};
+use rocket::{delete, get, http::Status, post, put, routes, serde::json::Json, Route, State};
+use rusqlite::{Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    acl,
+    db::Database,
+    error::{ApiError, ApiResult},
+    sessions::Session,
+    users::{get_parsed, UserRecord},
+};
+
+pub struct GroupRecord {
+    pub group_id: Uuid,
+    pub display_name: Option<String>,
+    pub key_id: Uuid,
+}
+
+impl GroupRecord {
+    pub fn find_by_id(conn: &Connection, group_id: Uuid) -> rusqlite::Result<Option<Self>> {
+        conn.query_row(
+            "SELECT group_id, display_name, key_id FROM groups WHERE group_id = ?1",
+            [group_id],
+            |row| {
+                Ok(Self {
+                    group_id: row.get(0)?,
+                    display_name: row.get(1)?,
+                    key_id: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+    }
+
+    pub fn members(&self, conn: &Connection) -> rusqlite::Result<Vec<GroupMember>> {
+        let mut stmt = conn.prepare(
+            "SELECT member_id, member_kind FROM group_members WHERE group_id = ?1 ORDER BY rowid",
+        )?;
+        let members = stmt.query_map([self.group_id], |row| {
+            Ok(GroupMember {
+                member_id: row.get(0)?,
+                kind: get_parsed(row, 1)?,
+            })
+        })?;
+        members.collect()
+    }
+}
+
+fn set_member(
+    conn: &Connection,
+    group_id: Uuid,
+    member_id: Uuid,
+    kind: PrincipalKind,
+    key: &GroupKeyInfo,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO group_members (group_id, member_id, member_kind, secured_group_key, group_key_iv, group_auth_tag)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (group_id, member_id) DO UPDATE SET
+                secured_group_key = excluded.secured_group_key,
+                group_key_iv = excluded.group_key_iv,
+                group_auth_tag = excluded.group_auth_tag",
+        rusqlite::params![
+            group_id,
+            member_id,
+            kind.name(),
+            key.secured_item_key.as_ref(),
+            key.item_key_iv.as_ref(),
+            key.item_auth_tag.as_ref().map(|tag| tag.as_ref()),
+        ],
+    )?;
+    Ok(())
+}
+
+fn no_group(group_id: Uuid) -> ApiError {
+    ApiError::new(ErrorCode::NotFound, format!("no group {group_id}"))
+}
+
+fn find_group(conn: &Connection, group_id: Uuid) -> ApiResult<GroupRecord> {
+    GroupRecord::find_by_id(conn, group_id)?.ok_or_else(|| no_group(group_id))
+}
+
+/// Whether the session is a member of `group_id`, or allowed to `Read` it.
+fn can_read(conn: &Connection, session: &Session, group_id: Uuid) -> rusqlite::Result<bool> {
+    Ok(acl::subjects(conn, session.user_id)?.contains(&group_id)
+        || acl::is_allowed(conn, session.user_id, group_id, &AclAction::Read)?)
+}
+
+#[post("/groups/new", data = "<req>")]
+fn new_group(
+    req: Json<NewGroupRequest>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<NewGroupResponse>> {
+    let NewGroupRequest {
+        display_name,
+        key_id,
+        creator_key,
+    } = req.into_inner();
+    let group_id = Uuid::new_v4();
+
+    let conn = db.lock();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO groups (group_id, display_name, key_id) VALUES (?1, ?2, ?3)",
+        rusqlite::params![group_id, display_name, key_id],
+    )?;
+    set_member(
+        &tx,
+        group_id,
+        session.user_id,
+        PrincipalKind::User,
+        &creator_key,
+    )?;
+    acl::insert_rows(
+        &tx,
+        group_id,
+        &[AclRow {
+            subject: session.user_id,
+            action: AclAction::Owner,
+            mode: AclMode::Allow,
+        }],
+    )?;
+    tx.commit()?;
+
+    Ok(Json(NewGroupResponse { group_id }))
+}
+
+#[get("/groups/<group_id>")]
+fn group(group_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Json<GroupInfo>> {
+    let conn = db.lock();
+    let group = find_group(&conn, group_id)?;
+    if !can_read(&conn, &session, group_id)? {
+        return Err(ApiError::new(
+            ErrorCode::PermissionDenied,
+            format!("only members of group {group_id} may read it"),
+        ));
+    }
+
+    Ok(Json(GroupInfo {
+        members: group.members(&conn)?,
+        display_name: group.display_name,
+        key_id: group.key_id,
+    }))
+}
+
+#[delete("/groups/<group_id>")]
+fn delete_group(group_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_group(&conn, group_id)?;
+    acl::require(&*conn, session.user_id, group_id, &AclAction::Delete)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM group_members WHERE group_id = ?1 OR member_id = ?1",
+        [group_id],
+    )?;
+    tx.execute(
+        "DELETE FROM acl WHERE object_id = ?1 OR subject_id = ?1",
+        [group_id],
+    )?;
+    tx.execute("DELETE FROM groups WHERE group_id = ?1", [group_id])?;
+    acl::require_global_owner(&tx)?;
+    tx.commit()?;
+    Ok(Status::NoContent)
+}
+
+#[put("/groups/<group_id>/members/<member_id>", data = "<key>")]
+fn put_member(
+    group_id: Uuid,
+    member_id: Uuid,
+    key: Json<GroupKeyInfo>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_group(&conn, group_id)?;
+    acl::require(&*conn, session.user_id, group_id, &AclAction::Write)?;
+
+    let kind = if UserRecord::find_by_id(&conn, member_id)?.is_some() {
+        PrincipalKind::User
+    } else if GroupRecord::find_by_id(&conn, member_id)?.is_some() {
+        // A group is a member of every group that contains it, so adding a group to one of those would make a
+        //  cycle
+        if member_id == group_id {
+            return Err(ApiError::new(
+                ErrorCode::InvalidRequest,
+                "a group cannot be a member of itself",
+            ));
+        }
+        if acl::subjects(&*conn, group_id)?.contains(&member_id) {
+            return Err(ApiError::new(
+                ErrorCode::InvalidRequest,
+                format!("group {member_id} already contains group {group_id}"),
+            ));
+        }
+        PrincipalKind::Group
+    } else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no user or group {member_id}"),
+        ));
+    };
+
+    set_member(&conn, group_id, member_id, kind, &key)?;
+    Ok(Status::NoContent)
+}
+
+#[delete("/groups/<group_id>/members/<member_id>")]
+fn delete_member(
+    group_id: Uuid,
+    member_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_group(&conn, group_id)?;
+    if member_id != session.user_id {
+        acl::require(&*conn, session.user_id, group_id, &AclAction::Write)?;
+    }
+
+    let rows = conn.execute(
+        "DELETE FROM group_members WHERE group_id = ?1 AND member_id = ?2",
+        rusqlite::params![group_id, member_id],
+    )?;
+    if rows == 0 {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("{member_id} is not a member of group {group_id}"),
+        ));
+    }
+    Ok(Status::NoContent)
+}
+
+#[get("/groups/<group_id>/members/<member_id>/key")]
+fn member_key(
+    group_id: Uuid,
+    member_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<GroupKeyInfo>> {
+    let conn = db.lock();
+    find_group(&conn, group_id)?;
+    if !acl::subjects(&*conn, session.user_id)?.contains(&member_id)
+        && !acl::is_allowed(&*conn, session.user_id, group_id, &AclAction::Read)?
+    {
+        return Err(ApiError::new(
+            ErrorCode::PermissionDenied,
+            format!("only {member_id} may read its group key"),
+        ));
+    }
+
+    conn.query_row(
+        "SELECT secured_group_key, group_key_iv, group_auth_tag FROM group_members
+            WHERE group_id = ?1 AND member_id = ?2",
+        rusqlite::params![group_id, member_id],
+        |row| {
+            Ok(GroupKeyInfo {
+                secured_item_key: row.get::<_, Vec<u8>>(0)?.into(),
+                item_key_iv: row.get::<_, Vec<u8>>(1)?.into(),
+                item_auth_tag: row.get::<_, Option<Vec<u8>>>(2)?.map(Into::into),
+            })
+        },
+    )
+    .optional()?
+    .map(Json)
+    .ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::NotFound,
+            format!("{member_id} is not a member of group {group_id}"),
+        )
+    })
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        new_group,
+        group,
+        delete_group,
+        put_member,
+        delete_member,
+        member_key
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use common::http::api::{
+        acl::{AclAction, AclMode, AclRow},
+        group::{GroupKeyInfo, PrincipalKind},
+    };
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::blocking::Client,
+    };
+    use uuid::Uuid;
+
+    use super::{routes, set_member};
+    use crate::{
+        acl::{self, AclSource},
+        db::Database,
+        testing,
+    };
+
+    const USER: Uuid = Uuid::from_u128(1);
+    const OTHER_USER: Uuid = Uuid::from_u128(2);
+    const VAULT: Uuid = Uuid::from_u128(11);
+    const GROUP: Uuid = Uuid::from_u128(20);
+    const OUTER_GROUP: Uuid = Uuid::from_u128(21);
+    const INNER_GROUP: Uuid = Uuid::from_u128(22);
+
+    fn key() -> GroupKeyInfo {
+        GroupKeyInfo {
+            secured_item_key: vec![1; 32].into(),
+            item_key_iv: vec![2; 12].into(),
+            item_auth_tag: None,
+        }
+    }
+
+    /// A database with the three groups.
+    fn database() -> Database {
+        let db = testing::database();
+        {
+            let conn = db.lock();
+            for group in [GROUP, OUTER_GROUP, INNER_GROUP] {
+                conn.execute(
+                    "INSERT INTO groups (group_id, key_id) VALUES (?1, ?1)",
+                    [group],
+                )
+                .unwrap();
+            }
+        }
+        db
+    }
+
+    /// A client for the group routes, and the `Authorization` header of a session for `USER`, a global `Owner`.
+    fn client(db: Database) -> (Client, Header<'static>) {
+        acl::grant_global_owner(&db.lock(), USER).unwrap();
+        let auth = testing::login(&db, USER);
+        (testing::client(db, routes()), auth)
+    }
+
+    #[test]
+    fn nested_groups() {
+        let db = database();
+        let conn = db.lock();
+        set_member(&conn, INNER_GROUP, USER, PrincipalKind::User, &key()).unwrap();
+        set_member(&conn, GROUP, INNER_GROUP, PrincipalKind::Group, &key()).unwrap();
+        set_member(&conn, OUTER_GROUP, GROUP, PrincipalKind::Group, &key()).unwrap();
+        acl::upsert_rows(
+            &conn,
+            VAULT,
+            &[AclRow {
+                subject: OUTER_GROUP,
+                action: AclAction::Read,
+                mode: AclMode::Allow,
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            acl::subjects(&*conn, USER).unwrap(),
+            [USER, INNER_GROUP, GROUP, OUTER_GROUP].into()
+        );
+        assert!(acl::is_allowed(&*conn, USER, VAULT, &AclAction::Read).unwrap());
+        assert!(!acl::is_allowed(&*conn, USER, VAULT, &AclAction::Write).unwrap());
+        assert!(!acl::is_allowed(&*conn, OTHER_USER, VAULT, &AclAction::Read).unwrap());
+
+        conn.execute(
+            "DELETE FROM group_members WHERE group_id = ?1 AND member_id = ?2",
+            [GROUP, INNER_GROUP],
+        )
+        .unwrap();
+        assert!(!acl::is_allowed(&*conn, USER, VAULT, &AclAction::Read).unwrap());
+    }
+
+    #[test]
+    fn membership_cycles() {
+        let (client, auth) = client(database());
+        let put = |group: Uuid, member: Uuid| {
+            client
+                .put(format!("/groups/{group}/members/{member}"))
+                .header(auth.clone())
+                .header(ContentType::JSON)
+                .body(rocket::serde::json::to_string(&key()).unwrap())
+                .dispatch()
+                .status()
+        };
+
+        assert_eq!(put(GROUP, GROUP), Status::BadRequest);
+        assert_eq!(put(OUTER_GROUP, GROUP), Status::NoContent);
+        assert_eq!(put(GROUP, OUTER_GROUP), Status::BadRequest);
+        assert_eq!(put(GROUP, INNER_GROUP), Status::NoContent);
+        assert_eq!(put(INNER_GROUP, OUTER_GROUP), Status::BadRequest);
+        assert_eq!(put(INNER_GROUP, VAULT), Status::NotFound);
+
+        let db = testing::served(&client);
+        let conn = db.lock();
+        assert_eq!(conn.groups(INNER_GROUP).unwrap(), [GROUP]);
+        assert_eq!(conn.groups(GROUP).unwrap(), [OUTER_GROUP]);
+        assert!(conn.groups(OUTER_GROUP).unwrap().is_empty());
+    }
+
+    #[test]
+    fn delete_group() {
+        let db = database();
+        {
+            let conn = db.lock();
+            set_member(&conn, OUTER_GROUP, GROUP, PrincipalKind::Group, &key()).unwrap();
+            let row = |subject| AclRow {
+                subject,
+                action: AclAction::Read,
+                mode: AclMode::Allow,
+            };
+            acl::upsert_rows(&conn, VAULT, &[row(GROUP), row(OTHER_USER)]).unwrap();
+            acl::upsert_rows(&conn, GROUP, &[row(OTHER_USER)]).unwrap();
+        }
+        let (client, auth) = client(db);
+
+        let status = client
+            .delete(format!("/groups/{GROUP}"))
+            .header(auth)
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::NoContent);
+
+        let db = testing::served(&client);
+        let conn = db.lock();
+        assert_eq!(conn.rows(VAULT).unwrap().len(), 1);
+        assert_eq!(conn.rows(VAULT).unwrap()[0].subject, OTHER_USER);
+        assert!(conn.rows(GROUP).unwrap().is_empty());
+        assert!(conn.groups(GROUP).unwrap().is_empty());
+    }
+}
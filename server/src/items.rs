@@ -0,0 +1,808 @@
+use common::{
+    data::Bytes,
+    error::ErrorCode,
+    http::api::{
+        acl::{AclAction, AclMode, AclRow},
+        item::{
+            ItemContents, ItemKeyInfo, ItemKeys, ItemMetadata, NewItemRequest, NewItemResponse,
+        },
+    },
+    item::VAULT_TYPE,
+};
+use rocket::{
+    delete, get,
+    http::{Header, Status},
+    post, put,
+    request::{FromRequest, Outcome},
+    routes,
+    serde::json::{self, Json},
+    Request, Responder, Route, State,
+};
+use rusqlite::{Connection, OptionalExtension, Row};
+use time::PrimitiveDateTime;
+use uuid::Uuid;
+
+use crate::{
+    acl::{self, AclSource},
+    db::Database,
+    error::{ApiError, ApiResult},
+    now,
+    sessions::Session,
+};
+
+pub struct ItemRecord {
+    pub content_type: String,
+    /// The key list, or `None` until the contents are first written.
+    pub keys: Option<ItemKeys>,
+    /// Incremented whenever the contents or the key list are replaced.
+    pub revision: i64,
+    pub ctime: PrimitiveDateTime,
+    pub mtime: PrimitiveDateTime,
+    pub atime: PrimitiveDateTime,
+}
+
+const ITEM_COLUMNS: &str = "content_type, keys, revision, ctime, mtime, atime";
+
+impl ItemRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            content_type: row.get(0)?,
+            keys: row
+                .get::<_, Option<String>>(1)?
+                .map(|keys| {
+                    json::from_str(&keys).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            1,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })
+                })
+                .transpose()?,
+            revision: row.get(2)?,
+            ctime: row.get(3)?,
+            mtime: row.get(4)?,
+            atime: row.get(5)?,
+        })
+    }
+
+    pub fn find_by_id(conn: &Connection, item_id: Uuid) -> rusqlite::Result<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT {ITEM_COLUMNS} FROM items WHERE item_id = ?1"),
+            [item_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    /// The `ETag` of the current contents and key list.
+    fn etag(&self) -> String {
+        format!("\"{}\"", self.revision)
+    }
+}
+
+fn keys_to_sql(keys: &ItemKeys) -> rusqlite::Result<String> {
+    json::to_string(keys).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn no_item(item_id: Uuid) -> ApiError {
+    ApiError::new(ErrorCode::NotFound, format!("no item {item_id}"))
+}
+
+fn no_contents(item_id: Uuid) -> ApiError {
+    ApiError::new(
+        ErrorCode::NotFound,
+        format!("item {item_id} has no contents yet"),
+    )
+}
+
+fn find_item(conn: &Connection, item_id: Uuid) -> ApiResult<ItemRecord> {
+    ItemRecord::find_by_id(conn, item_id)?.ok_or_else(|| no_item(item_id))
+}
+
+/// The `If-Match` header of a request, if it has one.
+pub struct IfMatch<'r>(Option<&'r str>);
+
+impl IfMatch<'_> {
+    /// Whether the precondition holds for an item whose current `ETag` is `etag`, or that has no contents yet if
+    ///  `etag` is `None`.
+    fn matches(&self, etag: Option<&str>) -> bool {
+        match (self.0, etag) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(header), Some(etag)) => header
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag == etag),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfMatch(req.headers().get_one("If-Match")))
+    }
+}
+
+/// A response that carries the `ETag` of an item.
+#[derive(Responder)]
+pub struct Tagged<R> {
+    inner: R,
+    etag: Header<'static>,
+}
+
+impl<R> Tagged<R> {
+    fn new(inner: R, etag: String) -> Self {
+        Self {
+            inner,
+            etag: Header::new("ETag", etag),
+        }
+    }
+}
+
+#[post("/items/new", data = "<req>")]
+fn new_item(
+    req: Json<NewItemRequest>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<NewItemResponse>> {
+    let NewItemRequest {
+        content_type,
+        base_acl,
+    } = req.into_inner();
+    acl::validate_rows(&base_acl)?;
+    let item_id = Uuid::new_v4();
+    let time = now();
+
+    let conn = db.lock();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO items (item_id, content_type, ctime, mtime, atime) VALUES (?1, ?2, ?3, ?3, ?3)",
+        rusqlite::params![item_id, content_type, time],
+    )?;
+    acl::insert_rows(&tx, item_id, &base_acl)?;
+    acl::insert_rows(
+        &tx,
+        item_id,
+        &[AclRow {
+            subject: session.user_id,
+            action: AclAction::Owner,
+            mode: AclMode::Allow,
+        }],
+    )?;
+    tx.commit()?;
+
+    Ok(Json(NewItemResponse { item_id }))
+}
+
+#[get("/items/<item_id>")]
+fn item(
+    item_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Tagged<Json<ItemContents>>> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Read)?;
+
+    let contents: Option<Vec<u8>> = conn.query_row(
+        "SELECT contents FROM items WHERE item_id = ?1",
+        [item_id],
+        |row| row.get(0),
+    )?;
+    let (Some(keys), Some(contents)) = (item.keys.clone(), contents) else {
+        return Err(no_contents(item_id));
+    };
+    conn.execute(
+        "UPDATE items SET atime = ?2 WHERE item_id = ?1",
+        rusqlite::params![item_id, now()],
+    )?;
+
+    Ok(Tagged::new(
+        Json(ItemContents {
+            keys,
+            contents: contents.into(),
+        }),
+        item.etag(),
+    ))
+}
+
+#[put("/items/<item_id>", data = "<body>")]
+fn put_item(
+    item_id: Uuid,
+    body: Json<ItemContents>,
+    if_match: IfMatch<'_>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Tagged<Status>> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Write)?;
+    if item.keys.as_ref().map(|keys| &keys.key_refs) != Some(&body.keys.key_refs) {
+        acl::require(&*conn, session.user_id, item_id, &AclAction::WriteKeys)?;
+    }
+    if !if_match.matches(item.keys.is_some().then(|| item.etag()).as_deref()) {
+        return Err(ApiError::new(
+            ErrorCode::Conflict,
+            format!("item {item_id} was changed concurrently"),
+        ));
+    }
+
+    let ItemContents { keys, contents } = body.into_inner();
+    conn.execute(
+        "UPDATE items SET keys = ?2, contents = ?3, revision = revision + 1, mtime = ?4 WHERE item_id = ?1",
+        rusqlite::params![item_id, keys_to_sql(&keys)?, contents.as_ref(), now()],
+    )?;
+    let saved = find_item(&conn, item_id)?;
+    Ok(Tagged::new(Status::NoContent, saved.etag()))
+}
+
+#[delete("/items/<item_id>")]
+fn delete_item(item_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Delete)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM item_keys WHERE item_id = ?1", [item_id])?;
+    tx.execute("DELETE FROM acl WHERE object_id = ?1", [item_id])?;
+    tx.execute(
+        "DELETE FROM acl_parents WHERE object_id = ?1 OR parent_id = ?1",
+        [item_id],
+    )?;
+    tx.execute("DELETE FROM items WHERE item_id = ?1", [item_id])?;
+    tx.commit()?;
+    Ok(Status::NoContent)
+}
+
+#[get("/items/<item_id>/metadata")]
+fn metadata(
+    item_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<ItemMetadata>> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Read)?;
+
+    Ok(Json(ItemMetadata {
+        content_type: item.content_type,
+        mtime: item.mtime,
+        atime: item.atime,
+        ctime: item.ctime,
+    }))
+}
+
+#[get("/items/<item_id>/keys")]
+fn keys(item_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Json<ItemKeys>> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Read)?;
+
+    item.keys.map(Json).ok_or_else(|| no_contents(item_id))
+}
+
+#[put("/items/<item_id>/keys", data = "<keys>")]
+fn put_keys(
+    item_id: Uuid,
+    keys: Json<ItemKeys>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::WriteKeys)?;
+
+    let current = item.keys.ok_or_else(|| no_contents(item_id))?;
+    if keys.base_cipher != current.base_cipher
+        || keys.item_iv != current.item_iv
+        || keys.item_auth_tag != current.item_auth_tag
+    {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!(
+                "only the key references of item {item_id} can be changed without its contents"
+            ),
+        ));
+    }
+
+    conn.execute(
+        "UPDATE items SET keys = ?2, revision = revision + 1 WHERE item_id = ?1",
+        rusqlite::params![item_id, keys_to_sql(&keys)?],
+    )?;
+    Ok(Status::NoContent)
+}
+
+#[delete("/items/<item_id>/keys")]
+fn delete_keys(item_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Status> {
+    let conn = db.lock();
+    let item = find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::DeleteKeys)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM item_keys WHERE item_id = ?1", [item_id])?;
+    if let Some(mut keys) = item.keys {
+        keys.key_refs.clear();
+        tx.execute(
+            "UPDATE items SET keys = ?2, revision = revision + 1 WHERE item_id = ?1",
+            rusqlite::params![item_id, keys_to_sql(&keys)?],
+        )?;
+    }
+    tx.commit()?;
+    Ok(Status::NoContent)
+}
+
+#[get("/items/<item_id>/keys/<key_id>")]
+fn key_info(
+    item_id: Uuid,
+    key_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<ItemKeyInfo>> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Read)?;
+
+    conn.query_row(
+        "SELECT secured_item_key, item_key_iv, item_auth_tag FROM item_keys
+            WHERE item_id = ?1 AND key_id = ?2",
+        rusqlite::params![item_id, key_id],
+        |row| {
+            Ok(ItemKeyInfo {
+                secured_item_key: row.get::<_, Vec<u8>>(0)?.into(),
+                item_key_iv: row.get::<_, Vec<u8>>(1)?.into(),
+                item_auth_tag: row.get::<_, Option<Vec<u8>>>(2)?.map(Bytes::from),
+            })
+        },
+    )
+    .optional()?
+    .map(Json)
+    .ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::NotFound,
+            format!("item {item_id} has no key {key_id}"),
+        )
+    })
+}
+
+#[put("/items/<item_id>/keys/<key_id>", data = "<info>")]
+fn put_key_info(
+    item_id: Uuid,
+    key_id: Uuid,
+    info: Json<ItemKeyInfo>,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::WriteKeys)?;
+
+    conn.execute(
+        "INSERT INTO item_keys (item_id, key_id, secured_item_key, item_key_iv, item_auth_tag)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (item_id, key_id) DO UPDATE SET
+                secured_item_key = excluded.secured_item_key,
+                item_key_iv = excluded.item_key_iv,
+                item_auth_tag = excluded.item_auth_tag",
+        rusqlite::params![
+            item_id,
+            key_id,
+            info.secured_item_key.as_ref(),
+            info.item_key_iv.as_ref(),
+            info.item_auth_tag.as_ref().map(|tag| tag.as_ref()),
+        ],
+    )?;
+    Ok(Status::NoContent)
+}
+
+#[delete("/items/<item_id>/keys/<key_id>")]
+fn delete_key_info(
+    item_id: Uuid,
+    key_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::DeleteKeys)?;
+
+    let rows = conn.execute(
+        "DELETE FROM item_keys WHERE item_id = ?1 AND key_id = ?2",
+        rusqlite::params![item_id, key_id],
+    )?;
+    if rows == 0 {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("item {item_id} has no key {key_id}"),
+        ));
+    }
+    Ok(Status::NoContent)
+}
+
+#[get("/items/<item_id>/parents")]
+fn parents(item_id: Uuid, session: Session, db: &State<Database>) -> ApiResult<Json<Vec<Uuid>>> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::Read)?;
+
+    Ok(Json(conn.parents(item_id)?))
+}
+
+#[put("/items/<item_id>/parents/<vault_id>")]
+fn put_parent(
+    item_id: Uuid,
+    vault_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    let vault = find_item(&conn, vault_id)?;
+    // The item gains the permissions of the vault, which changes its effective ACL
+    acl::require(&*conn, session.user_id, vault_id, &AclAction::Write)?;
+    acl::require(&*conn, session.user_id, item_id, &AclAction::WriteAcl)?;
+
+    if vault.content_type.split('+').next() != Some(VAULT_TYPE) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("item {vault_id} is not a vault"),
+        ));
+    }
+    if item_id == vault_id {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "a vault cannot contain itself",
+        ));
+    }
+
+    acl::add_parent(&conn, item_id, vault_id)?;
+    Ok(Status::NoContent)
+}
+
+#[delete("/items/<item_id>/parents/<vault_id>")]
+fn delete_parent(
+    item_id: Uuid,
+    vault_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    let conn = db.lock();
+    find_item(&conn, item_id)?;
+    if !acl::is_allowed(&*conn, session.user_id, vault_id, &AclAction::Write)? {
+        acl::require(&*conn, session.user_id, item_id, &AclAction::WriteAcl)?;
+    }
+
+    if !acl::remove_parent(&conn, item_id, vault_id)? {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("item {item_id} is not in vault {vault_id}"),
+        ));
+    }
+    Ok(Status::NoContent)
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        new_item,
+        item,
+        put_item,
+        delete_item,
+        metadata,
+        keys,
+        put_keys,
+        delete_keys,
+        key_info,
+        put_key_info,
+        delete_key_info,
+        parents,
+        put_parent,
+        delete_parent
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use common::{
+        http::api::{
+            acl::{AclAction, AclMode, AclRow},
+            item::{ItemContents, ItemKeyInfo, ItemKeys, NewItemRequest, NewItemResponse},
+        },
+        item::VAULT_TYPE,
+        suite::SymmetricCipherAlgorithm,
+    };
+    use rocket::{
+        http::{ContentType, Header, Status},
+        local::blocking::Client,
+        serde::json,
+    };
+    use uuid::Uuid;
+
+    use super::{routes, IfMatch};
+    use crate::{acl::AclSource, testing};
+
+    const USER: Uuid = Uuid::from_u128(1);
+    const OTHER_USER: Uuid = Uuid::from_u128(2);
+    const KEY: Uuid = Uuid::from_u128(30);
+    const OTHER_KEY: Uuid = Uuid::from_u128(31);
+
+    /// A client for the item routes, and sessions for `USER` and `OTHER_USER`.
+    fn client() -> (Client, Header<'static>, Header<'static>) {
+        let db = testing::database();
+        let user = testing::login(&db, USER);
+        let other_user = testing::login(&db, OTHER_USER);
+        (testing::client(db, routes()), user, other_user)
+    }
+
+    /// Creates an item owned by the session `auth`, with the extra `base_acl`.
+    fn new_item(
+        client: &Client,
+        auth: &Header<'static>,
+        content_type: &str,
+        base_acl: Vec<AclRow>,
+    ) -> Uuid {
+        let res = client
+            .post("/items/new")
+            .header(auth.clone())
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&NewItemRequest {
+                    content_type: content_type.into(),
+                    base_acl,
+                })
+                .unwrap(),
+            )
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        res.into_json::<NewItemResponse>().unwrap().item_id
+    }
+
+    fn keys(key_refs: &[Uuid]) -> ItemKeys {
+        ItemKeys {
+            base_cipher: SymmetricCipherAlgorithm::Aes256Gcm,
+            key_refs: key_refs.to_vec(),
+            item_iv: vec![1; 12].into(),
+            item_auth_tag: Some(vec![2; 16].into()),
+        }
+    }
+
+    /// `PUT /items/<item>`, returning the status and the new `ETag`.
+    fn put(
+        client: &Client,
+        auth: &Header<'static>,
+        item: Uuid,
+        contents: &ItemContents,
+        if_match: Option<&str>,
+    ) -> (Status, Option<String>) {
+        let mut req = client
+            .put(format!("/items/{item}"))
+            .header(auth.clone())
+            .header(ContentType::JSON)
+            .body(json::to_string(contents).unwrap());
+        if let Some(if_match) = if_match {
+            req = req.header(Header::new("If-Match", if_match.to_string()));
+        }
+        let res = req.dispatch();
+        let etag = res.headers().get_one("ETag").map(str::to_string);
+        (res.status(), etag)
+    }
+
+    fn put_keys(client: &Client, auth: &Header<'static>, item: Uuid, keys: &ItemKeys) -> Status {
+        client
+            .put(format!("/items/{item}/keys"))
+            .header(auth.clone())
+            .header(ContentType::JSON)
+            .body(json::to_string(keys).unwrap())
+            .dispatch()
+            .status()
+    }
+
+    fn put_parent(client: &Client, auth: &Header<'static>, item: Uuid, vault: Uuid) -> Status {
+        client
+            .put(format!("/items/{item}/parents/{vault}"))
+            .header(auth.clone())
+            .dispatch()
+            .status()
+    }
+
+    fn contents(key_refs: &[Uuid], contents: &[u8]) -> ItemContents {
+        ItemContents {
+            keys: keys(key_refs),
+            contents: contents.to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn put_item_preconditions() {
+        let (client, user, other_user) = client();
+        let item = new_item(
+            &client,
+            &user,
+            "text/plain",
+            vec![AclRow {
+                subject: OTHER_USER,
+                action: AclAction::Write,
+                mode: AclMode::Allow,
+            }],
+        );
+
+        // There is nothing to match before the first write
+        let first = contents(&[KEY], b"first");
+        assert_eq!(
+            put(&client, &user, item, &first, Some("*")).0,
+            Status::Conflict
+        );
+        let (status, etag) = put(&client, &user, item, &first, None);
+        assert_eq!(status, Status::NoContent);
+        let etag = etag.unwrap();
+
+        let second = contents(&[KEY], b"second");
+        let (status, current) = put(&client, &user, item, &second, Some(&etag));
+        assert_eq!(status, Status::NoContent);
+        let current = current.unwrap();
+        assert_ne!(current, etag);
+        assert_eq!(
+            put(&client, &user, item, &first, Some(&etag)).0,
+            Status::Conflict
+        );
+
+        let res = client
+            .get(format!("/items/{item}"))
+            .header(user.clone())
+            .dispatch();
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.headers().get_one("ETag"), Some(current.as_str()));
+        assert_eq!(res.into_json::<ItemContents>().unwrap(), second);
+
+        // Write is enough to replace the contents, but not to change who the item is shared with
+        let shared = contents(&[KEY, OTHER_KEY], b"shared");
+        assert_eq!(
+            put(&client, &other_user, item, &shared, Some(&current)).0,
+            Status::Forbidden
+        );
+        let (status, current) = put(&client, &other_user, item, &first, Some(&current));
+        assert_eq!(status, Status::NoContent);
+        assert_eq!(
+            put(&client, &user, item, &shared, current.as_deref()).0,
+            Status::NoContent
+        );
+    }
+
+    #[test]
+    fn put_keys_changes_only_key_refs() {
+        let (client, user, _) = client();
+        let item = new_item(&client, &user, "text/plain", Vec::new());
+        assert_eq!(
+            put_keys(&client, &user, item, &keys(&[KEY])),
+            Status::NotFound
+        );
+        let (_, etag) = put(&client, &user, item, &contents(&[KEY], b"item"), None);
+
+        let mut changed = keys(&[KEY]);
+        changed.base_cipher = SymmetricCipherAlgorithm::Chacha20;
+        assert_eq!(put_keys(&client, &user, item, &changed), Status::BadRequest);
+        let mut changed = keys(&[KEY]);
+        changed.item_iv = vec![3; 12].into();
+        assert_eq!(put_keys(&client, &user, item, &changed), Status::BadRequest);
+
+        let shared = keys(&[KEY, OTHER_KEY]);
+        assert_eq!(put_keys(&client, &user, item, &shared), Status::NoContent);
+        let res = client
+            .get(format!("/items/{item}"))
+            .header(user.clone())
+            .dispatch();
+        assert_ne!(res.headers().get_one("ETag"), etag.as_deref());
+        let saved = res.into_json::<ItemContents>().unwrap();
+        assert_eq!(saved.keys, shared);
+        assert_eq!(saved.contents.as_ref(), b"item");
+    }
+
+    #[test]
+    fn delete_item_removes_rows() {
+        let (client, user, _) = client();
+        let vault = new_item(&client, &user, VAULT_TYPE, Vec::new());
+        let item = new_item(&client, &user, "text/plain", Vec::new());
+        let child = new_item(&client, &user, "text/plain", Vec::new());
+        put(&client, &user, item, &contents(&[KEY], b"item"), None);
+        let status = client
+            .put(format!("/items/{item}/keys/{KEY}"))
+            .header(user.clone())
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&ItemKeyInfo {
+                    secured_item_key: vec![4; 32].into(),
+                    item_key_iv: vec![5; 12].into(),
+                    item_auth_tag: None,
+                })
+                .unwrap(),
+            )
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::NoContent);
+        assert_eq!(put_parent(&client, &user, item, vault), Status::NoContent);
+        // Rows that name the deleted item as a parent go as well, whatever its type
+        testing::served(&client)
+            .lock()
+            .execute(
+                "INSERT INTO acl_parents (object_id, parent_id) VALUES (?1, ?2)",
+                [child, item],
+            )
+            .unwrap();
+
+        let status = client
+            .delete(format!("/items/{item}"))
+            .header(user.clone())
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::NoContent);
+        let status = client
+            .get(format!("/items/{item}/metadata"))
+            .header(user.clone())
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::NotFound);
+
+        let conn = testing::served(&client).lock();
+        let count = |sql: &str| -> usize { conn.query_row(sql, [item], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM items WHERE item_id = ?1"), 0);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM item_keys WHERE item_id = ?1"),
+            0
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM acl WHERE object_id = ?1"), 0);
+        assert_eq!(
+            count("SELECT COUNT(*) FROM acl_parents WHERE object_id = ?1 OR parent_id = ?1"),
+            0
+        );
+        assert!(conn.parents(child).unwrap().is_empty());
+        assert_eq!(conn.rows(vault).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn put_parent_requires_a_vault() {
+        let (client, user, other_user) = client();
+        let vault = new_item(&client, &user, VAULT_TYPE, Vec::new());
+        let typed_vault = new_item(&client, &user, &format!("{VAULT_TYPE}+json"), Vec::new());
+        let item = new_item(&client, &user, "text/plain", Vec::new());
+        let other = new_item(&client, &user, "text/plain", Vec::new());
+
+        assert_eq!(put_parent(&client, &user, item, other), Status::BadRequest);
+        assert_eq!(put_parent(&client, &user, vault, vault), Status::BadRequest);
+        assert_eq!(
+            put_parent(&client, &user, item, Uuid::from_u128(99)),
+            Status::NotFound
+        );
+        assert_eq!(
+            put_parent(&client, &other_user, item, vault),
+            Status::Forbidden
+        );
+        assert_eq!(put_parent(&client, &user, item, vault), Status::NoContent);
+        assert_eq!(
+            put_parent(&client, &user, item, typed_vault),
+            Status::NoContent
+        );
+
+        let res = client
+            .get(format!("/items/{item}/parents"))
+            .header(user.clone())
+            .dispatch();
+        let mut parents = res.into_json::<Vec<Uuid>>().unwrap();
+        parents.sort();
+        let mut expected = vec![vault, typed_vault];
+        expected.sort();
+        assert_eq!(parents, expected);
+    }
+
+    #[test]
+    fn if_match() {
+        assert!(IfMatch(None).matches(None));
+        assert!(IfMatch(None).matches(Some("\"1\"")));
+        assert!(IfMatch(Some("\"1\"")).matches(Some("\"1\"")));
+        assert!(IfMatch(Some("\"0\", \"1\"")).matches(Some("\"1\"")));
+        assert!(IfMatch(Some("*")).matches(Some("\"1\"")));
+        assert!(!IfMatch(Some("\"0\"")).matches(Some("\"1\"")));
+        assert!(!IfMatch(Some("*")).matches(None));
+    }
+}
@@ -1,5 +1,138 @@
+use std::path::PathBuf;
+
+use common::{
+    http::api::{Hello, PROTOCOL_ID_PASSMAN},
+    suite::DigestAlgorithm,
+};
+use rocket::{
+    catchers,
+    fairing::{self, AdHoc},
+    get, launch, routes,
+    serde::json::Json,
+    Build, Rocket, State,
+};
+use serde::Deserialize;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use uuid::Uuid;
+
+use auth::PendingChallenges;
+use db::Database;
+
+mod acl;
+mod auth;
+mod db;
+mod error;
+mod groups;
+mod items;
+mod sessions;
+#[cfg(test)]
+mod testing;
 mod users;
 
-fn main() {
-    println!("Hello, world!");
+/// Passman specific configuration, read from the `Rocket.toml` or `ROCKET_*` environment variables.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ServerConfig {
+    /// Path to the SQLite database file.
+    #[serde(default = "ServerConfig::default_database")]
+    pub database: PathBuf,
+    /// Digest used to hash user addresses before they are stored.
+    #[serde(default = "ServerConfig::default_address_digest")]
+    pub address_digest: DigestAlgorithm,
+    /// How long a session token remains valid after it is issued or refreshed, in seconds.
+    #[serde(default = "ServerConfig::default_session_lifetime")]
+    pub session_lifetime: u64,
+    /// Address of the user that is made a global `Owner`, when it registers or at startup if it already exists.
+    ///  It is compared after [`users::normalize_address`]. If unset, the first user to register is made a global
+    ///  `Owner` instead.
+    #[serde(default)]
+    pub admin: Option<String>,
+}
+
+impl ServerConfig {
+    fn default_database() -> PathBuf {
+        PathBuf::from("passman.db")
+    }
+
+    fn default_address_digest() -> DigestAlgorithm {
+        DigestAlgorithm::Sha256
+    }
+
+    fn default_session_lifetime() -> u64 {
+        12 * 60 * 60
+    }
+
+    pub fn session_lifetime(&self) -> time::Duration {
+        time::Duration::seconds(self.session_lifetime.try_into().unwrap_or(i64::MAX))
+    }
+}
+
+/// The identity of the server, loaded once at startup.
+pub struct ServerIdentity {
+    pub server_id: Uuid,
+}
+
+pub fn now() -> PrimitiveDateTime {
+    let now = OffsetDateTime::now_utc();
+    PrimitiveDateTime::new(now.date(), now.time())
+}
+
+#[get("/hello")]
+fn hello(identity: &State<ServerIdentity>) -> Json<Hello> {
+    Json(Hello {
+        server_id: identity.server_id,
+        protocol_id: PROTOCOL_ID_PASSMAN,
+        hello_time: now(),
+    })
+}
+
+async fn init_database(rocket: Rocket<Build>) -> fairing::Result {
+    let config = match rocket.state::<ServerConfig>() {
+        Some(config) => config,
+        None => return Err(rocket),
+    };
+
+    let db = match Database::open(&config.database) {
+        Ok(db) => db,
+        Err(e) => {
+            rocket::error!(
+                "Failed to open database {}: {}",
+                config.database.display(),
+                e
+            );
+            return Err(rocket);
+        }
+    };
+
+    let server_id = match db.server_id() {
+        Ok(id) => id,
+        Err(e) => {
+            rocket::error!("Failed to load server identity: {}", e);
+            return Err(rocket);
+        }
+    };
+
+    if let Some(admin) = &config.admin {
+        if let Err(e) = users::bootstrap_admin(&db.lock(), config, admin) {
+            rocket::error!("Failed to grant global permissions to {}: {}", admin, e.0);
+            return Err(rocket);
+        }
+    }
+
+    Ok(rocket.manage(db).manage(ServerIdentity { server_id }))
+}
+
+#[launch]
+fn rocket() -> _ {
+    rocket::build()
+        .attach(AdHoc::config::<ServerConfig>())
+        .attach(AdHoc::try_on_ignite("Database", init_database))
+        .mount("/", routes![hello])
+        .register("/", catchers![error::default_catcher])
+        .manage(PendingChallenges::default())
+        .mount("/", users::routes())
+        .mount("/", auth::routes())
+        .mount("/", sessions::routes())
+        .mount("/", acl::routes())
+        .mount("/", groups::routes())
+        .mount("/", items::routes())
 }
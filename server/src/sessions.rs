@@ -0,0 +1,345 @@
+use client_sdk::cipher::digest::DefaultDigest;
+use common::{
+    data::Bytes,
+    error::ErrorCode,
+    http::api::auth::{AuthSession, SessionInfo},
+    suite::DigestAlgorithm,
+};
+use rand::{rngs::OsRng, RngCore};
+use rocket::{
+    delete, get,
+    http::Status,
+    post,
+    request::{FromRequest, Outcome},
+    routes,
+    serde::json::Json,
+    Request, Route, State,
+};
+use rusqlite::{Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    error::{ApiError, ApiResult},
+    now,
+    users::other_user,
+    ServerConfig,
+};
+
+const SESSION_TOKEN_LEN: usize = 32;
+
+/// The raw token from an `Authorization: Bearer <token>` header.
+pub struct BearerToken<'r>(pub &'r str);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BearerToken<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|v| v.strip_prefix("Bearer "))
+        {
+            Some(token) => Outcome::Success(BearerToken(token.trim())),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// A request made with a valid, unexpired session token.
+///
+/// Using the session as a request guard records the time it was last used.
+pub struct Session {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Session {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = match req.guard::<BearerToken<'r>>().await {
+            Outcome::Success(token) => token,
+            Outcome::Error(e) => return Outcome::Error(e),
+            Outcome::Forward(f) => return Outcome::Forward(f),
+        };
+        let Some(token) = Bytes::from_base64(token.0) else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+        let db = match req.guard::<&State<Database>>().await {
+            Outcome::Success(db) => db,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+
+        match find_session(&db.lock(), &token) {
+            Ok(Some(session)) => Outcome::Success(session),
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(_) => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+fn hash_session_token(token: &[u8]) -> Vec<u8> {
+    DefaultDigest::digest(DigestAlgorithm::Sha256, token)
+        .expect("sha256 is always supported")
+        .into_inner()
+}
+
+fn generate_session_token() -> Vec<u8> {
+    let mut token = vec![0u8; SESSION_TOKEN_LEN];
+    OsRng.fill_bytes(&mut token);
+    token
+}
+
+/// Looks up the unexpired session for `token`, and marks it as used.
+fn find_session(conn: &Connection, token: &[u8]) -> rusqlite::Result<Option<Session>> {
+    let time = now();
+    let session = conn
+        .query_row(
+            "SELECT session_id, user_id FROM sessions WHERE token_hash = ?1 AND expires_at > ?2",
+            rusqlite::params![hash_session_token(token), time],
+            |row| {
+                Ok(Session {
+                    session_id: row.get(0)?,
+                    user_id: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+
+    if let Some(session) = &session {
+        conn.execute(
+            "UPDATE sessions SET last_used_at = ?2 WHERE session_id = ?1",
+            rusqlite::params![session.session_id, time],
+        )?;
+    }
+
+    Ok(session)
+}
+
+/// Issues a new session for `user_id`, valid for `lifetime`.
+pub fn create_session(
+    conn: &Connection,
+    user_id: Uuid,
+    lifetime: time::Duration,
+) -> rusqlite::Result<AuthSession> {
+    let token = generate_session_token();
+    let session_id = Uuid::new_v4();
+    let created = now();
+    let expires = created + lifetime;
+
+    conn.execute(
+        "DELETE FROM sessions WHERE expires_at <= ?1",
+        rusqlite::params![created],
+    )?;
+    conn.execute(
+        "INSERT INTO sessions (session_id, user_id, token_hash, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![session_id, user_id, hash_session_token(&token), created, expires],
+    )?;
+
+    Ok(AuthSession {
+        session_id,
+        session_token: Bytes::new(token),
+        expires,
+    })
+}
+
+#[post("/auth/session/refresh")]
+fn refresh_session(
+    session: Session,
+    db: &State<Database>,
+    config: &State<ServerConfig>,
+) -> ApiResult<Json<AuthSession>> {
+    let token = generate_session_token();
+    let expires = now() + config.session_lifetime();
+
+    db.lock().execute(
+        "UPDATE sessions SET token_hash = ?2, expires_at = ?3 WHERE session_id = ?1",
+        rusqlite::params![session.session_id, hash_session_token(&token), expires],
+    )?;
+
+    Ok(Json(AuthSession {
+        session_id: session.session_id,
+        session_token: Bytes::new(token),
+        expires,
+    }))
+}
+
+#[delete("/auth/session")]
+fn end_session(session: Session, db: &State<Database>) -> ApiResult<Status> {
+    db.lock().execute(
+        "DELETE FROM sessions WHERE session_id = ?1",
+        [session.session_id],
+    )?;
+    Ok(Status::NoContent)
+}
+
+#[get("/users/<user_id>/sessions")]
+fn list_sessions(
+    user_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<Vec<SessionInfo>>> {
+    if session.user_id != user_id {
+        return Err(other_user());
+    }
+
+    let conn = db.lock();
+    let mut stmt = conn.prepare(
+        "SELECT session_id, created_at, last_used_at, expires_at FROM sessions
+            WHERE user_id = ?1 AND expires_at > ?2 ORDER BY created_at",
+    )?;
+    let sessions = stmt
+        .query_map(rusqlite::params![user_id, now()], |row| {
+            let session_id = row.get(0)?;
+            Ok(SessionInfo {
+                session_id,
+                created: row.get(1)?,
+                last_used: row.get(2)?,
+                expires: row.get(3)?,
+                current: session_id == session.session_id,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())?;
+
+    Ok(Json(sessions))
+}
+
+#[delete("/users/<user_id>/sessions/<session_id>")]
+fn revoke_session(
+    user_id: Uuid,
+    session_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Status> {
+    if session.user_id != user_id {
+        return Err(other_user());
+    }
+
+    let rows = db.lock().execute(
+        "DELETE FROM sessions WHERE session_id = ?1 AND user_id = ?2",
+        rusqlite::params![session_id, user_id],
+    )?;
+
+    if rows == 0 {
+        Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no session {session_id}"),
+        ))
+    } else {
+        Ok(Status::NoContent)
+    }
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![refresh_session, end_session, list_sessions, revoke_session]
+}
+
+#[cfg(test)]
+mod test {
+    use rocket::{http::Header, local::blocking::Client};
+
+    use super::*;
+    use crate::testing;
+
+    const USER: Uuid = Uuid::from_u128(1);
+    const OTHER_USER: Uuid = Uuid::from_u128(2);
+
+    fn sessions(client: &Client, user: Uuid, auth: &Header<'static>) -> Option<Vec<SessionInfo>> {
+        let response = client
+            .get(format!("/users/{user}/sessions"))
+            .header(auth.clone())
+            .dispatch();
+        (response.status() == Status::Ok).then(|| response.into_json().unwrap())
+    }
+
+    fn revoke(client: &Client, user: Uuid, session: Uuid, auth: &Header<'static>) -> Status {
+        client
+            .delete(format!("/users/{user}/sessions/{session}"))
+            .header(auth.clone())
+            .dispatch()
+            .status()
+    }
+
+    #[test]
+    fn refresh_replaces_the_token() {
+        let db = testing::database();
+        let old = testing::login(&db, USER);
+        let client = testing::client(db, routes());
+        let session_id = sessions(&client, USER, &old).unwrap()[0].session_id;
+
+        let response = client
+            .post("/auth/session/refresh")
+            .header(old.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let refreshed: AuthSession = response.into_json().unwrap();
+        assert_eq!(refreshed.session_id, session_id);
+        let new = Header::new(
+            "Authorization",
+            format!("Bearer {}", refreshed.session_token.to_base64()),
+        );
+
+        assert_eq!(sessions(&client, USER, &old), None);
+        let listed = sessions(&client, USER, &new).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].session_id, session_id);
+        assert!(listed[0].current);
+        assert_eq!(listed[0].expires, refreshed.expires);
+    }
+
+    #[test]
+    fn logout_revokes_the_session() {
+        let db = testing::database();
+        let (auth, other) = (testing::login(&db, USER), testing::login(&db, USER));
+        let client = testing::client(db, routes());
+
+        let response = client
+            .delete("/auth/session")
+            .header(auth.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+        assert_eq!(sessions(&client, USER, &auth), None);
+        let response = client
+            .delete("/auth/session")
+            .header(auth.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let listed = sessions(&client, USER, &other).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].current);
+    }
+
+    #[test]
+    fn sessions_of_other_users() {
+        let db = testing::database();
+        let (auth, second) = (testing::login(&db, USER), testing::login(&db, USER));
+        let other = testing::login(&db, OTHER_USER);
+        let client = testing::client(db, routes());
+        let listed = sessions(&client, USER, &auth).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed.iter().filter(|session| session.current).count(), 1);
+        let second_id = listed.iter().find(|s| !s.current).unwrap().session_id;
+        let other_id = sessions(&client, OTHER_USER, &other).unwrap()[0].session_id;
+
+        let response = client
+            .get(format!("/users/{OTHER_USER}/sessions"))
+            .header(auth.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        assert_eq!(
+            revoke(&client, OTHER_USER, other_id, &auth),
+            Status::Forbidden
+        );
+        assert_eq!(revoke(&client, USER, other_id, &auth), Status::NotFound);
+        assert!(sessions(&client, OTHER_USER, &other).is_some());
+
+        assert_eq!(revoke(&client, USER, second_id, &auth), Status::NoContent);
+        assert_eq!(sessions(&client, USER, &second), None);
+        assert_eq!(revoke(&client, USER, second_id, &auth), Status::NotFound);
+        assert_eq!(sessions(&client, USER, &auth).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+//! Helpers for the route tests.
+
+use common::suite::DigestAlgorithm;
+use rocket::{catchers, http::Header, local::blocking::Client, Route};
+use uuid::Uuid;
+
+use crate::{
+    auth::PendingChallenges, db::Database, error::default_catcher, sessions::create_session,
+    ServerConfig,
+};
+
+/// An empty database with every migration applied.
+pub fn database() -> Database {
+    Database::open(":memory:").unwrap()
+}
+
+/// The configuration the route tests run with, without an `admin`.
+pub fn config() -> ServerConfig {
+    ServerConfig {
+        database: ":memory:".into(),
+        address_digest: DigestAlgorithm::Sha256,
+        session_lifetime: 60 * 60,
+        admin: None,
+    }
+}
+
+/// The `Authorization` header of a new session for `user`.
+pub fn login(db: &Database, user: Uuid) -> Header<'static> {
+    let token = create_session(&db.lock(), user, time::Duration::hours(1))
+        .unwrap()
+        .session_token
+        .to_base64();
+    Header::new("Authorization", format!("Bearer {token}"))
+}
+
+/// A client for `routes`, served from `db` with the test [`config`].
+pub fn client(db: Database, routes: Vec<Route>) -> Client {
+    client_with(db, config(), routes)
+}
+
+/// Like [`client`], with `config` instead of the test configuration.
+pub fn client_with(db: Database, config: ServerConfig, routes: Vec<Route>) -> Client {
+    Client::tracked(
+        rocket::build()
+            .manage(db)
+            .manage(config)
+            .manage(PendingChallenges::default())
+            .mount("/", routes)
+            .register("/", catchers![default_catcher]),
+    )
+    .unwrap()
+}
+
+/// The database served by `client`.
+pub fn served(client: &Client) -> &Database {
+    client.rocket().state::<Database>().unwrap()
+}
@@ -1,13 +1,520 @@
-use common::suite::{AsymmetricCipherAlgorithm, DigestAlgorithm};
+use client_sdk::cipher::{self, digest::DefaultDigest};
+use common::{
+    data::Bytes,
+    error::ErrorCode,
+    http::api::{
+        acl::AclAction,
+        auth::{KdfParams, UserAuth},
+        user::{NewUserRequest, NewUserResponse, UserPublicKey, UserRootInfo},
+    },
+    suite::{AsymmetricCipherAlgorithm, DigestAlgorithm, SymmetricCipherAlgorithm},
+};
+use rocket::{
+    get, post, routes,
+    serde::json::{self, Json},
+    Route, State,
+};
+use rusqlite::{
+    types::{FromSql, ValueRef},
+    Connection, OptionalExtension, Row,
+};
 use uuid::Uuid;
 
+use crate::{
+    acl,
+    db::Database,
+    error::{ApiError, ApiResult},
+    sessions::Session,
+    ServerConfig,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UserRecord {
-    userid: Uuid,
-    address_digest_algorithm: DigestAlgorithm,
-    address_hash: Vec<u8>,
-    key_pair_algorithm: AsymmetricCipherAlgorithm,
-    pubkey: Vec<u8>,
-    sealed_priv_key: Vec<u8>,
-    root_key_id: Uuid,
-    root_object_id: Uuid,
+    pub userid: Uuid,
+    pub address_digest_algorithm: DigestAlgorithm,
+    pub address_hash: Vec<u8>,
+    pub key_pair_algorithm: AsymmetricCipherAlgorithm,
+    pub pubkey: Vec<u8>,
+    pub kdf_base_digest_algorithm: DigestAlgorithm,
+    pub kdf_params: Option<KdfParams>,
+    pub priv_key_cipher: Option<SymmetricCipherAlgorithm>,
+    pub priv_key_iv: Vec<u8>,
+    pub sealed_priv_key: Vec<u8>,
+    pub root_key_id: Uuid,
+    pub root_object_id: Uuid,
+}
+
+const USER_COLUMNS: &str = "user_id, address_digest_algorithm, address_hash, key_pair_algorithm, pubkey, kdf_base_digest_algorithm, priv_key_iv, sealed_priv_key, root_key_id, root_object_id, kdf_params, priv_key_cipher";
+
+pub(crate) fn get_parsed<T: core::str::FromStr>(row: &Row, idx: usize) -> rusqlite::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let name = String::column_result(row.get_ref(idx)?)?;
+    name.parse().map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            idx,
+            rusqlite::types::Type::Text,
+            Box::new(e) as Box<dyn std::error::Error + Send + Sync>,
+        )
+    })
+}
+
+/// Like [`get_parsed`], for columns that may be `NULL`.
+pub(crate) fn get_parsed_opt<T: core::str::FromStr>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match row.get_ref(idx)? {
+        ValueRef::Null => Ok(None),
+        _ => get_parsed(row, idx).map(Some),
+    }
+}
+
+impl UserRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            userid: row.get(0)?,
+            address_digest_algorithm: get_parsed(row, 1)?,
+            address_hash: row.get(2)?,
+            key_pair_algorithm: get_parsed(row, 3)?,
+            pubkey: row.get(4)?,
+            kdf_base_digest_algorithm: get_parsed(row, 5)?,
+            priv_key_iv: row.get(6)?,
+            sealed_priv_key: row.get(7)?,
+            root_key_id: row.get(8)?,
+            root_object_id: row.get(9)?,
+            kdf_params: row
+                .get::<_, Option<String>>(10)?
+                .map(|params| {
+                    json::from_str(&params).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(
+                            10,
+                            rusqlite::types::Type::Text,
+                            Box::new(e),
+                        )
+                    })
+                })
+                .transpose()?,
+            priv_key_cipher: get_parsed_opt(row, 11)?,
+        })
+    }
+
+    /// Stores a new user. Fails if a user with the same ID or address hash already exists.
+    pub fn insert(&self, conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute(
+            &format!(
+                "INSERT INTO users ({USER_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+            ),
+            rusqlite::params![
+                self.userid,
+                self.address_digest_algorithm.name(),
+                self.address_hash,
+                self.key_pair_algorithm.name(),
+                self.pubkey,
+                self.kdf_base_digest_algorithm.name(),
+                self.priv_key_iv,
+                self.sealed_priv_key,
+                self.root_key_id,
+                self.root_object_id,
+                self.kdf_params
+                    .as_ref()
+                    .map(json::to_string)
+                    .transpose()
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                self.priv_key_cipher.map(SymmetricCipherAlgorithm::name),
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn find_by_id(conn: &Connection, userid: Uuid) -> rusqlite::Result<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT {USER_COLUMNS} FROM users WHERE user_id = ?1"),
+            [userid],
+            Self::from_row,
+        )
+        .optional()
+    }
+
+    pub fn find_by_address_hash(
+        conn: &Connection,
+        alg: DigestAlgorithm,
+        address_hash: &[u8],
+    ) -> rusqlite::Result<Option<Self>> {
+        conn.query_row(
+            &format!(
+                "SELECT {USER_COLUMNS} FROM users WHERE address_digest_algorithm = ?1 AND address_hash = ?2"
+            ),
+            rusqlite::params![alg.name(), address_hash],
+            Self::from_row,
+        )
+        .optional()
+    }
+}
+
+/// The form of a user address that is hashed: without surrounding whitespace and in lower case, so that
+///  ` Alice@Example.com` and `alice@example.com` are the same user.
+pub fn normalize_address(address: &str) -> String {
+    address.trim().to_lowercase()
+}
+
+/// Hashes a user address, after [`normalize_address`], so that the server never stores it in the clear.
+pub fn hash_address(alg: DigestAlgorithm, address: &str) -> cipher::Result<Vec<u8>> {
+    DefaultDigest::digest(alg, normalize_address(address).as_bytes()).map(Bytes::into_inner)
+}
+
+/// Makes the user registered with `address` a global `Owner`, if it exists and has no global `Owner` row yet.
+pub fn bootstrap_admin(
+    conn: &Connection,
+    config: &ServerConfig,
+    address: &str,
+) -> Result<(), ApiError> {
+    let address_hash = hash_address(config.address_digest, address)?;
+    if let Some(user) =
+        UserRecord::find_by_address_hash(conn, config.address_digest, &address_hash)?
+    {
+        acl::grant_global_owner(conn, user.userid)?;
+    }
+    Ok(())
+}
+
+fn no_user(user_id: Uuid) -> ApiError {
+    ApiError::new(ErrorCode::NotFound, format!("no user {user_id}"))
+}
+
+/// The error for a session that accesses another user's resources.
+pub fn other_user() -> ApiError {
+    ApiError::new(
+        ErrorCode::PermissionDenied,
+        "session belongs to another user",
+    )
+}
+
+#[post("/users/new", data = "<req>")]
+fn new_user(
+    req: Json<NewUserRequest>,
+    db: &State<Database>,
+    config: &State<ServerConfig>,
+) -> ApiResult<Json<NewUserResponse>> {
+    let NewUserRequest {
+        user_address,
+        initial_auth,
+    } = req.into_inner();
+
+    if normalize_address(&user_address).is_empty() {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            "user address must not be empty",
+        ));
+    }
+
+    let address_hash = hash_address(config.address_digest, &user_address)?;
+
+    let record = UserRecord {
+        userid: Uuid::new_v4(),
+        address_digest_algorithm: config.address_digest,
+        address_hash,
+        key_pair_algorithm: initial_auth.auth_key_alg,
+        pubkey: initial_auth.pub_key.into_inner(),
+        kdf_base_digest_algorithm: initial_auth.kdf_base_digest_alg,
+        kdf_params: initial_auth.kdf_params,
+        priv_key_cipher: initial_auth.priv_key_cipher,
+        priv_key_iv: initial_auth.priv_key_iv.into_inner(),
+        sealed_priv_key: initial_auth.secured_private_key.into_inner(),
+        root_key_id: Uuid::new_v4(),
+        root_object_id: Uuid::new_v4(),
+    };
+
+    let conn = db.lock();
+    let tx = conn.unchecked_transaction()?;
+
+    let is_admin = match &config.admin {
+        Some(admin) => normalize_address(admin) == normalize_address(&user_address),
+        None => !tx.query_row("SELECT EXISTS (SELECT 1 FROM users)", [], |row| {
+            row.get::<_, bool>(0)
+        })?,
+    };
+
+    if UserRecord::find_by_address_hash(&tx, record.address_digest_algorithm, &record.address_hash)?
+        .is_some()
+    {
+        return Err(ApiError::new(
+            ErrorCode::Conflict,
+            "a user with this address already exists",
+        ));
+    }
+
+    record.insert(&tx)?;
+    if is_admin {
+        acl::grant_global_owner(&tx, record.userid)?;
+    }
+    tx.commit()?;
+
+    Ok(Json(NewUserResponse {
+        user_id: record.userid,
+    }))
+}
+
+#[get("/users/<user_id>/auth")]
+fn user_auth(user_id: Uuid, db: &State<Database>) -> ApiResult<Json<UserAuth>> {
+    let user = UserRecord::find_by_id(&db.lock(), user_id)?.ok_or_else(|| no_user(user_id))?;
+
+    Ok(Json(UserAuth {
+        kdf_base_digest_alg: user.kdf_base_digest_algorithm,
+        kdf_params: user.kdf_params,
+        priv_key_cipher: user.priv_key_cipher,
+        auth_key_alg: user.key_pair_algorithm,
+        pub_key: user.pubkey.into(),
+        priv_key_iv: user.priv_key_iv.into(),
+        secured_private_key: user.sealed_priv_key.into(),
+    }))
+}
+
+#[get("/users/<user_id>/root")]
+fn user_root(
+    user_id: Uuid,
+    session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<UserRootInfo>> {
+    let conn = db.lock();
+    if session.user_id != user_id {
+        acl::require(&*conn, session.user_id, user_id, &AclAction::ReadRootInfo)?;
+    }
+
+    let user = UserRecord::find_by_id(&conn, user_id)?.ok_or_else(|| no_user(user_id))?;
+
+    Ok(Json(UserRootInfo {
+        root_object: user.root_object_id,
+        root_key: user.root_key_id,
+    }))
+}
+
+#[get("/users/<user_id>/public-key")]
+fn user_public_key(
+    user_id: Uuid,
+    _session: Session,
+    db: &State<Database>,
+) -> ApiResult<Json<UserPublicKey>> {
+    let user = UserRecord::find_by_id(&db.lock(), user_id)?.ok_or_else(|| no_user(user_id))?;
+
+    Ok(Json(UserPublicKey {
+        pub_key: user.pubkey.into(),
+        pub_key_alg: user.key_pair_algorithm,
+    }))
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![new_user, user_auth, user_root, user_public_key]
+}
+
+#[cfg(test)]
+mod test {
+    use common::http::api::acl::{AclMode, AclRow};
+    use rocket::{
+        http::{ContentType, Status},
+        local::blocking::{Client, LocalResponse},
+    };
+
+    use super::*;
+    use crate::testing;
+
+    const ADMIN: &str = "admin@example.com";
+
+    fn auth() -> UserAuth {
+        UserAuth {
+            kdf_base_digest_alg: DigestAlgorithm::Sha256,
+            kdf_params: None,
+            priv_key_cipher: None,
+            auth_key_alg: AsymmetricCipherAlgorithm::Ec25519,
+            pub_key: vec![1; 32].into(),
+            priv_key_iv: vec![2; 12].into(),
+            secured_private_key: vec![3; 48].into(),
+        }
+    }
+
+    fn new_user<'c>(client: &'c Client, address: &str) -> LocalResponse<'c> {
+        client
+            .post("/users/new")
+            .header(ContentType::JSON)
+            .body(
+                json::to_string(&NewUserRequest {
+                    user_address: address.into(),
+                    initial_auth: auth(),
+                })
+                .unwrap(),
+            )
+            .dispatch()
+    }
+
+    fn register(client: &Client, address: &str) -> Uuid {
+        let response = new_user(client, address);
+        assert_eq!(response.status(), Status::Ok);
+        response.into_json::<NewUserResponse>().unwrap().user_id
+    }
+
+    fn is_global_owner(db: &Database, user: Uuid) -> bool {
+        acl::is_allowed(&*db.lock(), user, acl::SERVER_OBJECT, &AclAction::Owner).unwrap()
+    }
+
+    fn admin_config() -> ServerConfig {
+        ServerConfig {
+            admin: Some(ADMIN.into()),
+            ..testing::config()
+        }
+    }
+
+    #[test]
+    fn user_record_round_trip() {
+        let db = testing::database();
+        let conn = db.lock();
+        let record = UserRecord {
+            userid: Uuid::new_v4(),
+            address_digest_algorithm: DigestAlgorithm::Sha256,
+            address_hash: hash_address(DigestAlgorithm::Sha256, "user@example.com").unwrap(),
+            key_pair_algorithm: AsymmetricCipherAlgorithm::Ec25519,
+            pubkey: vec![1; 32],
+            kdf_base_digest_algorithm: DigestAlgorithm::Sha512,
+            kdf_params: Some(KdfParams {
+                version: common::http::api::auth::KDF_PARAMS_VERSION,
+                function: common::http::api::auth::KdfFunction::Pbkdf2 {
+                    iterations: 1000,
+                    salt: vec![4; 16].into(),
+                },
+            }),
+            priv_key_cipher: Some(SymmetricCipherAlgorithm::Aes256Gcm),
+            priv_key_iv: vec![2; 12],
+            sealed_priv_key: vec![3; 48],
+            root_key_id: Uuid::new_v4(),
+            root_object_id: Uuid::new_v4(),
+        };
+        let defaults = UserRecord {
+            userid: Uuid::new_v4(),
+            address_hash: hash_address(DigestAlgorithm::Sha256, "other@example.com").unwrap(),
+            kdf_params: None,
+            priv_key_cipher: None,
+            ..record.clone()
+        };
+        record.insert(&conn).unwrap();
+        defaults.insert(&conn).unwrap();
+
+        for record in [record, defaults] {
+            assert_eq!(
+                UserRecord::find_by_id(&conn, record.userid).unwrap(),
+                Some(record.clone())
+            );
+            assert_eq!(
+                UserRecord::find_by_address_hash(
+                    &conn,
+                    DigestAlgorithm::Sha256,
+                    &record.address_hash
+                )
+                .unwrap(),
+                Some(record.clone())
+            );
+            assert!(record.insert(&conn).is_err());
+        }
+        assert_eq!(UserRecord::find_by_id(&conn, Uuid::new_v4()).unwrap(), None);
+        let hash = hash_address(DigestAlgorithm::Sha256, "user@example.com").unwrap();
+        assert_eq!(
+            UserRecord::find_by_address_hash(&conn, DigestAlgorithm::Sha512, &hash).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn registration() {
+        let client = testing::client(testing::database(), routes());
+        let user = register(&client, "user@example.com");
+
+        let response = client.get(format!("/users/{user}/auth")).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_json::<UserAuth>().unwrap(), auth());
+        let record = UserRecord::find_by_address_hash(
+            &testing::served(&client).lock(),
+            DigestAlgorithm::Sha256,
+            &hash_address(DigestAlgorithm::Sha256, "user@example.com").unwrap(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(record.userid, user);
+        assert_ne!(record.root_key_id, record.root_object_id);
+    }
+
+    #[test]
+    fn registration_errors() {
+        let client = testing::client(testing::database(), routes());
+        register(&client, "user@example.com");
+
+        for address in ["user@example.com", " User@Example.COM\n"] {
+            assert_eq!(new_user(&client, address).status(), Status::Conflict);
+        }
+        for address in ["", " \t"] {
+            assert_eq!(new_user(&client, address).status(), Status::BadRequest);
+        }
+        let users: usize = testing::served(&client)
+            .lock()
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(users, 1);
+    }
+
+    #[test]
+    fn first_user_is_owner() {
+        let client = testing::client(testing::database(), routes());
+        let first = register(&client, "first@example.com");
+        let second = register(&client, "second@example.com");
+
+        let db = testing::served(&client);
+        assert!(is_global_owner(db, first));
+        assert!(!is_global_owner(db, second));
+    }
+
+    #[test]
+    fn configured_admin_is_owner() {
+        let client = testing::client_with(testing::database(), admin_config(), routes());
+        let first = register(&client, "first@example.com");
+        let admin = register(&client, "Admin@Example.com");
+
+        let db = testing::served(&client);
+        assert!(!is_global_owner(db, first));
+        assert!(is_global_owner(db, admin));
+    }
+
+    #[test]
+    fn bootstrap_admin_keeps_existing_rows() {
+        let config = admin_config();
+        let client = testing::client_with(testing::database(), admin_config(), routes());
+        let admin = register(&client, ADMIN);
+        let other = register(&client, "other@example.com");
+        let db = testing::served(&client);
+        let conn = db.lock();
+
+        acl::upsert_rows(
+            &conn,
+            acl::SERVER_OBJECT,
+            &[AclRow {
+                subject: admin,
+                action: AclAction::Owner,
+                mode: AclMode::Deny,
+            }],
+        )
+        .unwrap();
+        bootstrap_admin(&conn, &config, ADMIN).unwrap();
+        assert_eq!(
+            acl::resolve(&*conn, admin, acl::SERVER_OBJECT, &AclAction::Owner).unwrap(),
+            AclMode::Deny
+        );
+
+        let config = ServerConfig {
+            admin: Some("other@example.com".into()),
+            ..config
+        };
+        bootstrap_admin(&conn, &config, "other@example.com").unwrap();
+        assert!(acl::is_allowed(&*conn, other, acl::SERVER_OBJECT, &AclAction::Owner).unwrap());
+        bootstrap_admin(&conn, &config, "unknown@example.com").unwrap();
+    }
 }